@@ -0,0 +1,347 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::sync::OnceLock;
+
+/// Output casing applied to the final CHROM (`--chrom-case`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ChromCase {
+    /// Leave CHROM as produced by the ignore rules
+    #[default]
+    Keep,
+    /// ASCII-lowercase the whole CHROM, e.g. "chrX" -> "chrx"
+    Lower,
+    /// ASCII-uppercase the whole CHROM, e.g. "chr1" -> "CHR1"
+    Upper,
+}
+
+impl ChromCase {
+    /// Parse a `--chrom-case` value; anything unknown means `keep`
+    pub fn from_arg(value: Option<&String>) -> Self {
+        match value.map(|s| s.to_ascii_lowercase()).as_deref() {
+            Some("lower") => ChromCase::Lower,
+            Some("upper") => ChromCase::Upper,
+            _ => ChromCase::Keep,
+        }
+    }
+
+    pub fn apply(self, chrom: &str) -> String {
+        match self {
+            ChromCase::Keep => chrom.to_string(),
+            ChromCase::Lower => chrom.to_ascii_lowercase(),
+            ChromCase::Upper => chrom.to_ascii_uppercase(),
+        }
+    }
+
+    /// Apply the casing to the `ID=` of a `##contig=<...>` line; other lines are returned as-is.
+    pub fn apply_to_contig_line(self, line: &str) -> String {
+        if self == ChromCase::Keep || !line.starts_with("##contig=<") {
+            return line.to_string();
+        }
+        if let Some(id_start) = line.find("ID=") {
+            let from = id_start + 3;
+            let id_len = line[from..].find([',', '>']).unwrap_or(line.len() - from);
+            let id = &line[from..from + id_len];
+            return format!(
+                "{}{}{}",
+                &line[..from],
+                self.apply(id),
+                &line[from + id_len..]
+            );
+        }
+        line.to_string()
+    }
+
+    /// Apply the casing to the CHROM (first column) of a VCF body line
+    pub fn apply_to_body_line(self, line: String) -> String {
+        if self == ChromCase::Keep {
+            return line;
+        }
+        match line.split_once('\t') {
+            Some((chrom, rest)) => format!("{}\t{}", self.apply(chrom), rest),
+            None => self.apply(&line),
+        }
+    }
+}
+
+/// Characters that may not appear anywhere in a VCF CHROM / contig ID: whitespace and control
+/// characters break the tab-delimited layout, the rest are reserved by the VCF spec.
+fn is_illegal_chrom_char(c: char) -> bool {
+    c.is_whitespace()
+        || c.is_control()
+        || matches!(
+            c,
+            ',' | '<' | '>' | '[' | ']' | '{' | '}' | '(' | ')' | '"' | '\'' | '\\'
+        )
+}
+
+/// Characters that may not start a CHROM (`#` would also turn the record into a header line)
+fn is_illegal_chrom_start(c: char) -> bool {
+    matches!(c, '*' | '=' | '#')
+}
+
+/// Why `name` cannot be written as a VCF CHROM, or `None` when it is fine
+pub fn invalid_chrom_reason(name: &str) -> Option<&'static str> {
+    let first = name.chars().next()?;
+    if is_illegal_chrom_start(first) {
+        return Some("illegal leading character");
+    }
+    if name.contains(['\t', '\n', '\r']) {
+        return Some("tab/newline");
+    }
+    if name.chars().any(is_illegal_chrom_char) {
+        return Some("character illegal in CHROM");
+    }
+    None
+}
+
+/// Percent-encode the characters `invalid_chrom_reason` objects to (`--escape-special`),
+/// e.g. "sample 1#chr1" -> "sample%201#chr1", "a\tb" -> "a%09b".
+pub fn escape_chrom(name: &str) -> String {
+    let mut out = String::with_capacity(name.len());
+    for (i, c) in name.chars().enumerate() {
+        if is_illegal_chrom_char(c) || (i == 0 && is_illegal_chrom_start(c)) {
+            let mut buf = [0u8; 4];
+            for b in c.encode_utf8(&mut buf).bytes() {
+                out.push_str(&format!("%{b:02X}"));
+            }
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+/// Check a set of output CHROM names: with `escape` nothing is rejected, otherwise list every
+/// offending name (with the reason) in the returned error.
+pub fn check_chrom_names<'a, I>(names: I, escape: bool) -> Result<(), String>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    if escape {
+        return Ok(());
+    }
+    let mut bad: Vec<String> = names
+        .into_iter()
+        .filter_map(|n| invalid_chrom_reason(n).map(|r| format!("{n:?} ({r})")))
+        .collect();
+    if bad.is_empty() {
+        return Ok(());
+    }
+    bad.sort();
+    bad.dedup();
+    Err(format!(
+        "{} path name(s) cannot be written as VCF CHROM; rename them or use --escape-special: {}",
+        bad.len(),
+        bad.join(", ")
+    ))
+}
+
+static AUTOSOMES: OnceLock<u32> = OnceLock::new();
+
+/// Set the number of numbered chromosomes from `--autosomes`, once per process; setting a
+/// different count later is an error instead of being ignored
+pub fn set_autosomes(n: u32) -> Result<(), String> {
+    match AUTOSOMES.set(n) {
+        Ok(()) => Ok(()),
+        Err(_) if autosomes() == n => Ok(()),
+        Err(_) => Err(format!(
+            "--autosomes is already set to {} in this process; cannot change it to {n}",
+            autosomes()
+        )),
+    }
+}
+
+/// Numbered chromosomes accepted as standard by ignore levels 4/5 and ranked by `sort`:
+/// 1..=22 unless `--autosomes` says otherwise (e.g. 19 for mouse)
+pub fn autosomes() -> u32 {
+    AUTOSOMES.get().copied().unwrap_or(22)
+}
+
+/// True for a standard numbered chromosome, 1..=`autosomes()`
+pub fn is_std_autosome(n: u32) -> bool {
+    (1..=autosomes()).contains(&n)
+}
+
+static CHROM_MAP: OnceLock<HashMap<String, String>> = OnceLock::new();
+
+/// Load the `--chrom-map` table (`raw<TAB>canonical`; blank and `#` lines skipped) into the
+/// process-wide alias map consulted before the ignore levels. Only one map can be loaded per
+/// process: loading a different one later is an error (the same table again is accepted).
+/// Returns the number of aliases.
+pub fn load_chrom_map(path: &str) -> Result<usize, Box<dyn std::error::Error>> {
+    let mut map: HashMap<String, String> = HashMap::new();
+    for (i, line) in BufReader::new(File::open(path)?).lines().enumerate() {
+        let line = line?;
+        let t = line.trim_end_matches(['\n', '\r']);
+        if t.trim().is_empty() || t.starts_with('#') {
+            continue;
+        }
+        let mut it = t.split('\t');
+        let (raw, canonical) = match (it.next(), it.next()) {
+            (Some(r), Some(c)) if !r.trim().is_empty() && !c.trim().is_empty() => {
+                (r.trim(), c.trim())
+            }
+            _ => {
+                return Err(
+                    format!("{path}:{}: expected raw<TAB>canonical, got '{t}'", i + 1).into(),
+                );
+            }
+        };
+        match map.insert(raw.to_string(), canonical.to_string()) {
+            Some(prev) if prev != canonical => eprintln!(
+                "[warn] --chrom-map: {raw} listed twice ({prev}, {canonical}); using {canonical}"
+            ),
+            _ => {}
+        }
+    }
+    let n = map.len();
+    match CHROM_MAP.set(map) {
+        Ok(()) => Ok(n),
+        Err(map) if CHROM_MAP.get() == Some(&map) => Ok(n),
+        Err(_) => Err(format!(
+            "--chrom-map {path}: another alias map is already loaded in this process"
+        )
+        .into()),
+    }
+}
+
+/// Canonical CHROM for `raw` from `--chrom-map`, if any: the full name is tried first, then
+/// the contig part after the last `#` of a PanSN name (e.g. "GRCh38#0#CM000663.2").
+pub fn chrom_map_lookup(raw: &str) -> Option<&'static str> {
+    let map = CHROM_MAP.get()?;
+    map.get(raw)
+        .or_else(|| raw.rsplit_once('#').and_then(|(_, contig)| map.get(contig)))
+        .map(|s| s.as_str())
+}
+
+/// Case-insensitive search for the *last* "chr" and extract the token right after it, so every
+/// subcommand reads "chr1_scaffold_chr2" as chromosome 2 and "GRCh38.chr12" as 12:
+/// - the token is a run of digits (e.g. "12") or a single X/Y/M letter, uppercased
+/// - Returns (token, has_suffix_after_token, found_chr), e.g. "chr12_random" -> ("12", true, true),
+///   "CHR0X" -> ("0", true, true), "chrUn_KI270302v1" -> (None, true, true)
+pub fn extract_chr_token(raw: &str) -> (Option<String>, bool, bool) {
+    let lower = raw.to_ascii_lowercase();
+    let idx = match lower.rfind("chr") {
+        Some(i) => i,
+        None => return (None, false, false),
+    };
+    let tail = &raw[idx + 3..];
+    let digits = tail.bytes().take_while(u8::is_ascii_digit).count();
+    let token = if digits > 0 {
+        tail[..digits].to_string()
+    } else {
+        match tail.chars().next().map(|c| c.to_ascii_uppercase()) {
+            Some(c @ ('X' | 'Y' | 'M')) => c.to_string(),
+            _ => return (None, !tail.is_empty(), true),
+        }
+    };
+    let has_suffix = tail.len() > token.len();
+    (Some(token), has_suffix, true)
+}
+
+/// Standard human chromosome token: 1..=`autosomes()`, X, Y or M (case-insensitive)
+pub fn is_std_human_chr_token(tok: &str) -> bool {
+    match tok.to_ascii_uppercase().as_str() {
+        "X" | "Y" | "M" => true,
+        n => n.parse::<u32>().map(is_std_autosome).unwrap_or(false),
+    }
+}
+
+/// Apply ignore rules 0..=5 to a path/contig name (`--chrom-map` aliases win over every level):
+/// 0: Keep as-is (no checks)
+/// 1: Keep only if string (case-insensitively) contains "chr"
+/// 2: As 1, and drop if token after "chr" is not [digits|X|Y|M]
+/// 3: As 2, and drop if there is any suffix after the token (e.g., "chr12_random")
+/// 4: Keep only standard set {1..N, X, Y, M}, N = `--autosomes` (default 22).
+///    Normalize to "chr{TOKEN}" (uppercase token), stripping any extra context,
+///    e.g. "GRCh38.chr12_random" -> "chr12".
+/// 5: Same as 4, but output only "{TOKEN}" without "chr" prefix, e.g. "12", "X", "Y", "M".
+pub fn apply_ignore_rules(raw: &str, level: u8) -> Option<String> {
+    if let Some(canonical) = chrom_map_lookup(raw) {
+        return Some(canonical.to_string());
+    }
+    match level {
+        0 => Some(raw.to_string()),
+        1 => raw
+            .to_ascii_lowercase()
+            .contains("chr")
+            .then(|| raw.to_string()),
+        2 => {
+            // Any token passes here, standard range or not
+            let (tok_opt, _has_suffix, _found_chr) = extract_chr_token(raw);
+            tok_opt.map(|_| raw.to_string())
+        }
+        3 => match extract_chr_token(raw) {
+            (Some(_), false, _) => Some(raw.to_string()),
+            _ => None,
+        },
+        4 | 5 => {
+            let (tok_opt, _has_suffix, _found_chr) = extract_chr_token(raw);
+            let t = tok_opt.filter(|t| is_std_human_chr_token(t))?;
+            if level == 4 {
+                Some(format!("chr{t}"))
+            } else {
+                Some(t)
+            }
+        }
+        _ => Some(raw.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tab_and_space_in_names_are_reported_or_escaped() {
+        assert_eq!(invalid_chrom_reason("sample\t1#chr1"), Some("tab/newline"));
+        assert_eq!(
+            invalid_chrom_reason("sample 1#chr1"),
+            Some("character illegal in CHROM")
+        );
+        assert_eq!(invalid_chrom_reason("sample1#chr1"), None);
+
+        let err = check_chrom_names(["sample\t1#chr1", "chr2"], false).unwrap_err();
+        assert!(err.contains("\"sample\\t1#chr1\" (tab/newline)"), "{err}");
+        assert!(check_chrom_names(["sample\t1#chr1"], true).is_ok());
+        assert_eq!(escape_chrom("sample\t1#chr1"), "sample%091#chr1");
+        assert_eq!(escape_chrom("sample 1#chr1"), "sample%201#chr1");
+    }
+
+    #[test]
+    fn ignore_rules_for_prefixed_and_padded_names() {
+        let levels = |raw: &str| -> Vec<Option<String>> {
+            (0..=5).map(|l| apply_ignore_rules(raw, l)).collect()
+        };
+        let some = |s: &str| Some(s.to_string());
+
+        let raw = "GRCh38.chr12_random";
+        assert_eq!(extract_chr_token(raw), (some("12"), true, true));
+        assert_eq!(
+            levels(raw),
+            [
+                some(raw),
+                some(raw),
+                some(raw),
+                None,
+                some("chr12"),
+                some("12")
+            ]
+        );
+
+        // The token is the digit run "0", not X, and chromosome 0 is not standard
+        assert_eq!(extract_chr_token("CHR0X"), (some("0"), true, true));
+        assert_eq!(
+            levels("CHR0X"),
+            [
+                some("CHR0X"),
+                some("CHR0X"),
+                some("CHR0X"),
+                None,
+                None,
+                None
+            ]
+        );
+    }
+}
@@ -1,144 +1,667 @@
+use crate::chrom::{apply_ignore_rules, extract_chr_token};
+use crate::line_sep::eol;
+use crate::progress::Progress;
 use clap::ArgMatches;
+use flate2::read::MultiGzDecoder;
 use gfa_reader::Gfa;
 use rayon::prelude::*;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::collections::hash_map::DefaultHasher;
 use std::fs::File;
-use std::io::{BufWriter, Write};
+use std::hash::{Hash, Hasher};
+use std::io::{BufReader, BufWriter, Write};
 use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
 
-/// Extracts the token that follows a case-insensitive "chr" occurrence.
-/// Returns (token_opt, has_suffix_after_token, found_chr_anywhere)
-/// - token is uppercased (e.g., "x" -> "X")
-/// - token is either a run of digits (e.g., "12") or a single letter in {X,Y,M}
-fn extract_chr_token(raw: &str) -> (Option<String>, bool, bool) {
-    let lower = raw.to_ascii_lowercase();
-    if let Some(idx) = lower.find("chr") {
-        let after = &raw[idx + 3..];
-        let mut chars = after.chars();
-        if let Some(first) = chars.next() {
-            // Accept a run of digits OR a single X/Y/M letter
-            if first.is_ascii_digit() {
-                let mut token = String::new();
-                token.push(first);
-                for c in chars.clone() {
-                    if c.is_ascii_digit() {
-                        token.push(c);
-                    } else {
-                        break;
-                    }
-                }
-                let token_len = token.len();
-                let has_suffix = after[token_len + 0..].chars().next().is_some();
-                return (Some(token), has_suffix, true);
+/// Why `apply_ignore_rules` rejects a path name, for `--report-ignore-drops`
+const IGNORE_DROP_REASONS: [&str; 4] = ["no_chr", "bad_token", "suffix", "non_standard"];
+
+/// Index into `IGNORE_DROP_REASONS` for a name rejected at `level`; mirrors `apply_ignore_rules`.
+fn ignore_drop_reason(raw: &str, level: u8) -> usize {
+    let (tok_opt, has_suffix, found_chr) = extract_chr_token(raw);
+    if !found_chr {
+        return 0;
+    }
+    match tok_opt {
+        None => 1,
+        Some(_) if level == 3 && has_suffix => 2,
+        Some(_) => 3,
+    }
+}
+
+/// `--samples` / `--samples-regex`: which PanSN paths (`sample#hap#contig`) to extract
+enum PathSelector {
+    /// `--samples`: tokens like `HG002` or `HG002#1`, compared with the leading `#` fields
+    Prefixes(Vec<Vec<String>>),
+    /// `--samples-regex`: matched anywhere in the full path name
+    Regex(regex::Regex),
+}
+
+impl PathSelector {
+    fn matches(&self, name: &str) -> bool {
+        match self {
+            PathSelector::Prefixes(tokens) => {
+                let fields: Vec<&str> = name.split('#').collect();
+                tokens
+                    .iter()
+                    .any(|t| t.len() <= fields.len() && t.iter().zip(&fields).all(|(a, b)| a == b))
+            }
+            PathSelector::Regex(re) => re.is_match(name),
+        }
+    }
+}
+
+/// One kept path for the `--summary` table: (GFA path index, GFA name, output name, nodes, bp)
+type PathSummary = (usize, String, String, usize, u64);
+
+/// First-seen (length, sequence hash) per node, shared across the parallel path walk
+type NodeSignatures = Mutex<HashMap<u32, (usize, u64)>>;
+
+/// One inconsistent occurrence of a node: (node, first_len, first_hash, len, hash, path)
+type DuplicateNode = (u32, usize, u64, usize, u64, String);
+
+/// Row layout of the output (`--format`, `--with-strand`, `--revcomp`, `--with-gc`)
+#[derive(Debug, Clone, Copy)]
+struct RowFormat {
+    bed: bool,
+    with_strand: bool,
+    revcomp: bool,
+    with_gc: bool,
+}
+
+impl RowFormat {
+    /// TSV column header (BED has none)
+    fn write_header<W: Write>(&self, w: &mut W) -> std::io::Result<()> {
+        if !self.bed {
+            // `strand` is appended so the other columns keep their positions
+            let strand_col = if self.with_strand { "\tstrand" } else { "" };
+            let gc_cols = if self.with_gc {
+                "\tgc_frac\tn_count"
             } else {
-                let up = first.to_ascii_uppercase();
-                if up == 'X' || up == 'Y' || up == 'M' {
-                    let token = up.to_string();
-                    let has_suffix = after[1..].chars().next().is_some();
-                    return (Some(token), has_suffix, true);
-                }
+                ""
+            };
+            write!(
+                w,
+                "node\tstart\tend\tseq\tlength\tpath{}{}{}",
+                strand_col,
+                gc_cols,
+                eol()
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Append the row of one node step to `buf`
+    #[allow(clippy::too_many_arguments)]
+    fn push_row(
+        &self,
+        buf: &mut String,
+        out_name: &str,
+        node: u32,
+        start: usize,
+        end: usize,
+        seq: &str,
+        forward: bool,
+    ) {
+        use std::fmt::Write as _;
+        let seq_out: Cow<str> = if self.revcomp && !forward {
+            Cow::Owned(reverse_complement(seq))
+        } else {
+            Cow::Borrowed(seq)
+        };
+        let strand = match (self.with_strand, forward) {
+            (false, _) => "",
+            (true, true) => "\t+",
+            (true, false) => "\t-",
+        };
+        // Starts grow along the path, so BED rows come out sorted by start within each path
+        let _ = if self.bed {
+            // BED6 (score 0) when the strand is requested, BED4 otherwise
+            let score = if self.with_strand { "\t0" } else { "" };
+            write!(
+                buf,
+                "{}\t{}\t{}\t{}{}{}{}",
+                out_name,
+                start,
+                end,
+                node,
+                score,
+                strand,
+                eol()
+            )
+        } else {
+            let _ = write!(
+                buf,
+                "{}\t{}\t{}\t{}\t{}\t{}{}",
+                node,
+                start,
+                end,
+                seq_out,
+                seq.len(),
+                out_name,
+                strand
+            );
+            if self.with_gc {
+                let (gc_frac, n_count) = gc_content(seq);
+                let _ = write!(buf, "\t{gc_frac:.4}\t{n_count}");
             }
+            write!(buf, "{}", eol())
+        };
+    }
+}
+
+/// `--with-gc`: G/C fraction among the non-N bases (0 when there are none) and the N count
+fn gc_content(seq: &str) -> (f64, usize) {
+    let (mut gc, mut n) = (0usize, 0usize);
+    for b in seq.bytes() {
+        match b {
+            b'G' | b'g' | b'C' | b'c' => gc += 1,
+            b'N' | b'n' => n += 1,
+            _ => {}
         }
-        return (None, false, true);
     }
-    (None, false, false)
+    let called = seq.len() - n;
+    let frac = if called == 0 {
+        0.0
+    } else {
+        gc as f64 / called as f64
+    };
+    (frac, n)
+}
+
+/// Counters of one extraction, shared by the in-memory and `--low-mem` paths
+#[derive(Default)]
+struct ExtractCounters {
+    paths_kept: AtomicU64,
+    paths_dropped: AtomicU64,
+    paths_unselected: AtomicU64,
+    rows_written: AtomicU64,
+    nodes_filtered: AtomicU64,
+    drops_by_reason: [AtomicU64; 4],
+    summaries: Mutex<Vec<PathSummary>>,
+    /// First coordinate problem of each affected path: (GFA path index, GFA name, message)
+    coord_problems: Mutex<Vec<(usize, String, String)>>,
+}
+
+/// Check of one path's coordinates against data they are not computed from (`--strict-coords`
+/// turns a hit into an error): every node needs a sequence, and a W line's `SeqStart`/`SeqEnd`
+/// must span as many bases as its nodes. Only the first problem of a path is kept.
+#[derive(Default)]
+struct CoordCheck {
+    /// Bases of all nodes seen, including those dropped by `--min/--max-node-length`
+    bases: usize,
+    problem: Option<String>,
 }
 
-/// Standard human chromosome token check: {1..22, X, Y, M}
-fn is_std_human_chr_token(tok: &str) -> bool {
-    let t = tok.to_ascii_uppercase();
-    if t == "X" || t == "Y" || t == "M" {
-        return true;
+impl CoordCheck {
+    /// `node` of `len` bases was visited; 0 means its segment is missing or empty
+    fn node(&mut self, node: u32, len: usize) {
+        if len == 0 && self.problem.is_none() {
+            self.problem = Some(format!(
+                "node {node} has no sequence (missing or empty segment) and overlaps the next node"
+            ));
+        }
+        self.bases += len;
     }
-    if t.chars().all(|c| c.is_ascii_digit()) {
-        if let Ok(n) = t.parse::<u32>() {
-            return (1..=22).contains(&n);
+
+    /// All nodes of a W line were visited; compare with its `SeqStart`/`SeqEnd` (`*` is skipped)
+    fn walk_span(&mut self, seq_start: &str, seq_end: &str) {
+        let (from, to) = match (seq_start.parse::<usize>(), seq_end.parse::<usize>()) {
+            (Ok(f), Ok(t)) => (f, t),
+            _ => return,
+        };
+        if self.problem.is_none() && to.checked_sub(from) != Some(self.bases) {
+            self.problem = Some(format!(
+                "W line spans {from}-{to} but its nodes add up to {} bp",
+                self.bases
+            ));
+        }
+    }
+
+    fn report(self, counters: &ExtractCounters, path_idx: usize, name: &str) {
+        if let Some(msg) = self.problem {
+            counters
+                .coord_problems
+                .lock()
+                .unwrap()
+                .push((path_idx, name.to_string(), msg));
         }
     }
-    false
 }
 
-/// Apply ignore rules 0..=5 (see doc for details)
-fn apply_ignore_rules(raw: &str, level: u8) -> Option<String> {
-    match level {
-        0 => Some(raw.to_string()),
-        1 => {
-            let contains_chr = raw.to_ascii_lowercase().contains("chr");
-            if contains_chr {
-                Some(raw.to_string())
-            } else {
-                None
-            }
+/// Path-level settings shared by the in-memory and `--low-mem` paths
+struct PathRules<'a> {
+    ignore_level: u8,
+    escape_special: bool,
+    report_ignore_drops: bool,
+    selector: Option<&'a PathSelector>,
+    /// `--min-node-length..=--max-node-length`
+    len_range: std::ops::RangeInclusive<usize>,
+    reindex: bool,
+}
+
+impl PathRules<'_> {
+    fn selected(&self, name: &str) -> bool {
+        self.selector.is_none_or(|s| s.matches(name))
+    }
+
+    /// Output name of a GFA path, or None (counted) when `--samples` or `--ignore` drops it
+    fn output_name(&self, name: &str, counters: &ExtractCounters) -> Option<String> {
+        if !self.selected(name) {
+            counters.paths_unselected.fetch_add(1, Ordering::Relaxed);
+            return None;
         }
-        2 => {
-            let (tok_opt, _has_suffix, found_chr) = extract_chr_token(raw);
-            if !found_chr {
-                return None;
+        match apply_ignore_rules(name, self.ignore_level) {
+            Some(s) => {
+                counters.paths_kept.fetch_add(1, Ordering::Relaxed);
+                Some(if self.escape_special {
+                    crate::chrom::escape_chrom(&s)
+                } else {
+                    s
+                })
             }
-            match tok_opt {
-                Some(t) => {
-                    let ok = matches!(t.as_str(), "X" | "Y" | "M")
-                        || t.chars().all(|c| c.is_ascii_digit());
-                    if ok { Some(raw.to_string()) } else { None }
+            None => {
+                counters.paths_dropped.fetch_add(1, Ordering::Relaxed);
+                if self.report_ignore_drops {
+                    counters.drops_by_reason[ignore_drop_reason(name, self.ignore_level)]
+                        .fetch_add(1, Ordering::Relaxed);
                 }
-                none => none,
+                None
             }
         }
-        3 => {
-            let (tok_opt, has_suffix, found_chr) = extract_chr_token(raw);
-            if !found_chr {
-                return None;
-            }
-            if tok_opt.is_none() {
-                return None;
-            }
-            if has_suffix {
-                return None;
+    }
+
+    /// Refuse (or, with `--escape-special`, announce escaping of) output names that are not valid
+    /// VCF CHROM values; run before anything is written
+    fn check_names<'n, I>(&self, names: I) -> Result<(), String>
+    where
+        I: IntoIterator<Item = &'n str>,
+    {
+        let kept_names: Vec<String> = names
+            .into_iter()
+            .filter(|n| self.selected(n))
+            .filter_map(|n| apply_ignore_rules(n, self.ignore_level))
+            .collect();
+        crate::chrom::check_chrom_names(
+            kept_names.iter().map(String::as_str),
+            self.escape_special,
+        )?;
+        if self.escape_special {
+            let escaped = kept_names
+                .iter()
+                .filter(|n| crate::chrom::invalid_chrom_reason(n).is_some())
+                .count();
+            if escaped > 0 {
+                eprintln!(
+                    "[warn] --escape-special: escaped {escaped} path name(s) with special characters"
+                );
             }
-            Some(raw.to_string())
         }
-        4 => {
-            let (tok_opt, _has_suffix, found_chr) = extract_chr_token(raw);
-            if !found_chr {
-                return None;
+        Ok(())
+    }
+}
+
+/// Reverse complement of a node sequence for `--revcomp` (case kept, IUPAC codes paired,
+/// anything unknown left as-is)
+fn reverse_complement(seq: &str) -> String {
+    seq.chars()
+        .rev()
+        .map(|c| match c {
+            'A' => 'T',
+            'T' => 'A',
+            'C' => 'G',
+            'G' => 'C',
+            'a' => 't',
+            't' => 'a',
+            'c' => 'g',
+            'g' => 'c',
+            'R' => 'Y',
+            'Y' => 'R',
+            'K' => 'M',
+            'M' => 'K',
+            'B' => 'V',
+            'V' => 'B',
+            'D' => 'H',
+            'H' => 'D',
+            'r' => 'y',
+            'y' => 'r',
+            'k' => 'm',
+            'm' => 'k',
+            'b' => 'v',
+            'v' => 'b',
+            'd' => 'h',
+            'h' => 'd',
+            other => other,
+        })
+        .collect()
+}
+
+fn seq_hash(seq: &str) -> u64 {
+    let mut h = DefaultHasher::new();
+    seq.hash(&mut h);
+    h.finish()
+}
+
+/// Record the (length, sequence hash) of a node occurrence. Returns the first-seen signature
+/// when it differs from this occurrence, i.e. the same node id carries incompatible sequences
+/// (impossible in a valid GFA).
+fn check_node_consistency(seen: &NodeSignatures, node: u32, seq: &str) -> Option<(usize, u64)> {
+    let sig = (seq.len(), seq_hash(seq));
+    let mut guard = seen.lock().unwrap();
+    match guard.get(&node) {
+        Some(prev) if *prev != sig => Some(*prev),
+        Some(_) => None,
+        None => {
+            guard.insert(node, sig);
+            None
+        }
+    }
+}
+
+/// Stream-decompress a gzipped GFA into `tmp_path` so it can be handed to
+/// `Gfa::parse_gfa_file_multi`, which only accepts an uncompressed path.
+fn decompress_gfa_to_tmp(gz_path: &str, tmp_path: &str) -> std::io::Result<()> {
+    let mut reader = BufReader::new(MultiGzDecoder::new(File::open(gz_path)?));
+    let mut writer = BufWriter::new(File::create(tmp_path)?);
+    std::io::copy(&mut reader, &mut writer)?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Optional non-negative length argument (`--min-node-length` / `--max-node-length`)
+fn parse_len_arg(matches: &ArgMatches, name: &str) -> Result<Option<usize>, String> {
+    match matches.get_one::<String>(name) {
+        Some(s) => s
+            .trim()
+            .parse::<usize>()
+            .map(Some)
+            .map_err(|_| format!("--{name} expects a length in bp, got '{s}'")),
+        None => Ok(None),
+    }
+}
+
+/// Segment id of a P/W step; the in-memory parser also requires numeric ids
+fn parse_node_id(raw: &str, line_no: u64) -> Result<u32, String> {
+    raw.parse::<u32>()
+        .map_err(|_| format!("line {line_no}: segment id '{raw}' is not numeric"))
+}
+
+/// Steps of a P line (`1+,2-`) or a W line (`>1<2`) as (node, forward)
+fn parse_steps(kind: &str, raw: &str, line_no: u64) -> Result<Vec<(u32, bool)>, String> {
+    if kind == "P" {
+        raw.split(',')
+            .filter(|s| !s.is_empty())
+            .map(|s| {
+                let (id, forward) = match s.strip_suffix('-') {
+                    Some(id) => (id, false),
+                    None => (s.strip_suffix('+').unwrap_or(s), true),
+                };
+                Ok((parse_node_id(id, line_no)?, forward))
+            })
+            .collect()
+    } else {
+        let mut steps = Vec::new();
+        let mut rest = raw;
+        while let Some(c) = rest.chars().next() {
+            let body = &rest[1..];
+            let end = body.find(['>', '<']).unwrap_or(body.len());
+            steps.push((parse_node_id(&body[..end], line_no)?, c != '<'));
+            rest = &body[end..];
+        }
+        Ok(steps)
+    }
+}
+
+/// Name of a P line, or the name `walk_to_path("#")` gives a W line
+/// (`sample#hap#seqid:start-end`), so both modes name converted walks alike
+fn path_line_name(fields: &[&str]) -> Option<String> {
+    match fields {
+        ["P", name, _, ..] => Some(name.to_string()),
+        ["W", sample, hap, seqid, start, end, _, ..] => {
+            Some(format!("{sample}#{hap}#{seqid}:{start}-{end}"))
+        }
+        _ => None,
+    }
+}
+
+/// In-memory mode: `walk_to_path` does not keep the W coordinates, so read `SeqStart`/`SeqEnd`
+/// from the raw W lines and compare them with the summed segment lengths (`CoordCheck::walk_span`
+/// as in `--low-mem`). W lines with a missing segment are left to the per-path check, which
+/// reports that instead. `first_idx` is the GFA path index of the first W line (after the P lines).
+fn check_walk_spans(
+    gfa_file: &str,
+    seg_len: impl Fn(u32) -> usize,
+    rules: &PathRules,
+    first_idx: usize,
+    counters: &ExtractCounters,
+) -> Result<(), Box<dyn std::error::Error>> {
+    use std::io::BufRead;
+
+    let reader = crate::io_utils::open_input(gfa_file)?;
+    let mut w_idx = 0usize;
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        if !line.starts_with("W\t") {
+            continue;
+        }
+        let fields: Vec<&str> = line.trim_end_matches('\r').split('\t').collect();
+        let name = match path_line_name(&fields) {
+            Some(n) => n,
+            None => continue,
+        };
+        let path_idx = first_idx + w_idx;
+        w_idx += 1;
+        if !rules.selected(&name) || apply_ignore_rules(&name, rules.ignore_level).is_none() {
+            continue;
+        }
+        let lens: Vec<usize> = parse_steps("W", fields[6], i as u64 + 1)?
+            .iter()
+            .map(|&(node, _)| seg_len(node))
+            .collect();
+        if lens.contains(&0) {
+            continue;
+        }
+        let mut coords = CoordCheck {
+            bases: lens.iter().sum(),
+            problem: None,
+        };
+        coords.walk_span(fields[4], fields[5]);
+        coords.report(counters, path_idx, &name);
+    }
+    Ok(())
+}
+
+/// `--low-mem`: stream the GFA twice instead of loading the whole graph.
+///
+/// Pass 1 spools the segment sequences to `{output}.seq.tmp` and keeps only an
+/// offset/length index per node; pass 2 walks the P/W lines in file order and reads each
+/// node back from the spool. Single-threaded, output is always in GFA path order.
+/// Returns the number of P/W paths seen.
+fn extract_low_mem(
+    gfa_file: &str,
+    output_file: &str,
+    rules: &PathRules,
+    row_fmt: RowFormat,
+    counters: &ExtractCounters,
+) -> Result<usize, Box<dyn std::error::Error>> {
+    use std::io::{BufRead, Read, Seek, SeekFrom};
+
+    let spool_path = format!("{output_file}.seq.tmp");
+    log_info!("[info] Low-memory mode: spooling segment sequences to {spool_path}");
+    let mut index: HashMap<u32, (u64, u32)> = HashMap::new();
+    let mut names: Vec<String> = Vec::new();
+    let mut dup_segments: usize = 0;
+    let mut first_dup: u32 = 0;
+    {
+        let mut spool = BufWriter::new(File::create(&spool_path)?);
+        let mut offset: u64 = 0;
+        let mut line = String::new();
+        let mut line_no: u64 = 0;
+        let mut reader = crate::io_utils::open_input(gfa_file)?;
+        loop {
+            line.clear();
+            if reader.read_line(&mut line)? == 0 {
+                break;
             }
-            let t = tok_opt?;
-            if !is_std_human_chr_token(&t) {
-                return None;
+            line_no += 1;
+            let trimmed = line.trim_end_matches(['\n', '\r']);
+            match trimmed.as_bytes().first() {
+                Some(b'S') => {
+                    let mut it = trimmed.split('\t');
+                    let (id, seq) = match (it.nth(1), it.next()) {
+                        (Some(id), Some(seq)) => (id, seq),
+                        _ => return Err(format!("line {line_no}: malformed S line").into()),
+                    };
+                    let seq = if seq == "*" { "" } else { seq };
+                    let node = parse_node_id(id, line_no)?;
+                    if index.insert(node, (offset, seq.len() as u32)).is_some() {
+                        if dup_segments == 0 {
+                            first_dup = node;
+                        }
+                        dup_segments += 1;
+                    }
+                    spool.write_all(seq.as_bytes())?;
+                    offset += seq.len() as u64;
+                }
+                Some(b'P' | b'W') => {
+                    let fields: Vec<&str> = trimmed.split('\t').collect();
+                    if let Some(name) = path_line_name(&fields) {
+                        names.push(name);
+                    }
+                }
+                _ => {}
             }
-            Some(format!("chr{}", t.to_ascii_uppercase()))
         }
-        5 => {
-            let (tok_opt, _has_suffix, found_chr) = extract_chr_token(raw);
-            if !found_chr {
-                return None;
-            }
-            let t = tok_opt?;
-            if !is_std_human_chr_token(&t) {
-                return None;
+        spool.flush()?;
+    }
+    if dup_segments > 0 {
+        eprintln!(
+            "[warn] {dup_segments} duplicate S line(s) (first: segment {first_dup}); the last sequence of each id is used"
+        );
+    }
+    log_info!(
+        "[info] Indexed {} segments and {} paths",
+        index.len(),
+        names.len()
+    );
+    rules.check_names(names.iter().map(String::as_str))?;
+    drop(names);
+
+    let mut out = BufWriter::new(File::create(output_file)?);
+    row_fmt.write_header(&mut out)?;
+    let mut spool = File::open(&spool_path)?;
+    let mut seq_buf: Vec<u8> = Vec::new();
+    let mut row_buf = String::new();
+    let mut paths_total: usize = 0;
+    let mut progress = Progress::new("extract");
+    let mut line = String::new();
+    let mut line_no: u64 = 0;
+    let mut reader = crate::io_utils::open_input(gfa_file)?;
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            break;
+        }
+        line_no += 1;
+        progress.tick(line.len());
+        if !(line.starts_with("P\t") || line.starts_with("W\t")) {
+            continue;
+        }
+        let fields: Vec<&str> = line.trim_end_matches(['\n', '\r']).split('\t').collect();
+        let name = match path_line_name(&fields) {
+            Some(n) => n,
+            None => continue,
+        };
+        let path_idx = paths_total;
+        paths_total += 1;
+        let out_name = match rules.output_name(&name, counters) {
+            Some(n) => n,
+            None => continue,
+        };
+        let walk = if fields[0] == "P" {
+            fields[2]
+        } else {
+            fields[6]
+        };
+        let steps = parse_steps(fields[0], walk, line_no)?;
+        let mut rows: u64 = 0;
+        let mut start = 0usize;
+        let mut coords = CoordCheck::default();
+        for &(node, forward) in &steps {
+            let (offset, len) = match index.get(&node) {
+                Some(&entry) => entry,
+                None => {
+                    return Err(
+                        format!("line {line_no}: path {name} uses unknown segment {node}").into(),
+                    );
+                }
+            };
+            let len = len as usize;
+            coords.node(node, len);
+            if !rules.len_range.contains(&len) {
+                counters.nodes_filtered.fetch_add(1, Ordering::Relaxed);
+                if !rules.reindex {
+                    start += len;
+                }
+                continue;
             }
-            Some(t.to_ascii_uppercase())
+            seq_buf.resize(len, 0);
+            spool.seek(SeekFrom::Start(offset))?;
+            spool.read_exact(&mut seq_buf)?;
+            let seq = std::str::from_utf8(&seq_buf)?;
+            rows += 1;
+            let end = start + len;
+            row_buf.clear();
+            row_fmt.push_row(&mut row_buf, &out_name, node, start, end, seq, forward);
+            out.write_all(row_buf.as_bytes())?;
+            start = end;
+        }
+        if fields[0] == "W" {
+            coords.walk_span(fields[4], fields[5]);
         }
-        _ => Some(raw.to_string()),
+        coords.report(counters, path_idx, &name);
+        counters.rows_written.fetch_add(rows, Ordering::Relaxed);
+        counters.summaries.lock().unwrap().push((
+            path_idx,
+            name,
+            out_name,
+            steps.len(),
+            start as u64,
+        ));
     }
+    progress.finish();
+    out.flush()?;
+    let _ = std::fs::remove_file(&spool_path);
+    Ok(paths_total)
 }
 
 /// Extract paths and node coordinates from GFA
-/// Writes a TSV with columns: node, start, end, seq, length, path (supports P & W by converting W to paths).
+/// Writes a TSV with columns: node, start, end, seq, length, path (supports P & W by converting W to paths),
+/// or with `--format bed` a headerless BED: path, start, end, node (0-based, half-open).
 pub fn extract_main(matches: &ArgMatches) -> Result<(), Box<dyn std::error::Error>> {
     let gfa_file = matches
         .get_one::<String>("gfa")
         .expect("--gfa is required")
         .to_string();
+    let bed = matches
+        .get_one::<String>("format")
+        .is_some_and(|f| f.eq_ignore_ascii_case("bed"));
 
-    // Default output: same directory as the input GFA, file name "reference.tsv"
+    // Default output: same directory as the input GFA, file name "reference.tsv" (or "reference.bed")
     let output_file: String = matches
         .get_one::<String>("output")
         .cloned()
         .unwrap_or_else(|| {
             let p = Path::new(&gfa_file);
             let dir = p.parent().unwrap_or_else(|| Path::new("."));
-            dir.join("reference.tsv").to_string_lossy().into_owned()
+            let name = if bed {
+                "reference.bed"
+            } else {
+                "reference.tsv"
+            };
+            dir.join(name).to_string_lossy().into_owned()
         });
 
     let output_file_clone = output_file.clone();
@@ -157,10 +680,10 @@ pub fn extract_main(matches: &ArgMatches) -> Result<(), Box<dyn std::error::Erro
         .and_then(|s| s.parse::<u8>().ok())
         .unwrap_or(0);
 
-    println!("[info] [extract] Running with arguments:");
-    println!("    --gfa     : {}", gfa_file);
-    println!("    --output  : {}", output_file);
-    println!(
+    log_info!("[info] [extract] Running with arguments:");
+    log_info!("    --gfa     : {}", gfa_file);
+    log_info!("    --output  : {}", output_file);
+    log_info!(
         "    --threads : {}",
         if threads.is_empty() {
             "1 (default)"
@@ -168,63 +691,377 @@ pub fn extract_main(matches: &ArgMatches) -> Result<(), Box<dyn std::error::Erro
             threads.as_str()
         }
     );
-    println!("    --ignore  : {}", ignore_level);
+    log_info!("    --ignore  : {}", ignore_level);
+    if let Some(p) = matches.get_one::<String>("chrom-map") {
+        let n = crate::chrom::load_chrom_map(p)?;
+        log_info!("    --chrom-map : {p} ({n} aliases)");
+    }
+    let dup_report = matches.get_one::<String>("report-duplicate-nodes").cloned();
+    log_info!(
+        "    --report-duplicate-nodes : {}",
+        dup_report.as_deref().unwrap_or("None")
+    );
+    let report_ignore_drops = matches.get_flag("report-ignore-drops");
+    log_info!("    --report-ignore-drops : {}", report_ignore_drops);
+    let escape_special = matches.get_flag("escape-special");
+    let with_strand = matches.get_flag("with-strand");
+    let with_gc = matches.get_flag("with-gc");
+    let revcomp = matches.get_flag("revcomp");
+    log_info!("    --format : {}", if bed { "bed" } else { "tsv" });
+    let unordered = matches.get_flag("unordered");
+    log_info!("    --unordered : {}", unordered);
+    let low_mem = matches.get_flag("low-mem");
+    log_info!("    --low-mem : {}", low_mem);
+    log_info!("    --with-strand : {}", with_strand);
+    log_info!("    --with-gc : {}", with_gc);
+    let strict_coords = matches.get_flag("strict-coords");
+    log_info!("    --strict-coords : {}", strict_coords);
+    if with_gc && bed {
+        return Err("--with-gc adds TSV columns and cannot be used with --format bed".into());
+    }
+    log_info!("    --revcomp : {}", revcomp);
+    log_info!("    --escape-special : {}", escape_special);
+    let selector = match (
+        matches.get_one::<String>("samples"),
+        matches.get_one::<String>("samples-regex"),
+    ) {
+        (Some(list), _) => {
+            log_info!("    --samples : {list}");
+            let tokens: Vec<Vec<String>> = list
+                .split(',')
+                .map(str::trim)
+                .filter(|t| !t.is_empty())
+                .map(|t| t.split('#').map(str::to_string).collect())
+                .collect();
+            if tokens.is_empty() {
+                return Err("--samples needs at least one sample or sample#haplotype".into());
+            }
+            Some(PathSelector::Prefixes(tokens))
+        }
+        (None, Some(pat)) => {
+            log_info!("    --samples-regex : {pat}");
+            let re = regex::Regex::new(pat).map_err(|e| format!("--samples-regex '{pat}': {e}"))?;
+            Some(PathSelector::Regex(re))
+        }
+        (None, None) => None,
+    };
+    let min_len = parse_len_arg(matches, "min-node-length")?;
+    let max_len = parse_len_arg(matches, "max-node-length")?;
+    let len_range = min_len.unwrap_or(0)..=max_len.unwrap_or(usize::MAX);
+    let reindex = matches.get_flag("reindex");
+    log_info!(
+        "    --min-node-length : {}",
+        min_len.map_or("None".to_string(), |n| n.to_string())
+    );
+    log_info!(
+        "    --max-node-length : {}",
+        max_len.map_or("None".to_string(), |n| n.to_string())
+    );
+    log_info!("    --reindex : {}", reindex);
+    if len_range.is_empty() {
+        return Err(format!(
+            "--min-node-length {} is above --max-node-length {}",
+            len_range.start(),
+            len_range.end()
+        )
+        .into());
+    }
+    let summary_out = matches.get_one::<String>("summary").cloned();
+    log_info!(
+        "    --summary : {}",
+        summary_out.as_deref().unwrap_or("stderr")
+    );
 
     if num_threads > 1 {
         rayon::ThreadPoolBuilder::new()
             .num_threads(num_threads)
             .build_global()
             .ok();
-        println!("[info] Rayon thread pool set to {num_threads} threads");
+        log_info!("[info] Rayon thread pool set to {num_threads} threads");
     }
 
-    println!("[info] Reading GFA and extracting path information...");
-    let mut graph: Gfa<u32, (), ()> = Gfa::parse_gfa_file_multi(&gfa_file, num_threads);
+    let rules = PathRules {
+        ignore_level,
+        escape_special,
+        report_ignore_drops,
+        selector: selector.as_ref(),
+        len_range,
+        reindex,
+    };
+    let row_fmt = RowFormat {
+        bed,
+        with_strand,
+        revcomp,
+        with_gc,
+    };
+    let counters = ExtractCounters::default();
+    let duplicates: Mutex<Vec<DuplicateNode>> = Mutex::new(Vec::new());
 
-    // Convert GFA2 Walks (W) into regular Paths so we handle both P & W uniformly.
-    // The separator synthesizes a PanSN-style path name from (sample,hap,seq,coords).
-    // Overlaps are set to "*" per the walk specification.
-    let converted_before = graph.walk.len();
-    graph.walk_to_path("#");
-    if converted_before > 0 {
-        println!("[info] Converted {converted_before} walks (W) into path entries");
+    let paths_total = if low_mem {
+        extract_low_mem(&gfa_file, &output_file, &rules, row_fmt, &counters)?
     } else {
-        println!("[info] No walks (W) found to convert; proceeding with native P paths only");
-    }
+        // Gzipped GFA: decompress to a temp file next to the output, parse that, then clean up
+        let gfa_tmp: Option<String> = if gfa_file.ends_with(".gz") {
+            let tmp = format!("{output_file}.gfa.tmp");
+            log_info!("[info] Decompressing gzipped GFA {gfa_file} -> {tmp}");
+            decompress_gfa_to_tmp(&gfa_file, &tmp)?;
+            Some(tmp)
+        } else {
+            None
+        };
+        let parse_path = gfa_tmp.as_deref().unwrap_or(&gfa_file);
 
-    let out_file = File::create(output_file)?;
-    let out = Arc::new(Mutex::new(BufWriter::new(out_file)));
-    {
-        let mut guard = out.lock().unwrap();
-        writeln!(guard, "node\tstart\tend\tseq\tlength\tpath")?;
-    }
-    // Parallel, streamed write: per-path local buffer -> append under lock; order not guaranteed
-    graph.paths.par_iter().for_each(|path| {
-        // Apply ignore rules to path name; skip entire path if it does not pass.
-        let maybe_name = apply_ignore_rules(&path.name, ignore_level);
-        let out_name: String = match maybe_name {
-            Some(s) => s,
-            _none => return,
+        log_info!("[info] Reading GFA and extracting path information...");
+        let mut graph: Gfa<u32, (), ()> = Gfa::parse_gfa_file_multi(parse_path, num_threads);
+        if let Some(tmp) = &gfa_tmp {
+            let _ = std::fs::remove_file(tmp);
+        }
+
+        // Convert GFA2 Walks (W) into regular Paths so we handle both P & W uniformly.
+        // The separator synthesizes a PanSN-style path name from (sample,hap,seq,coords).
+        // Overlaps are set to "*" per the walk specification.
+        let converted_before = graph.walk.len();
+        graph.walk_to_path("#");
+        if converted_before > 0 {
+            log_info!("[info] Converted {converted_before} walks (W) into path entries");
+            check_walk_spans(
+                &gfa_file,
+                |node| graph.get_sequence_by_id(&node).len(),
+                &rules,
+                graph.paths.len() - converted_before,
+                &counters,
+            )?;
+        } else {
+            log_info!("[info] No walks (W) found to convert; proceeding with native P paths only");
+        }
+
+        // Path names end up in the last TSV column and later in CHROM: refuse (or escape) names
+        // that would break either before anything is written
+        rules.check_names(graph.paths.iter().map(|p| p.name.as_str()))?;
+
+        let out_file = File::create(&output_file)?;
+        let out = Arc::new(Mutex::new(BufWriter::new(out_file)));
+        row_fmt.write_header(&mut *out.lock().unwrap())?;
+        // Optional node consistency check (only allocated when --report-duplicate-nodes is set)
+        let node_sigs: Option<NodeSignatures> =
+            dup_report.as_ref().map(|_| Mutex::new(HashMap::new()));
+
+        // Rows of one path (None when --samples or --ignore drops it)
+        let render_path = |i: usize| -> Option<String> {
+            let path = &graph.paths[i];
+            let out_name = rules.output_name(&path.name, &counters)?;
+            let mut rows: u64 = 0;
+            let mut start = 0usize;
+            let mut coords = CoordCheck::default();
+            if path.dir.len() != path.nodes.len() {
+                coords.problem = Some(format!(
+                    "{} nodes but {} orientations",
+                    path.nodes.len(),
+                    path.dir.len()
+                ));
+            }
+            let mut local_buf = String::with_capacity(path.nodes.len().saturating_mul(32));
+            for (i, node) in path.nodes.iter().enumerate() {
+                let forward = path.dir.get(i).copied().unwrap_or(true);
+                let seq = graph.get_sequence_by_id(node);
+                let len = seq.len();
+                coords.node(*node, len);
+                if let Some((first_len, first_hash)) = node_sigs
+                    .as_ref()
+                    .and_then(|sigs| check_node_consistency(sigs, *node, seq))
+                {
+                    duplicates.lock().unwrap().push((
+                        *node,
+                        first_len,
+                        first_hash,
+                        len,
+                        seq_hash(seq),
+                        path.name.clone(),
+                    ));
+                }
+                // --min/--max-node-length: no row; the node still takes up its bases in the path
+                // coordinates unless --reindex closes the gap
+                if !rules.len_range.contains(&len) {
+                    counters.nodes_filtered.fetch_add(1, Ordering::Relaxed);
+                    if !rules.reindex {
+                        start += len;
+                    }
+                    continue;
+                }
+                rows += 1;
+                let end = start + len;
+                row_fmt.push_row(&mut local_buf, &out_name, *node, start, end, seq, forward);
+                start = end;
+            }
+            coords.report(&counters, i, &path.name);
+            counters.rows_written.fetch_add(rows, Ordering::Relaxed);
+            counters.summaries.lock().unwrap().push((
+                i,
+                path.name.clone(),
+                out_name,
+                path.nodes.len(),
+                start as u64,
+            ));
+            Some(local_buf)
         };
-        let mut start = 0usize;
-        let mut local_buf = String::with_capacity(path.nodes.len().saturating_mul(32));
-        for node in &path.nodes {
-            let seq = graph.get_sequence_by_id(node);
-            let len = seq.len();
-            let end = start + len;
-            use std::fmt::Write as _;
-            let _ = writeln!(
-                &mut local_buf,
-                "{}\t{}\t{}\t{}\t{}\t{}",
-                node, start, end, seq, len, out_name
+
+        if unordered {
+            // Parallel, streamed write: per-path local buffer -> append under lock; order not guaranteed
+            (0..graph.paths.len()).into_par_iter().for_each(|i| {
+                if let Some(buf) = render_path(i).filter(|b| !b.is_empty()) {
+                    let mut guard = out.lock().unwrap();
+                    let _ = guard.write_all(buf.as_bytes());
+                }
+            });
+        } else {
+            // Render in parallel, then write in GFA path order so the output is reproducible
+            let bufs: Vec<Option<String>> = (0..graph.paths.len())
+                .into_par_iter()
+                .map(render_path)
+                .collect();
+            let mut guard = out.lock().unwrap();
+            for buf in bufs.into_iter().flatten() {
+                guard.write_all(buf.as_bytes())?;
+            }
+        }
+        out.lock().unwrap().flush()?;
+        graph.paths.len()
+    };
+
+    // Missing segments, W spans that disagree with their nodes, broken walk conversions
+    let mut coord_problems = std::mem::take(&mut *counters.coord_problems.lock().unwrap());
+    coord_problems.sort_unstable_by_key(|p| p.0);
+    if let Some((_, name, msg)) = coord_problems.first() {
+        if strict_coords {
+            let _ = std::fs::remove_file(&output_file);
+            return Err(format!(
+                "--strict-coords: path {name}: {msg} ({} path(s) affected; {output_file} removed)",
+                coord_problems.len()
+            )
+            .into());
+        }
+        for (_, name, msg) in &coord_problems {
+            eprintln!("[warn] path {name}: {msg}; its start/end coordinates may be wrong");
+        }
+    }
+
+    // Per-path summary of the kept paths, in GFA path order
+    let mut summaries = counters.summaries.into_inner().unwrap();
+    summaries.sort_unstable_by_key(|s| s.0);
+    match &summary_out {
+        Some(summary_path) => {
+            let mut w = BufWriter::new(File::create(summary_path)?);
+            write!(w, "gfa_path\tpath\tnodes\tlength{}", eol())?;
+            for (_, gfa_name, name, nodes, len) in &summaries {
+                write!(w, "{}\t{}\t{}\t{}{}", gfa_name, name, nodes, len, eol())?;
+            }
+            w.flush()?;
+            log_info!(
+                "[info] Path summary ({} paths) written to {summary_path}",
+                summaries.len()
             );
-            start = end;
         }
-        if !local_buf.is_empty() {
-            let mut guard = out.lock().unwrap();
-            let _ = guard.write_all(local_buf.as_bytes());
+        None => {
+            for (_, gfa_name, name, nodes, len) in &summaries {
+                elog_info!("[info] [extract] path {gfa_name} -> {name}: {nodes} nodes, {len} bp");
+            }
+        }
+    }
+
+    if let Some(report_path) = dup_report {
+        let mut dups = duplicates.into_inner().unwrap();
+        dups.sort();
+        let mut w = BufWriter::new(File::create(&report_path)?);
+        write!(
+            w,
+            "node\tfirst_length\tfirst_seq_hash\tlength\tseq_hash\tpath{}",
+            eol()
+        )?;
+        for (node, first_len, first_hash, len, hash, path) in &dups {
+            write!(
+                w,
+                "{}\t{}\t{:016x}\t{}\t{:016x}\t{}{}",
+                node,
+                first_len,
+                first_hash,
+                len,
+                hash,
+                path,
+                eol()
+            )?;
+        }
+        w.flush()?;
+        if dups.is_empty() {
+            log_info!(
+                "[info] No inconsistent node occurrences found; report written to {report_path}"
+            );
+        } else {
+            eprintln!(
+                "[warn] {} node occurrence(s) with inconsistent sequence/length; see {report_path}",
+                dups.len()
+            );
+        }
+    }
+    if report_ignore_drops {
+        let by_reason: Vec<String> = IGNORE_DROP_REASONS
+            .iter()
+            .zip(counters.drops_by_reason.iter())
+            .map(|(r, c)| format!("{r}={}", c.load(Ordering::Relaxed)))
+            .collect();
+        log_info!(
+            "[info] --ignore {}: kept {} path(s), dropped {} ({})",
+            ignore_level,
+            counters.paths_kept.load(Ordering::Relaxed),
+            counters.paths_dropped.load(Ordering::Relaxed),
+            by_reason.join(", ")
+        );
+    }
+    let mut summary = vec![
+        ("paths", paths_total as u64),
+        ("paths_kept", counters.paths_kept.load(Ordering::Relaxed)),
+        (
+            "paths_dropped",
+            counters.paths_dropped.load(Ordering::Relaxed),
+        ),
+        ("rows", counters.rows_written.load(Ordering::Relaxed)),
+    ];
+    if min_len.is_some() || max_len.is_some() {
+        summary.push((
+            "nodes_filtered",
+            counters.nodes_filtered.load(Ordering::Relaxed),
+        ));
+    }
+    if selector.is_some() {
+        let unselected = counters.paths_unselected.load(Ordering::Relaxed);
+        if unselected == paths_total as u64 {
+            eprintln!("[warn] --samples/--samples-regex matched none of the {unselected} path(s)");
         }
-    });
-    println!("[info] Extraction complete. Output written to {output_file_clone}.");
+        summary.push(("paths_unselected", unselected));
+    }
+    crate::stats::emit("extract", &summary);
+    log_info!("[info] Extraction complete. Output written to {output_file_clone}.");
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn node_with_conflicting_sequences_is_flagged() {
+        let seen: NodeSignatures = Mutex::new(HashMap::new());
+        assert_eq!(check_node_consistency(&seen, 7, "ACGT"), None);
+        assert_eq!(check_node_consistency(&seen, 7, "ACGT"), None);
+        assert_eq!(check_node_consistency(&seen, 8, "TT"), None);
+        // Same length, different bases, then a different length
+        assert_eq!(
+            check_node_consistency(&seen, 7, "ACGA"),
+            Some((4, seq_hash("ACGT")))
+        );
+        assert_eq!(
+            check_node_consistency(&seen, 7, "AC"),
+            Some((4, seq_hash("ACGT")))
+        );
+        assert_eq!(check_node_consistency(&seen, 8, "TT"), None);
+    }
+}
@@ -1,79 +1,203 @@
+/// Default header output: <dir>/<basename-without-.vcf>.headed.vcf (handle .vcf.gz),
+/// where <dir> is `output_dir` if given, else the input's directory.
+pub fn default_headed_path(vcf_in: &str, output_dir: Option<&str>) -> String {
+    let in_path = std::path::Path::new(crate::io_utils::name_for_defaults(vcf_in));
+    let parent = output_dir
+        .map(std::path::Path::new)
+        .unwrap_or_else(|| in_path.parent().unwrap_or(std::path::Path::new(".")));
+    let fname = in_path
+        .file_name()
+        .and_then(|s| s.to_str())
+        .unwrap_or("output");
+
+    let stem = if fname.ends_with(".vcf.gz") {
+        &fname[..fname.len() - ".vcf.gz".len()]
+    } else if fname.ends_with(".vcf") {
+        &fname[..fname.len() - ".vcf".len()]
+    } else {
+        fname
+    };
+
+    parent
+        .join(format!("{}.headed.vcf", stem))
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// True when `a` and `b` name the same existing file
+fn same_file(a: &str, b: &str) -> bool {
+    match (std::fs::canonicalize(a), std::fs::canonicalize(b)) {
+        (Ok(x), Ok(y)) => x == y,
+        _ => false,
+    }
+}
+
+/// Behavior switches for `header_run` (set from `header` CLI flags, or by `align`)
+#[derive(Debug, Clone, Default)]
+pub struct HeaderOptions {
+    /// `--ignore`: CHROM normalization level 0..=5
+    pub ignore: u8,
+    /// `--chrom-case`: casing applied to contig IDs and the body CHROM
+    pub chrom_case: ChromCase,
+    /// `--drop-untyped-info`: omit INFO keys that only ever had empty values
+    pub drop_untyped_info: bool,
+    /// `--contig-length-override`: name<TAB>length TSV superseding reference.tsv lengths
+    pub contig_length_override: Option<String>,
+    /// `--contig-length-from-pos`: for body contigs without a known length, use the max POS seen
+    pub contig_length_from_pos: bool,
+    /// `--dict`: ID<TAB>Number<TAB>Type<TAB>Description TSV overriding inferred INFO/FORMAT lines
+    pub dict: Option<String>,
+    /// `--fileformat`: `##fileformat` version (e.g. `VCFv4.3`), overriding the input's
+    pub fileformat: Option<String>,
+    /// `--infer-samples`: sample columns per record scanned for FORMAT inference (None = all)
+    pub infer_samples: Option<usize>,
+    /// `--extra-header`: file of `##` lines inserted verbatim just before `#CHROM`
+    pub extra_header: Option<String>,
+    /// `align --gzip`: gzip-compress the output as it is written
+    pub gzip: bool,
+}
+
+/// What `header_run` wrote; the numbers behind the `header` stats line
+#[derive(Debug, Clone, Default)]
+pub struct HeaderReport {
+    /// Path of the headed VCF
+    pub output: String,
+    /// Lines in the new header, `#CHROM` line included
+    pub header_lines: u64,
+    /// `##contig` lines emitted (body contigs plus those kept from the input header)
+    pub contigs: u64,
+    /// `##INFO` lines added by inference or `--dict`
+    pub inferred_info: u64,
+    /// `##FORMAT` lines added by inference or `--dict`
+    pub inferred_format: u64,
+    /// `##ALT` lines added for symbolic alleles
+    pub inferred_alt: u64,
+    /// `##FILTER` lines added for FILTER values the input header does not declare
+    pub inferred_filter: u64,
+    /// Of the added INFO/FORMAT lines, how many came from `--dict`
+    pub from_dict: u64,
+    /// True when the input had no `#CHROM` line and one was written
+    pub synthesized_column_header: bool,
+}
+
+impl HeaderReport {
+    /// Emit the `header` stats line and the completion message (CLI output)
+    pub fn print(&self) {
+        crate::stats::emit(
+            "header",
+            &[
+                ("header_lines", self.header_lines),
+                ("contigs", self.contigs),
+                ("inferred_info", self.inferred_info),
+                ("inferred_format", self.inferred_format),
+                ("inferred_alt", self.inferred_alt),
+                ("inferred_filter", self.inferred_filter),
+                ("from_dict", self.from_dict),
+                (
+                    "synthesized_column_header",
+                    self.synthesized_column_header as u64,
+                ),
+            ],
+        );
+        log_info!("[info] Header synthesis complete → {}", self.output);
+        log_info!(
+            "[note] Streaming + parallel inference. Record-body normalization is not performed."
+        );
+    }
+}
+
+/// `VCFv<major>.<minor>`, e.g. `VCFv4.2`
+fn is_valid_fileformat(v: &str) -> bool {
+    match v.strip_prefix("VCFv").and_then(|x| x.split_once('.')) {
+        Some((major, minor)) => {
+            !major.is_empty()
+                && !minor.is_empty()
+                && major.chars().all(|c| c.is_ascii_digit())
+                && minor.chars().all(|c| c.is_ascii_digit())
+        }
+        None => false,
+    }
+}
+
 pub fn header_run(
     vcf_in: &str,
     reference_tsv: &str,
     threads: Option<usize>,
     output: Option<&str>,
-    ignore: u8,
-) -> Result<String, Box<dyn std::error::Error>> {
+    opts: &HeaderOptions,
+) -> Result<HeaderReport, Box<dyn std::error::Error>> {
+    if let Some(v) = opts
+        .fileformat
+        .as_deref()
+        .filter(|v| !is_valid_fileformat(v))
+    {
+        return Err(format!(
+            "--fileformat '{v}' is not of the form VCFv<major>.<minor> (e.g. VCFv4.3)"
+        )
+        .into());
+    }
+    let ignore = opts.ignore;
+    let chrom_case = opts.chrom_case;
     if let Some(n) = threads {
         rayon::ThreadPoolBuilder::new()
             .num_threads(n)
             .build_global()
             .ok();
-        println!("[info] Rayon thread pool set to {n} threads");
+        log_info!("[info] Rayon thread pool set to {n} threads");
     }
 
-    // Output path default: <same-dir>/<basename-without-.vcf>.withheader.vcf (handle .vcf.gz)
+    // Output path default: <same-dir>/<basename-without-.vcf>.headed.vcf (handle .vcf.gz)
     let out_path = if let Some(o) = output {
         o.to_string()
     } else {
-        let in_path = std::path::Path::new(vcf_in);
-        let parent = in_path.parent().unwrap_or(std::path::Path::new("."));
-        let fname = in_path
-            .file_name()
-            .and_then(|s| s.to_str())
-            .unwrap_or("output");
-
-        let stem = if fname.ends_with(".vcf.gz") {
-            &fname[..fname.len() - ".vcf.gz".len()]
-        } else if fname.ends_with(".vcf") {
-            &fname[..fname.len() - ".vcf".len()]
-        } else {
-            fname
-        };
-
-        parent
-            .join(format!("{}.headed.vcf", stem))
-            .to_string_lossy()
-            .into_owned()
+        default_headed_path(vcf_in, None)
     };
 
-    println!("[info] [header] --vcf {vcf_in}");
-    println!("[info] [header] --reference {reference_tsv}");
-    println!("[info] [header] --output {out_path}");
+    log_info!("[info] [header] --vcf {vcf_in}");
+    log_info!("[info] [header] --reference {reference_tsv}");
+    log_info!("[info] [header] --output {out_path}");
 
-    // Reader supports plain text and .gz
-    let infile = File::open(vcf_in)?;
-    let ext = Path::new(vcf_in)
-        .extension()
-        .and_then(|s| s.to_str())
-        .unwrap_or("");
-    let reader: Box<dyn BufRead> = if ext.eq_ignore_ascii_case("gz") {
-        Box::new(BufReader::new(MultiGzDecoder::new(infile)))
+    // Reader supports plain text, .gz and stdin (`-`)
+    let reader = open_input(vcf_in)?;
+
+    // A regular file is read twice (inference, then the body copy), so no spool is needed.
+    // stdin cannot be re-read, and overwriting the input in place would truncate it before
+    // the copy: those spool the body to a temp file (in $TMPDIR for stdin).
+    let spool_path = if is_stdin(vcf_in) || same_file(vcf_in, &out_path) {
+        Some(temp_path(vcf_in, &out_path, ".spool.tmp"))
     } else {
-        Box::new(BufReader::new(infile))
+        None
+    };
+    let tmpw: Box<dyn Write> = match &spool_path {
+        Some(p) => Box::new(BufWriter::new(File::create(p)?)),
+        None => Box::new(io::sink()),
     };
-
-    // Temp spool for body
-    let tmp_path = format!("{}.spool.tmp", out_path);
-    let tmpf = File::create(&tmp_path)?;
-    let tmpw = BufWriter::new(tmpf);
 
     // Read header + blocks; spool body as we go
     let block_cap = 100_000; // lines per block
     let (pre_header, column_header, blocks) = read_blocks_and_spool(reader, tmpw, block_cap)?;
 
     // Parallel inference
-    let (inferred_info, inferred_fmt, first_data, contig_maxpos) = infer_from_blocks_parallel(blocks, ignore);
+    let (inferred_info, inferred_fmt, first_data, contig_maxpos_raw, alt_symbols, filter_ids) =
+        infer_from_blocks_parallel(blocks, ignore, opts.infer_samples.unwrap_or(usize::MAX));
+
+    // Contig IDs follow --chrom-case, as does the body when it is written below
+    let mut contig_maxpos: BTreeMap<String, u64> = BTreeMap::new();
+    for (id, maxpos) in contig_maxpos_raw {
+        let e = contig_maxpos.entry(chrom_case.apply(&id)).or_insert(0);
+        if maxpos > *e {
+            *e = maxpos;
+        }
+    }
 
-    // Optional: load contig lengths from reference.tsv, but we will emit contigs
-    // based **only** on what appears in the VCF body (after `ignore`).
+    // Load contig lengths from reference.tsv; contigs are emitted for what appears in the
+    // VCF body (after `ignore`) plus any `##contig` lines already in the input header.
     let mut ref_len_map: BTreeMap<String, u64> = BTreeMap::new();
     match parse_reference_tsv(reference_tsv) {
         Ok(contigs_ref) => {
             for (k, len) in contigs_ref {
                 if let Some(id) = apply_ignore_rules(&k, ignore) {
-                    let e = ref_len_map.entry(id).or_insert(0);
+                    let e = ref_len_map.entry(chrom_case.apply(&id)).or_insert(0);
                     if len > *e {
                         *e = len;
                     }
@@ -85,9 +209,76 @@ pub fn header_run(
         }
     }
 
+    // Existing ##contig lines: a length there beats reference.tsv, and other attributes
+    // (assembly, md5, ...) are carried over. Keyed by normalized ID, first line wins.
+    let mut existing_contigs: BTreeMap<String, Vec<(String, String)>> = BTreeMap::new();
+    let mut existing_len: BTreeMap<String, u64> = BTreeMap::new();
+    for l in &pre_header {
+        let attrs = match l
+            .strip_prefix("##contig=<")
+            .and_then(|b| b.strip_suffix('>'))
+        {
+            Some(body) => parse_header_attrs(body),
+            None => continue,
+        };
+        let id = match attrs
+            .iter()
+            .find(|(k, _)| k == "ID")
+            .and_then(|(_, v)| apply_ignore_rules(v, ignore))
+        {
+            Some(id) => chrom_case.apply(&id),
+            None => continue,
+        };
+        let extra = existing_contigs.entry(id.clone()).or_default();
+        for (k, v) in attrs {
+            if k == "ID" || v.is_empty() || extra.iter().any(|(ek, _)| *ek == k) {
+                continue;
+            }
+            if k == "length" {
+                match v.parse::<u64>() {
+                    Ok(len) if len > 0 => {
+                        existing_len.entry(id.clone()).or_insert(len);
+                    }
+                    _ => {}
+                }
+                continue;
+            }
+            extra.push((k, v));
+        }
+    }
+    if !existing_contigs.is_empty() {
+        log_info!(
+            "[info] Merging {} ##contig line(s) from the input header ({} with length)",
+            existing_contigs.len(),
+            existing_len.len()
+        );
+    }
+    ref_len_map.extend(existing_len);
+
+    // Authoritative lengths (e.g. a .genome file) supersede reference.tsv, matched after ignore/case
+    if let Some(override_path) = &opts.contig_length_override {
+        let overrides = parse_contig_length_override(override_path)?;
+        let mut applied = 0usize;
+        let mut rejected = 0usize;
+        for (name, len) in overrides {
+            match apply_ignore_rules(&name, ignore) {
+                Some(id) => {
+                    ref_len_map.insert(chrom_case.apply(&id), len);
+                    applied += 1;
+                }
+                None => rejected += 1,
+            }
+        }
+        log_info!(
+            "[info] --contig-length-override {override_path}: {applied} length(s) applied, {rejected} name(s) rejected by --ignore {ignore}"
+        );
+    }
+
     // Track existing INFO/FORMAT/FILTER
     let mut existing_info: BTreeSet<String> = BTreeSet::new();
     let mut existing_format: BTreeSet<String> = BTreeSet::new();
+    let mut existing_alt: BTreeSet<String> = BTreeSet::new();
+    let mut existing_filter: BTreeSet<String> = BTreeSet::new();
     for l in &pre_header {
         if let Some(id) = l
             .strip_prefix("##INFO=<ID=")
@@ -99,55 +290,194 @@ pub fn header_run(
             .and_then(|x| x.split(',').next())
         {
             existing_format.insert(id.to_string());
+        } else if let Some(id) = l
+            .strip_prefix("##ALT=<ID=")
+            .and_then(|x| x.split(',').next())
+        {
+            existing_alt.insert(id.trim_end_matches('>').to_string());
+        } else if let Some(id) = l
+            .strip_prefix("##FILTER=<ID=")
+            .and_then(|x| x.split(',').next())
+        {
+            existing_filter.insert(id.trim_end_matches('>').to_string());
         }
     }
 
+    let extra_header = match &opts.extra_header {
+        Some(p) => parse_extra_header(p)?,
+        None => Vec::new(),
+    };
+
     // Final header
     let mut new_header: Vec<String> = Vec::new();
-    let fileformat_line = pre_header
-        .iter()
-        .find(|l| l.starts_with("##fileformat="))
-        .cloned()
-        .unwrap_or_else(|| "##fileformat=VCFv4.2".to_string());
-    println!("[info] Emitting fileformat line: {fileformat_line}");
+    let input_fileformat = pre_header.iter().find(|l| l.starts_with("##fileformat="));
+    let fileformat_line = match (&opts.fileformat, input_fileformat) {
+        (Some(v), Some(old)) if *old != format!("##fileformat={v}") => {
+            eprintln!("[warn] --fileformat {v} overrides the input's {old}");
+            format!("##fileformat={v}")
+        }
+        (Some(v), _) => format!("##fileformat={v}"),
+        (None, Some(old)) => old.clone(),
+        (None, None) => "##fileformat=VCFv4.2".to_string(),
+    };
+    log_info!("[info] Emitting fileformat line: {fileformat_line}");
     new_header.push(fileformat_line);
-    new_header.push("##source=gfa2bin-aligner/header".to_string());
-
-    // Emit contigs discovered from the VCF body (post-ignore). Use reference.tsv only for lengths.
-    println!("[info] Emitting contigs discovered from VCF body (ignore={ignore}). Seen {} contigs.", contig_maxpos.len());
-    for (id, _maxpos) in &contig_maxpos {
-        if let Some(len) = ref_len_map.get(id).copied() {
-            if len > 0 {
-                new_header.push(format!("##contig=<ID={},length={}>", id, len));
-                continue;
+    new_header.push(format!(
+        "##source=gfa2bin-aligner/header v{}",
+        crate::VERSION
+    ));
+
+    // Emit contigs discovered from the VCF body (post-ignore) merged with the input's ##contig
+    // lines, once per ID. Lengths: --contig-length-override > input header > reference.tsv.
+    log_info!(
+        "[info] Emitting contigs discovered from VCF body (ignore={ignore}). Seen {} contigs.",
+        contig_maxpos.len()
+    );
+    let contig_ids: BTreeSet<&String> = contig_maxpos
+        .keys()
+        .chain(existing_contigs.keys())
+        .collect();
+    let mut body_only: Vec<&str> = Vec::new();
+    for id in &contig_ids {
+        let mut line = format!("##contig=<ID={id}");
+        match ref_len_map.get(*id).copied() {
+            Some(len) if len > 0 => line.push_str(&format!(",length={len}")),
+            _ => {
+                if let Some(maxpos) = contig_maxpos.get(*id) {
+                    body_only.push(id);
+                    if opts.contig_length_from_pos && *maxpos > 0 {
+                        line.push_str(&format!(",length={maxpos}"));
+                    }
+                }
             }
         }
-        new_header.push(format!("##contig=<ID={}>", id));
+        for (k, v) in existing_contigs.get(*id).into_iter().flatten() {
+            line.push_str(&format!(",{k}={v}"));
+        }
+        line.push('>');
+        new_header.push(line);
+    }
+    if !body_only.is_empty() {
+        eprintln!(
+            "[warn] {} contig(s) found only in the VCF body, without a length from reference.tsv ({}): {}",
+            body_only.len(),
+            if opts.contig_length_from_pos {
+                "length set to the max POS seen"
+            } else {
+                "declared without length"
+            },
+            body_only.join(",")
+        );
     }
 
     for l in &pre_header {
         if l.starts_with("##INFO=<ID=")
             || l.starts_with("##FORMAT=<ID=")
             || l.starts_with("##FILTER=<")
+            || l.starts_with("##ALT=<")
         {
             new_header.push(l.clone());
         }
     }
+    let mut added_alt: u64 = 0;
+    for id in &alt_symbols {
+        if !existing_alt.contains(id) {
+            new_header.push(alt_def(id));
+            added_alt += 1;
+        }
+    }
+    // FILTER values seen in the body; PASS and `.` need no definition
+    let mut added_filter: u64 = 0;
+    for id in &filter_ids {
+        if !existing_filter.contains(id) {
+            new_header.push(format!(
+                "##FILTER=<ID={id},Description=\"Inferred from FILTER column\">"
+            ));
+            added_filter += 1;
+        }
+    }
 
+    // Curated definitions (--dict) replace inference for the keys they list
+    let dict = match &opts.dict {
+        Some(p) => {
+            let d = parse_header_dict(p)?;
+            log_info!("[info] --dict {p}: {} definition(s)", d.len());
+            d
+        }
+        None => HashMap::new(),
+    };
+    let mut from_dict: u64 = 0;
+
+    let mut added_info: u64 = 0;
+    let mut added_format: u64 = 0;
+    let mut dropped_untyped: Vec<String> = Vec::new();
     for (k, ks) in inferred_info {
         // Always add INFO fields present in the data, even GT (which will be ignored by downstream tools if present in INFO).
         if !existing_info.contains(&k) {
+            if let Some(e) = dict.get(&k) {
+                new_header.push(dict_def("INFO", &k, e));
+                added_info += 1;
+                from_dict += 1;
+                continue;
+            }
+            // --drop-untyped-info: a key that only ever had empty values has no inferable type
+            if opts.drop_untyped_info && ks.typed == 0 {
+                dropped_untyped.push(k);
+                continue;
+            }
             // If no type/description can be inferred, allow empty description.
             // Use a flag to indicate that empty description is allowed.
             new_header.push(infer_info_def_with_empty(&k, &ks, true));
+            added_info += 1;
         }
     }
+    if !dropped_untyped.is_empty() {
+        eprintln!(
+            "[warn] --drop-untyped-info: omitted {} INFO definition(s) with only empty values: {}",
+            dropped_untyped.len(),
+            dropped_untyped.join(",")
+        );
+    }
     for (k, (kind, card)) in inferred_fmt {
         if !existing_format.contains(&k) {
+            if let Some(e) = dict.get(&k) {
+                new_header.push(dict_def("FORMAT", &k, e));
+                added_format += 1;
+                from_dict += 1;
+                continue;
+            }
             new_header.push(infer_format_def(&k, Some(kind), Some(card)));
+            added_format += 1;
         }
     }
 
+    // --extra-header: verbatim, minus lines (or structured IDs) the header already has
+    if let Some(p) = &opts.extra_header {
+        let mut added = 0usize;
+        let mut skipped: Vec<String> = Vec::new();
+        for l in extra_header {
+            let present = new_header.iter().any(|h| {
+                *h == l
+                    || structured_meta_id(&l).is_some_and(|id| structured_meta_id(h) == Some(id))
+            });
+            if present {
+                skipped.push(l);
+            } else {
+                new_header.push(l);
+                added += 1;
+            }
+        }
+        log_info!("[info] --extra-header {p}: {added} line(s) added");
+        if !skipped.is_empty() {
+            eprintln!(
+                "[warn] --extra-header: skipped {} line(s) already in the header: {}",
+                skipped.len(),
+                skipped.join(" ")
+            );
+        }
+    }
+
+    let synthesized_column_header = column_header.is_none();
     if let Some(ch) = column_header {
         new_header.push(ch);
     } else {
@@ -159,142 +489,191 @@ pub fn header_run(
         }
     }
 
-    // Write header, then append spooled body
-    {
-        let mut out = BufWriter::new(File::create(&out_path)?);
-        for l in new_header {
-            writeln!(out, "{}", l)?;
-        }
-        out.flush()?;
+    let header_lines = new_header.len() as u64;
+
+    // Write header, then the body (spooled, or from a second pass over the input)
+    let mut out = OutputFile::create(&out_path, opts.gzip)?;
+    for l in new_header {
+        write!(out, "{}{}", l, eol())?;
     }
-    {
-        use std::io::copy;
-        let mut out_app = std::fs::OpenOptions::new().append(true).open(&out_path)?;
-        let mut tmp_r = BufReader::new(File::open(&tmp_path)?);
-        copy(&mut tmp_r, &mut out_app)?;
+    // Re-terminate body lines so the body follows --line-sep like the header, and recase CHROM
+    match &spool_path {
+        Some(p) => {
+            for line in BufReader::new(File::open(p)?).lines() {
+                write!(out, "{}{}", chrom_case.apply_to_body_line(line?), eol())?;
+            }
+            let _ = std::fs::remove_file(p);
+        }
+        None => {
+            let mut in_header = true;
+            for line in open_input(vcf_in)?.lines() {
+                let l = line?;
+                if in_header {
+                    if l.starts_with("##") {
+                        continue;
+                    }
+                    in_header = false;
+                    if l.starts_with("#CHROM\t")
+                        || l.starts_with("#CHROM ")
+                        || l.trim_end() == "#CHROM"
+                    {
+                        continue;
+                    }
+                }
+                write!(out, "{}{}", chrom_case.apply_to_body_line(l), eol())?;
+            }
+        }
     }
-
-    let _ = std::fs::remove_file(&tmp_path);
-
-    println!("[info] Header synthesis complete → {out_path}");
-    println!("[note] Streaming + parallel inference. Record-body normalization is not performed.");
-    Ok(out_path)
+    out.finish()?;
+
+    Ok(HeaderReport {
+        output: out_path,
+        header_lines,
+        contigs: contig_ids.len() as u64,
+        inferred_info: added_info,
+        inferred_format: added_format,
+        inferred_alt: added_alt,
+        inferred_filter: added_filter,
+        from_dict,
+        synthesized_column_header,
+    })
 }
-use clap::ArgMatches;
-use flate2::read::MultiGzDecoder;
-use std::collections::{BTreeMap, BTreeSet};
+use crate::chrom::{ChromCase, apply_ignore_rules};
+use crate::io_utils::{OutputFile, is_stdin, open_input, temp_path};
+use crate::line_sep::eol;
+use crate::progress::Progress;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::fs::File;
 use std::io::{self, BufRead, BufReader, BufWriter, Write};
-use std::path::Path;
 
 type Contigs = BTreeMap<String, u64>;
-fn parse_reference_tsv(p: &str) -> io::Result<Contigs> {
-    let f = BufReader::new(File::open(p)?);
-    let mut contigs: Contigs = BTreeMap::new();
-
-    // Heuristics supported:
-    // 1) Header like: node,start,end,path  (tab or comma)
-    // 2) Header like: path,length OR chrom,length
-    // 3) No header: assume at least 4 columns and take 4th as path, 3rd as end
-    // We aggregate by path and keep the maximum length/end.
-
-    let mut header_cols: Option<Vec<String>> = None;
+/// One `--dict` row: curated Number/Type/Description for an INFO or FORMAT ID
+struct DictEntry {
+    number: String,
+    typ: String,
+    description: String,
+}
 
+/// Parse the `--dict` TSV: `ID<TAB>Number<TAB>Type<TAB>Description`; `#` lines and an
+/// `ID` header row are skipped.
+fn parse_header_dict(p: &str) -> io::Result<HashMap<String, DictEntry>> {
+    let f = BufReader::new(File::open(p)?);
+    let mut out = HashMap::new();
     for (i, line) in f.lines().enumerate() {
         let l_raw = line?;
-        let l = l_raw.trim();
-        if l.is_empty() {
+        let l = l_raw.trim_end_matches(['\n', '\r']);
+        if l.trim().is_empty() || l.starts_with('#') {
             continue;
         }
+        let cols: Vec<&str> = l.split('\t').map(|c| c.trim()).collect();
+        if cols[0].eq_ignore_ascii_case("id") {
+            continue;
+        }
+        if cols.len() < 4 || cols[..3].iter().any(|c| c.is_empty()) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "{p}:{}: expected ID<TAB>Number<TAB>Type<TAB>Description, got '{l}'",
+                    i + 1
+                ),
+            ));
+        }
+        out.insert(
+            cols[0].to_string(),
+            DictEntry {
+                number: cols[1].to_string(),
+                typ: cols[2].to_string(),
+                description: cols[3].replace('"', "\\\""),
+            },
+        );
+    }
+    Ok(out)
+}
 
-        // Prefer TAB; fall back to comma
-        let parts_tab: Vec<&str> = l.split('\t').collect();
-        let parts: Vec<&str> = if parts_tab.len() > 1 {
-            parts_tab
+/// `--extra-header` lines, in file order; blank lines are skipped, anything else must start with `##`
+fn parse_extra_header(p: &str) -> io::Result<Vec<String>> {
+    let f = BufReader::new(File::open(p)?);
+    let mut out = Vec::new();
+    for (i, line) in f.lines().enumerate() {
+        let l_raw = line?;
+        let l = l_raw.trim_end_matches(['\n', '\r']);
+        if l.trim().is_empty() {
+            continue;
+        }
+        let bad = if !l.starts_with("##") {
+            Some("does not start with '##'")
+        } else if l.starts_with("##fileformat=") {
+            Some("sets ##fileformat (use --fileformat)")
         } else {
-            l.split(',').collect()
+            None
         };
-
-        if i == 0 {
-            // Detect header if any field has letters
-            let looks_like_header = parts
-                .iter()
-                .any(|c| c.chars().any(|ch| ch.is_ascii_alphabetic()));
-            if looks_like_header {
-                header_cols = Some(parts.iter().map(|s| s.to_ascii_lowercase()).collect());
-                continue; // data starts from next line
-            }
+        if let Some(why) = bad {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{p}:{}: header line {why}: '{l}'", i + 1),
+            ));
         }
+        out.push(l.to_string());
+    }
+    Ok(out)
+}
 
-        let (path_opt, len_opt) = if let Some(cols) = &header_cols {
-            // Map likely columns by name
-            let mut path_idx: Option<usize> = None;
-            let mut len_idx: Option<usize> = None;
-            let mut end_idx: Option<usize> = None;
-            let mut start_idx: Option<usize> = None;
-
-            for (idx, name) in cols.iter().enumerate() {
-                if path_idx.is_none()
-                    && (name.contains("path") || name == "chrom" || name.contains("chr"))
-                {
-                    path_idx = Some(idx);
-                }
-                if len_idx.is_none() && (name == "length" || name == "len") {
-                    len_idx = Some(idx);
-                }
-                if end_idx.is_none() && name == "end" {
-                    end_idx = Some(idx);
-                }
-                if start_idx.is_none() && (name == "start" || name == "beg") {
-                    start_idx = Some(idx);
-                }
-            }
-
-            let path_val = path_idx.and_then(|pi| parts.get(pi)).map(|s| s.to_string());
-            let len_val = if let Some(li) = len_idx {
-                parts.get(li).and_then(|s| s.parse::<u64>().ok())
-            } else if let Some(ei) = end_idx {
-                parts.get(ei).and_then(|s| s.parse::<u64>().ok())
-            } else if let (Some(_si), Some(ei)) = (start_idx, end_idx) {
-                parts.get(ei).and_then(|s| s.parse::<u64>().ok())
-            } else {
-                // last numeric-looking column
-                parts.last().and_then(|s| s.parse::<u64>().ok())
-            };
-
-            (path_val, len_val)
-        } else {
-            // No header: assume GFA-ish `node start end path`
-            let path_val = parts
-                .get(3)
-                .map(|s| s.to_string())
-                .or_else(|| parts.last().map(|s| s.to_string()));
-            let len_val = parts
-                .get(2)
-                .and_then(|s| s.parse::<u64>().ok())
-                .or_else(|| {
-                    parts
-                        .iter()
-                        .rev()
-                        .skip(1)
-                        .next()
-                        .and_then(|s| s.parse::<u64>().ok())
-                });
-            (path_val, len_val)
-        };
+/// (`KEY`, `ID`) of a structured meta line such as `##ALT=<ID=DEL,...>`
+fn structured_meta_id(l: &str) -> Option<(&str, &str)> {
+    let (key, body) = l.strip_prefix("##")?.split_once("=<")?;
+    let id = body.strip_prefix("ID=")?.split([',', '>']).next()?;
+    Some((key, id))
+}
 
-        let path = match path_opt {
-            Some(pv) if !pv.is_empty() => pv,
-            _ => continue,
-        };
-        let len = len_opt.unwrap_or(0);
+/// `##INFO`/`##FORMAT` line for `id` from its `--dict` entry
+fn dict_def(kind: &str, id: &str, e: &DictEntry) -> String {
+    format!(
+        "##{kind}=<ID={id},Number={},Type={},Description=\"{}\">",
+        e.number, e.typ, e.description
+    )
+}
 
-        contigs
-            .entry(path)
-            .and_modify(|m| *m = (*m).max(len))
-            .or_insert(len);
+/// Read `name<TAB>length` rows (a `.genome` / `.fai`-like file). Blank and `#` lines are
+/// skipped, as is a first row whose length column is not a number (header).
+fn parse_contig_length_override(p: &str) -> io::Result<Vec<(String, u64)>> {
+    let f = BufReader::new(File::open(p)?);
+    let mut out = Vec::new();
+    for (i, line) in f.lines().enumerate() {
+        let l_raw = line?;
+        let l = l_raw.trim();
+        if l.is_empty() || l.starts_with('#') {
+            continue;
+        }
+        let mut cols = l.split('\t');
+        let name = cols.next().unwrap_or("").trim();
+        let len_raw = cols.next().unwrap_or("").trim();
+        match len_raw.parse::<u64>() {
+            Ok(len) if !name.is_empty() => out.push((name.to_string(), len)),
+            _ if i == 0 => continue,
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("{p}:{}: expected name<TAB>length, got '{l}'", i + 1),
+                ));
+            }
+        }
     }
+    Ok(out)
+}
 
+/// Path lengths from reference.tsv, read with the same parser as `align`: the largest node end
+/// per path (`start + seq length` for a row without a readable end).
+fn parse_reference_tsv(p: &str) -> Result<Contigs, Box<dyn std::error::Error>> {
+    let reference = crate::io_stream::read_reference_tsv(p, "#")?;
+    let mut contigs: Contigs = BTreeMap::new();
+    for (node, path) in &reference.path {
+        let end = reference.end.get(node).copied().or_else(|| {
+            let start = reference.start.get(node)?;
+            Some(start + reference.seq.get(node)?.len() as u64)
+        });
+        let e = contigs.entry(path.clone()).or_insert(0);
+        *e = (*e).max(end.unwrap_or(0));
+    }
     Ok(contigs)
 }
 
@@ -315,7 +694,9 @@ struct KeyStats {
     all_singleton: bool, // always one value
     matches_a: usize,    // how many lines length == #ALT
     matches_r: usize,    // how many lines length == #ALT+1
+    matches_g: usize,    // how many lines length == #genotypes (diploid: (#ALT+1)(#ALT+2)/2)
     samples: usize,      // how many lines we saw this key in
+    typed: usize,        // how many of those had a non-empty value (or were flags)
 }
 
 impl Default for KeyStats {
@@ -327,7 +708,9 @@ impl Default for KeyStats {
             all_singleton: true,
             matches_a: 0,
             matches_r: 0,
+            matches_g: 0,
             samples: 0,
+            typed: 0,
         }
     }
 }
@@ -368,6 +751,8 @@ fn infer_info_def_with_empty(id: &str, ks: &KeyStats, allow_empty: bool) -> Stri
         "R".to_string()
     } else if ks.all_singleton {
         "1".to_string()
+    } else if ks.matches_g * 2 >= ks.samples && ks.samples > 0 {
+        "G".to_string()
     } else {
         ".".to_string()
     };
@@ -393,23 +778,116 @@ fn infer_format_def(
     example_kind: Option<ValKind>,
     example_card: Option<usize>,
 ) -> String {
-    let (number, _typ) = match (example_kind, example_card) {
-        (Some(_), Some(0)) => ("0".to_string(), "String".to_string()),
-        (Some(_), Some(1)) => ("1".to_string(), "String".to_string()),
-        (Some(_), Some(_n)) => (".".to_string(), "String".to_string()),
-        (Some(_), _none) => (".".to_string(), "String".to_string()),
-        (_none, _) => (".".to_string(), "String".to_string()),
+    // GT is always a single string (e.g. 0/1, 1|0, ./.), whatever it looks like
+    if id == "GT" {
+        return "##FORMAT=<ID=GT,Number=1,Type=String,Description=\"Genotype\">".to_string();
+    }
+    let number = match example_card {
+        Some(1) => "1",
+        _ => ".",
     };
-    let (number, typ) = match example_kind {
-        Some(ValKind::Int) => (number, "Integer".to_string()),
-        Some(ValKind::Float) => (number, "Float".to_string()),
-        _ => (number, "String".to_string()),
+    let typ = match (example_kind, example_card) {
+        // Only missing values seen: no type evidence
+        (_, Some(0)) => "String",
+        (Some(ValKind::Int), _) => "Integer",
+        (Some(ValKind::Float), _) => "Float",
+        _ => "String",
     };
     format!(
         "##FORMAT=<ID={id},Number={number},Type={typ},Description=\"Inferred from FORMAT column\">"
     )
 }
 
+/// Type of a FORMAT value token across its comma-separated parts (missing `.` parts ignored);
+/// `None` when the token is entirely missing.
+fn classify_format_token(tok: &str) -> Option<(ValKind, usize)> {
+    let mut kind: Option<ValKind> = None;
+    let mut card = 0;
+    for part in tok.split(',') {
+        card += 1;
+        if part.is_empty() || part == "." {
+            continue;
+        }
+        let k = classify_value_token(part);
+        kind = Some(kind.map_or(k, |prev| promote_kind(prev, k)));
+    }
+    kind.map(|k| (k, card))
+}
+
+/// Int < Float < String: one float anywhere makes the key Float, one string makes it String
+fn promote_kind(a: ValKind, b: ValKind) -> ValKind {
+    match (a, b) {
+        (ValKind::Stringy, _) | (_, ValKind::Stringy) => ValKind::Stringy,
+        (ValKind::Float, _) | (_, ValKind::Float) => ValKind::Float,
+        _ => ValKind::Int,
+    }
+}
+
+/// Fold one observation into a FORMAT key's evidence. A card of 0 means "only missing values
+/// so far" and yields to any typed observation; differing cardinalities end up as `Number=.`.
+fn merge_format_evidence(acc: &mut (ValKind, usize), obs: (ValKind, usize)) {
+    if acc.1 == 0 {
+        *acc = obs;
+    } else if obs.1 != 0 {
+        acc.0 = promote_kind(acc.0, obs.0);
+        if acc.1 != obs.1 {
+            acc.1 = acc.1.max(obs.1).max(2);
+        }
+    }
+}
+
+/// Items of a VCF INFO column as (key, value); flags have no value, `.` and empty items yield nothing
+pub fn info_items(info: &str) -> impl Iterator<Item = (&str, Option<&str>)> {
+    info.split(';')
+        .filter(|item| !item.is_empty() && *item != ".")
+        .map(|item| match item.split_once('=') {
+            Some((k, v)) => (k, Some(v)),
+            None => (item, None),
+        })
+}
+
+/// Split the inside of a structured header line (`ID=x,length=1,Description="a, b"`) into
+/// key/value pairs; commas inside double quotes do not split, and quotes are kept.
+fn parse_header_attrs(body: &str) -> Vec<(String, String)> {
+    let mut out = Vec::new();
+    let mut in_quotes = false;
+    let mut start = 0;
+    for (i, c) in body
+        .char_indices()
+        .chain(std::iter::once((body.len(), ',')))
+    {
+        match c {
+            '"' => in_quotes = !in_quotes,
+            ',' if !in_quotes => {
+                let item = &body[start..i];
+                if let Some((k, v)) = item.split_once('=') {
+                    out.push((k.trim().to_string(), v.trim().to_string()));
+                }
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    out
+}
+
+/// `##ALT` line for a symbolic allele ID; known VCF 4.x types get their spec description
+fn alt_def(id: &str) -> String {
+    let desc = match id {
+        "DEL" => "Deletion relative to the reference",
+        "INS" => "Insertion of novel sequence relative to the reference",
+        "DUP" => "Region of elevated copy number relative to the reference",
+        "INV" => "Inversion of reference sequence",
+        "CNV" => "Copy number variable region",
+        "DUP:TANDEM" => "Tandem duplication",
+        "DEL:ME" => "Deletion of mobile element relative to the reference",
+        "INS:ME" => "Insertion of a mobile element",
+        "*" | "NON_REF" => "Any possible alternative allele not listed",
+        _ => "Symbolic allele inferred from ALT column",
+    };
+    format!("##ALT=<ID={id},Description=\"{desc}\">")
+}
+
 // --- Streaming + parallel inference helpers ---
 
 #[inline]
@@ -420,7 +898,9 @@ fn merge_keystats(mut a: KeyStats, b: &KeyStats) -> KeyStats {
     a.all_singleton &= b.all_singleton;
     a.matches_a += b.matches_a;
     a.matches_r += b.matches_r;
+    a.matches_g += b.matches_g;
     a.samples += b.samples;
+    a.typed += b.typed;
     a
 }
 
@@ -443,7 +923,9 @@ fn merge_format_maps(
     b: BTreeMap<String, (ValKind, usize)>,
 ) -> BTreeMap<String, (ValKind, usize)> {
     for (k, v) in b {
-        a.entry(k).or_insert(v);
+        a.entry(k)
+            .and_modify(|x| merge_format_evidence(x, v))
+            .or_insert(v);
     }
     a
 }
@@ -470,7 +952,10 @@ fn read_blocks_and_spool<R: BufRead, W: Write>(
         }
         if buf.starts_with("##") {
             pre_header.push(buf.trim_end().to_string());
-        } else if buf.starts_with("#CHROM\t") || buf.starts_with("#CHROM ") {
+        } else if buf.starts_with("#CHROM\t")
+            || buf.starts_with("#CHROM ")
+            || buf.trim_end() == "#CHROM"
+        {
             column_header = Some(buf.trim_end().to_string());
             break;
         } else {
@@ -512,10 +997,12 @@ fn read_blocks_and_spool<R: BufRead, W: Write>(
     let mut blk = Block {
         lines: Vec::with_capacity(block_cap),
     };
+    let mut progress = Progress::new("header");
     loop {
         let mut l = String::new();
         let n = reader.read_line(&mut l)?;
         if n == 0 {
+            progress.finish();
             if !blk.lines.is_empty() {
                 for x in &blk.lines {
                     spool_writer.write_all(x.as_bytes())?;
@@ -524,6 +1011,7 @@ fn read_blocks_and_spool<R: BufRead, W: Write>(
             }
             break;
         }
+        progress.tick(n - 1);
         blk.lines.push(l);
         if blk.lines.len() == block_cap {
             for x in &blk.lines {
@@ -542,11 +1030,14 @@ fn read_blocks_and_spool<R: BufRead, W: Write>(
 fn infer_from_blocks_parallel(
     blocks: Vec<Block>,
     ignore: u8,
+    max_samples: usize,
 ) -> (
     BTreeMap<String, KeyStats>,
     BTreeMap<String, (ValKind, usize)>,
     Option<String>,
-    BTreeMap<String, u64>,
+    Contigs,
+    BTreeSet<String>,
+    BTreeSet<String>,
 ) {
     use rayon::prelude::*;
 
@@ -557,6 +1048,8 @@ fn infer_from_blocks_parallel(
             let mut fmt_map: BTreeMap<String, (ValKind, usize)> = BTreeMap::new();
             let mut contig_map: BTreeMap<String, u64> = BTreeMap::new();
             let mut first_data: Option<String> = None;
+            let mut alt_symbols: BTreeSet<String> = BTreeSet::new();
+            let mut filter_ids: BTreeSet<String> = BTreeSet::new();
 
             for line in &blk.lines {
                 let trimmed = line.trim_end();
@@ -589,18 +1082,33 @@ fn infer_from_blocks_parallel(
                     .map(|alts| alts.split(',').filter(|x| !x.is_empty()).count())
                     .unwrap_or(0);
 
+                // Symbolic ALT alleles (<DEL>, <DUP:TANDEM>, ...) need ##ALT lines
+                for id in fields[4]
+                    .split(',')
+                    .filter_map(|a| a.strip_prefix('<')?.strip_suffix('>'))
+                {
+                    if !id.is_empty() && !alt_symbols.contains(id) {
+                        alt_symbols.insert(id.to_string());
+                    }
+                }
+
+                // FILTER tags (`q10;LowQual`) need ##FILTER lines; PASS and `.` are implicit
+                for id in fields[6].split(';') {
+                    if !matches!(id, "" | "." | "PASS") && !filter_ids.contains(id) {
+                        filter_ids.insert(id.to_string());
+                    }
+                }
+
                 // INFO
                 if let Some(info) = fields.get(7) {
-                    for item in info.split(';') {
-                        if item.is_empty() {
-                            continue;
-                        }
-                        if let Some((k, v)) = item.split_once('=') {
+                    for (k, v) in info_items(info) {
+                        if let Some(v) = v {
                             let ks = info_map.entry(k.to_string()).or_default();
                             ks.samples += 1;
                             if v.is_empty() {
                                 continue;
                             }
+                            ks.typed += 1;
                             let vals: Vec<&str> = v.split(',').collect();
                             ks.all_singleton &= vals.len() == 1;
                             if vals.len() == alt_ct {
@@ -609,6 +1117,9 @@ fn infer_from_blocks_parallel(
                             if vals.len() == alt_ct + 1 {
                                 ks.matches_r += 1;
                             }
+                            if vals.len() == (alt_ct + 1) * (alt_ct + 2) / 2 {
+                                ks.matches_g += 1;
+                            }
                             for vv in &vals {
                                 match classify_value_token(vv) {
                                     ValKind::Int => { /* keep all_int */ }
@@ -623,38 +1134,46 @@ fn infer_from_blocks_parallel(
                             }
                         } else {
                             // Flag (no '=')
-                            let ks = info_map.entry(item.to_string()).or_default();
+                            let ks = info_map.entry(k.to_string()).or_default();
                             ks.samples += 1;
+                            ks.typed += 1;
                             ks.seen_as_flag = true;
                         }
                     }
                 }
 
-                // FORMAT
+                // FORMAT: evidence from every sample column (up to --infer-samples), not just
+                // the first
                 if fields.len() >= 10 {
-                    if let Some(fmt) = fields.get(8) {
-                        for key in fmt.split(':') {
+                    let keys: Vec<&str> = fields[8].split(':').collect();
+                    for sample in fields[9..].iter().take(max_samples) {
+                        let toks: Vec<&str> = sample.split(':').collect();
+                        for (pos, key) in keys.iter().enumerate() {
                             if key.is_empty() {
                                 continue;
                             }
-                            if let Some(sample) = fields.get(9) {
-                                if let Some((pos, _)) = fmt.split(':').enumerate().find(|(_, k)| *k == key) {
-                                    let sample_tok = sample.split(':').nth(pos).unwrap_or("");
-                                    let card = sample_tok.split(',').filter(|x| !x.is_empty()).count();
-                                    let kind = if card == 0 {
-                                        ValKind::Stringy
-                                    } else {
-                                        classify_value_token(sample_tok.split(',').next().unwrap_or(""))
-                                    };
-                                    fmt_map.entry(key.to_string()).or_insert((kind, card));
-                                }
-                            }
+                            // Trailing fields may be dropped from a sample; they count as missing
+                            let obs = toks
+                                .get(pos)
+                                .and_then(|t| classify_format_token(t))
+                                .unwrap_or((ValKind::Stringy, 0));
+                            fmt_map
+                                .entry(key.to_string())
+                                .and_modify(|x| merge_format_evidence(x, obs))
+                                .or_insert(obs);
                         }
                     }
                 }
             }
 
-            (info_map, fmt_map, first_data, contig_map)
+            (
+                info_map,
+                fmt_map,
+                first_data,
+                contig_map,
+                alt_symbols,
+                filter_ids,
+            )
         })
         .collect::<Vec<_>>();
 
@@ -662,8 +1181,10 @@ fn infer_from_blocks_parallel(
     let mut final_fmt: BTreeMap<String, (ValKind, usize)> = BTreeMap::new();
     let mut any_first: Option<String> = None;
     let mut final_contigs: BTreeMap<String, u64> = BTreeMap::new();
+    let mut final_alts: BTreeSet<String> = BTreeSet::new();
+    let mut final_filters: BTreeSet<String> = BTreeSet::new();
 
-    for (im, fm, fd, cm) in results {
+    for (im, fm, fd, cm, alts, filters) in results {
         final_info = merge_info_maps(final_info, im);
         final_fmt = merge_format_maps(final_fmt, fm);
         if any_first.is_none() {
@@ -672,139 +1193,23 @@ fn infer_from_blocks_parallel(
         for (k, v) in cm {
             final_contigs
                 .entry(k)
-                .and_modify(|m| if v > *m { *m = v })
+                .and_modify(|m| {
+                    if v > *m {
+                        *m = v
+                    }
+                })
                 .or_insert(v);
         }
+        final_alts.extend(alts);
+        final_filters.extend(filters);
     }
 
-    (final_info, final_fmt, any_first, final_contigs)
-}
-
-pub fn header_main(matches: &ArgMatches) -> Result<(), Box<dyn std::error::Error>> {
-    let vcf_in = matches.get_one::<String>("vcf").unwrap();
-    let reference_tsv = matches.get_one::<String>("reference").unwrap();
-    let threads = matches
-        .get_one::<String>("threads")
-        .and_then(|s| s.parse::<usize>().ok());
-    let out_opt = matches.get_one::<String>("output").map(|s| s.as_str());
-    let ignore: u8 = matches
-        .get_one::<String>("ignore")
-        .and_then(|s| s.parse::<u8>().ok())
-        .unwrap_or(0);
-    let _ = header_run(vcf_in, reference_tsv, threads, out_opt, ignore)?;
-    Ok(())
-}
-
-/// Helper function to apply ignore rules to contig names.
-/// Apply ignore rules 0..=5:
-/// 0: Keep as-is (no checks)
-/// 1: Keep only if string (case-insensitively) contains "chr"
-/// 2: As 1, and drop if token after "chr" is not [digits|X|Y|M]
-/// 3: As 2, and drop if there is any suffix after the token (e.g., "chr12_random")
-/// 4: Keep only standard human set {1..22, X, Y, M}. Normalize to "chr{TOKEN}" (uppercase token),
-///    stripping any extra context, e.g. "GRCh38.chr12_random" -> "chr12".
-/// 5: Same as 4, but output only "{TOKEN}" without "chr" prefix, e.g. "12", "X", "Y", "M".
-fn apply_ignore_rules(raw: &str, level: u8) -> Option<String> {
-    match level {
-        0 => Some(raw.to_string()),
-        1 => {
-            let contains_chr = raw.to_ascii_lowercase().contains("chr");
-            if contains_chr {
-                Some(raw.to_string())
-            } else {
-                None
-            }
-        }
-        2 => {
-            let (tok_opt, _has_suffix, found_chr) = extract_chr_token(raw);
-            if !found_chr {
-                return None;
-            }
-            match tok_opt {
-                Some(t) => {
-                    let ok = matches!(t.as_str(), "X" | "Y" | "M")
-                        || t.chars().all(|c| c.is_ascii_digit());
-                    if ok { Some(raw.to_string()) } else { None }
-                }
-                none => none,
-            }
-        }
-        3 => {
-            let (tok_opt, has_suffix, found_chr) = extract_chr_token(raw);
-            if !found_chr {
-                return None;
-            }
-            if tok_opt.is_none() {
-                return None;
-            }
-            if has_suffix {
-                return None;
-            }
-            Some(raw.to_string())
-        }
-        4 => {
-            let (tok_opt, _has_suffix, found_chr) = extract_chr_token(raw);
-            if !found_chr {
-                return None;
-            }
-            let t = tok_opt?;
-            if !is_std_human_chr_token(&t) {
-                return None;
-            }
-            Some(format!("chr{}", t.to_ascii_uppercase()))
-        }
-        5 => {
-            let (tok_opt, _has_suffix, found_chr) = extract_chr_token(raw);
-            if !found_chr {
-                return None;
-            }
-            let t = tok_opt?;
-            if !is_std_human_chr_token(&t) {
-                return None;
-            }
-            Some(t.to_ascii_uppercase())
-        }
-        _ => Some(raw.to_string()),
-    }
-}
-
-/// Extracts the token after "chr" (case-insensitive) in the string.
-/// Returns (Some(token), has_suffix, found_chr)
-fn extract_chr_token(raw: &str) -> (Option<String>, bool, bool) {
-    let raw_lower = raw.to_ascii_lowercase();
-    if let Some(idx) = raw_lower.find("chr") {
-        let after = &raw[idx + 3..];
-        let mut token = String::new();
-        let mut has_suffix = false;
-        for c in after.chars() {
-            if c.is_ascii_alphanumeric() {
-                token.push(c);
-            } else {
-                has_suffix = true;
-                break;
-            }
-        }
-        if token.is_empty() {
-            return (None, has_suffix, true);
-        }
-        let tok_norm = if token.chars().all(|c| c.is_ascii_digit()) {
-            token
-        } else {
-            token.to_ascii_uppercase()
-        };
-        (Some(tok_norm), has_suffix, true)
-    } else {
-        (None, false, false)
-    }
-}
-
-/// Returns true if token is a standard human chromosome (1-22, X, Y, M).
-fn is_std_human_chr_token(tok: &str) -> bool {
-    match tok.to_ascii_uppercase().as_str() {
-        "X" | "Y" | "M" => true,
-        n => n
-            .parse::<u8>()
-            .map(|v| (1..=22).contains(&v))
-            .unwrap_or(false),
-    }
+    (
+        final_info,
+        final_fmt,
+        any_first,
+        final_contigs,
+        final_alts,
+        final_filters,
+    )
 }
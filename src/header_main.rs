@@ -0,0 +1,46 @@
+use crate::chrom::{ChromCase, load_chrom_map};
+use crate::header::{HeaderOptions, default_headed_path, header_run};
+use clap::ArgMatches;
+
+/// `header` subcommand: CLI flags -> `HeaderOptions`, then `header_run`
+pub fn header_main(matches: &ArgMatches) -> Result<(), Box<dyn std::error::Error>> {
+    let vcf_in = matches.get_one::<String>("vcf").unwrap();
+    let reference_tsv = matches.get_one::<String>("reference").unwrap();
+    let threads = matches
+        .get_one::<String>("threads")
+        .and_then(|s| s.parse::<usize>().ok());
+    let output_dir = matches.get_one::<String>("output-dir").map(|s| s.as_str());
+    if let Some(d) = output_dir {
+        std::fs::create_dir_all(d)?;
+    }
+    let out_owned: Option<String> = matches
+        .get_one::<String>("output")
+        .cloned()
+        .or_else(|| output_dir.map(|d| default_headed_path(vcf_in, Some(d))));
+    let out_opt = out_owned.as_deref();
+    let ignore: u8 = matches
+        .get_one::<String>("ignore")
+        .and_then(|s| s.parse::<u8>().ok())
+        .unwrap_or(0);
+    let opts = HeaderOptions {
+        ignore,
+        chrom_case: ChromCase::from_arg(matches.get_one::<String>("chrom-case")),
+        drop_untyped_info: matches.get_flag("drop-untyped-info"),
+        contig_length_override: matches.get_one::<String>("contig-length-override").cloned(),
+        contig_length_from_pos: matches.get_flag("contig-length-from-pos"),
+        dict: matches.get_one::<String>("dict").cloned(),
+        fileformat: matches.get_one::<String>("fileformat").cloned(),
+        infer_samples: matches.get_one::<u64>("infer-samples").map(|&n| n as usize),
+        extra_header: matches.get_one::<String>("extra-header").cloned(),
+        gzip: false,
+    };
+    if let Some(p) = matches.get_one::<String>("chrom-map") {
+        let n = load_chrom_map(p)?;
+        log_info!("[info] [header] --chrom-map {p} ({n} aliases)");
+    }
+    if let Some(n) = opts.infer_samples {
+        log_info!("[info] [header] --infer-samples {n}");
+    }
+    header_run(vcf_in, reference_tsv, threads, out_opt, &opts)?.print();
+    Ok(())
+}
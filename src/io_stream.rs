@@ -1,12 +1,35 @@
+use crate::chrom::{ChromCase, apply_ignore_rules};
+use crate::io_utils::open_input;
+use crate::line_sep::eol;
+use crate::progress::Progress;
 use gfa_reader::Gfa;
+use regex::Regex;
 use std::collections::{HashMap, HashSet};
 use std::fs::File;
 use std::io::{BufRead, BufReader, BufWriter, Write};
-use std::sync::OnceLock;
+use std::time::Instant;
 
-static REF_NODE2PATH: OnceLock<HashMap<u64, String>> = OnceLock::new();
-static REF_NODE2START: OnceLock<HashMap<u64, u64>> = OnceLock::new();
-static REF_NODE2SEQ: OnceLock<HashMap<u64, String>> = OnceLock::new();
+/// reference.tsv as read by `read_reference_tsv`: the fallback path, start and sequence of
+/// each node, passed explicitly to the streaming functions
+#[derive(Debug, Clone, Default)]
+pub struct ReferenceMaps {
+    /// node -> path (last column, before any columns `extract` appends after it)
+    pub path: HashMap<u64, String>,
+    /// node -> 0-based start
+    pub start: HashMap<u64, u64>,
+    /// node -> end (exclusive, third column)
+    pub end: HashMap<u64, u64>,
+    /// node -> seq (6-column files only)
+    pub seq: HashMap<u64, String>,
+}
+
+impl ReferenceMaps {
+    /// `end - start` of a node, when both columns were readable
+    pub fn node_len(&self, node: u64) -> Option<u64> {
+        let (start, end) = (self.start.get(&node)?, self.end.get(&node)?);
+        end.checked_sub(*start)
+    }
+}
 
 // #[allow(dead_code)]
 // pub struct VcfRecord {
@@ -79,11 +102,253 @@ pub struct StreamStats {
     pub replaced_chrom: u64,
     pub replaced_pos: u64,
     pub replaced_id: u64,
+    pub kept_id: u64,
+    pub pos_decreasing: u64,
+    pub pos_collisions: u64,
     pub replaced_ref: u64,
+    pub ref_matching: u64,
     pub missing_start: u64,
     pub missing_seq: u64,
     pub used_ref_map: u64,
     pub used_aln_map: u64,
+    /// Data lines with fewer than the 8 fixed VCF columns
+    pub short_lines: u64,
+    /// Data lines whose column count differs from the `#CHROM` line
+    pub column_mismatch: u64,
+    /// Data lines whose POS is not a non-negative integer
+    pub bad_pos: u64,
+    /// Unmapped records removed by `--on-unmapped drop`
+    pub unmapped_dropped: u64,
+    /// SV records whose POS `--use-end` moved to the last base of their node
+    pub end_pos: u64,
+    /// SV records left at the node start by `--use-end` because the node length is unknown
+    pub end_missing_len: u64,
+}
+
+/// When REF is overwritten with the node sequence (`--ref-rewrite`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RefRewrite {
+    /// Always overwrite REF (previous behavior)
+    #[default]
+    Always,
+    /// Overwrite only if REF differs from the sequence ignoring case (keeps soft-masking)
+    IfDifferent,
+    /// Never touch REF
+    Never,
+}
+
+impl RefRewrite {
+    pub fn from_arg(value: Option<&String>) -> Self {
+        match value.map(|s| s.as_str()) {
+            Some("if-different") => RefRewrite::IfDifferent,
+            Some("never") => RefRewrite::Never,
+            _ => RefRewrite::Always,
+        }
+    }
+}
+
+/// alignment.tsv distances above this mark a node with no usable distance to the reference
+/// path; `--pos-mode sum` then uses the alignment position alone
+pub const UNALIGNED_DISTANCE: i64 = 1_000_000_000;
+
+/// How POS is computed for a mapped record (`--pos-mode`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PosMode {
+    /// POS := distance + position + 1 from alignment.tsv (previous behavior); distance == -1
+    /// (node on the path) gives POS := position, and distances above `UNALIGNED_DISTANCE`
+    /// use the position alone
+    #[default]
+    Sum,
+    /// POS := alignment.tsv position, ignoring the distance
+    Position,
+    /// POS := start of the node in reference.tsv + 1, ignoring alignment.tsv
+    Start,
+}
+
+impl PosMode {
+    pub fn from_arg(value: Option<&String>) -> Self {
+        match value.map(|s| s.as_str()) {
+            Some("position") => PosMode::Position,
+            Some("start") => PosMode::Start,
+            _ => PosMode::Sum,
+        }
+    }
+
+    /// POS from an alignment.tsv row, or None when the mode takes it from reference.tsv
+    pub fn aligned_pos(self, a: &AlnInfo) -> Option<u64> {
+        match self {
+            PosMode::Sum if a.distance > UNALIGNED_DISTANCE => Some(a.position),
+            // distance < -1 behaves like -1 so the offset cannot wrap
+            PosMode::Sum => Some(((a.distance.max(-1) + 1) as u64).saturating_add(a.position)),
+            PosMode::Position => Some(a.position),
+            PosMode::Start => None,
+        }
+    }
+}
+
+/// Coordinate base of a POS source; VCF POS is 1-based (`--coord-base`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CoordBase {
+    /// Already 1-based: used as is (alignment.tsv positions by default)
+    #[default]
+    One,
+    /// 0-based: 1 is added (reference.tsv starts written by `extract`)
+    Zero,
+}
+
+impl CoordBase {
+    pub fn from_arg(value: Option<&String>) -> Self {
+        match value.map(|s| s.as_str()) {
+            Some("0") => CoordBase::Zero,
+            _ => CoordBase::One,
+        }
+    }
+
+    /// 1-based VCF POS for a coordinate in this base
+    pub fn to_vcf_pos(self, pos: u64) -> u64 {
+        match self {
+            CoordBase::One => pos,
+            CoordBase::Zero => pos.saturating_add(1),
+        }
+    }
+}
+
+/// What happens to a record whose node has no path in alignment.tsv / reference.tsv
+/// (`--on-unmapped`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OnUnmapped {
+    /// Rewrite CHROM through the ignore rules; dropped when they reject it (previous behavior)
+    #[default]
+    Keep,
+    /// Remove the record (counted as `unmapped_dropped`)
+    Drop,
+    /// Write the original line untouched
+    Passthrough,
+}
+
+impl OnUnmapped {
+    pub fn from_arg(value: Option<&String>) -> Self {
+        match value.map(|s| s.as_str()) {
+            Some("drop") => OnUnmapped::Drop,
+            Some("passthrough") => OnUnmapped::Passthrough,
+            _ => OnUnmapped::Keep,
+        }
+    }
+}
+
+/// Where the pre-rewrite POS of a mapped record is recorded (`--original-pos-to`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OriginalPosTo {
+    /// ID := original POS (previous behavior)
+    #[default]
+    Id,
+    /// Append `OPOS=<pos>` to INFO, leaving ID untouched
+    Info,
+    /// Both of the above
+    Both,
+    /// Do not record it
+    None,
+}
+
+impl OriginalPosTo {
+    pub fn from_arg(value: Option<&String>) -> Self {
+        match value.map(|s| s.as_str()) {
+            Some("info") => OriginalPosTo::Info,
+            Some("both") => OriginalPosTo::Both,
+            Some("none") => OriginalPosTo::None,
+            _ => OriginalPosTo::Id,
+        }
+    }
+
+    fn to_id(self) -> bool {
+        matches!(self, OriginalPosTo::Id | OriginalPosTo::Both)
+    }
+
+    fn to_info(self) -> bool {
+        matches!(self, OriginalPosTo::Info | OriginalPosTo::Both)
+    }
+}
+
+/// Header line declaring the `OPOS` INFO key written by `--original-pos-to info|both`
+const OPOS_INFO_LINE: &str =
+    "##INFO=<ID=OPOS,Number=1,Type=Integer,Description=\"Original POS before align rewrote it\">";
+
+/// Append `key=value` to an INFO column ("." becomes just the new item)
+fn append_info(info: &mut String, item: &str) {
+    if info.is_empty() || info == "." {
+        *info = item.to_string();
+    } else {
+        info.push(';');
+        info.push_str(item);
+    }
+}
+
+/// Structural variant for `--use-end`: a symbolic ALT (`<DEL>`, ...) or an INFO `SVTYPE` key
+fn is_sv_record(fields: &[String]) -> bool {
+    let symbolic_alt = fields.get(4).is_some_and(|alt| {
+        alt.split(',')
+            .any(|a| a.starts_with('<') && a.ends_with('>'))
+    });
+    symbolic_alt
+        || fields.get(7).is_some_and(|info| {
+            info.split(';')
+                .any(|kv| kv.split('=').next() == Some("SVTYPE"))
+        })
+}
+
+/// Overwrite REF with `seq` according to `mode`, counting rewrites and case-folded matches.
+fn rewrite_ref(ref_field: &mut String, seq: &str, mode: RefRewrite, stats: &mut StreamStats) {
+    match mode {
+        RefRewrite::Never => {}
+        RefRewrite::IfDifferent if ref_field.eq_ignore_ascii_case(seq) => {
+            stats.ref_matching += 1;
+        }
+        _ => {
+            *ref_field = seq.to_string();
+            stats.replaced_ref += 1;
+        }
+    }
+}
+
+/// Behavior switches for `stream_replace_chrom_to_tmp` (set from `align` CLI flags)
+#[derive(Debug, Clone, Default)]
+pub struct StreamOptions {
+    /// `--skip` / `--skip-regex`: drop records whose raw CHROM matches any of these
+    pub skip: Vec<SkipMatcher>,
+    /// `--ignore`: CHROM normalization level 0..=5
+    pub ignore_level: u8,
+    /// `--oink`: set ALT to "oink" for nodes that are not on the reference
+    pub oink: bool,
+    /// `--normalize-info-chrom`: INFO keys whose values are CHROM names normalized like CHROM
+    pub info_chrom_keys: HashSet<String>,
+    /// `--keep-gt-only`: reduce FORMAT to `GT` and each sample to its GT subfield
+    pub keep_gt_only: bool,
+    /// `--chrom-case`: casing applied to the final CHROM
+    pub chrom_case: ChromCase,
+    /// `--node-offset`: VCF node id = graph node id + offset, so lookups use `vcf_id - offset`
+    pub node_offset: i64,
+    /// `--ref-rewrite`: when REF is replaced by the GFA / reference.tsv sequence
+    pub ref_rewrite: RefRewrite,
+    /// `--original-pos-to`: where the pre-rewrite POS is recorded (ID, INFO `OPOS`, both, none)
+    pub original_pos_to: OriginalPosTo,
+    /// `--escape-special`: percent-encode characters illegal in CHROM instead of rejecting the path
+    pub escape_special: bool,
+    /// `--keep-id`: never overwrite ID with the original POS (INFO `OPOS` is unaffected)
+    pub keep_id: bool,
+    /// `--check-sorted`: count records whose POS does not increase within their output CHROM
+    pub check_sorted: bool,
+    /// `--unmapped-out`: TSV receiving the raw CHROM and parsed node id of each unmapped record
+    pub unmapped_out: Option<String>,
+    /// `--pos-mode`: how POS is computed for mapped records
+    pub pos_mode: PosMode,
+    /// `--coord-base`: base of alignment.tsv positions (reference.tsv starts are always 0-based)
+    pub aln_coord_base: CoordBase,
+    /// `--strict`: stop at the first malformed data line instead of counting it
+    pub strict: bool,
+    /// `--on-unmapped`: keep (CHROM rewritten), drop or pass through unmapped records
+    pub on_unmapped: OnUnmapped,
+    /// `--use-end`: POS of SV records (symbolic ALT or INFO `SVTYPE`) is the node's last base
+    pub use_end: bool,
 }
 
 #[derive(Debug, Clone, Default)]
@@ -93,18 +358,69 @@ pub struct AlnInfo {
     pub position: u64,
 }
 
-/// Return true if the raw CHROM field should be skipped entirely (substring match)
-pub fn should_skip_chrom(chrom: &str, skip: &HashSet<String>) -> bool {
-    if skip.is_empty() {
-        return false;
-    }
-    // case-sensitive substring match by default
-    for k in skip {
-        if !k.is_empty() && chrom.contains(k) {
-            return true;
+/// One `--skip` / `--skip-regex` pattern, matched against the raw CHROM
+#[derive(Debug, Clone)]
+pub enum SkipMatcher {
+    /// `--skip`: case-sensitive substring
+    Substring(String),
+    /// `--skip` with `--skip-ci`: substring ignoring ASCII case (stored lowercased)
+    SubstringCi(String),
+    /// `--skip-regex`: pattern found anywhere in the CHROM (anchor with `^`/`$` if needed)
+    Regex(Regex),
+}
+
+impl SkipMatcher {
+    fn matches(&self, chrom: &str) -> bool {
+        match self {
+            SkipMatcher::Substring(k) => !k.is_empty() && chrom.contains(k.as_str()),
+            SkipMatcher::SubstringCi(k) => {
+                !k.is_empty() && chrom.to_ascii_lowercase().contains(k.as_str())
+            }
+            SkipMatcher::Regex(re) => re.is_match(chrom),
         }
     }
-    false
+}
+
+/// Build the matchers for `--skip` (comma-separated substrings, optionally case-insensitive)
+/// and `--skip-regex` (one pattern per occurrence, compiled once here).
+pub fn build_skip_matchers(
+    substrings: Option<&str>,
+    case_insensitive: bool,
+    regexes: &[String],
+) -> Result<Vec<SkipMatcher>, String> {
+    let mut out: Vec<SkipMatcher> = substrings
+        .unwrap_or("")
+        .split(',')
+        .map(|x| x.trim())
+        .filter(|x| !x.is_empty())
+        .map(|x| {
+            if case_insensitive {
+                SkipMatcher::SubstringCi(x.to_ascii_lowercase())
+            } else {
+                SkipMatcher::Substring(x.to_string())
+            }
+        })
+        .collect();
+    for pat in regexes {
+        let re = Regex::new(pat).map_err(|e| format!("--skip-regex '{pat}': {e}"))?;
+        out.push(SkipMatcher::Regex(re));
+    }
+    Ok(out)
+}
+
+/// Return true if the raw CHROM field should be skipped entirely (any matcher hits)
+pub fn should_skip_chrom(chrom: &str, skip: &[SkipMatcher]) -> bool {
+    skip.iter().any(|m| m.matches(chrom))
+}
+
+/// Translate a node id parsed from the VCF into the id space of the maps/GFA
+/// (`graph_id = vcf_id - offset`). Ids that would fall below 0 are treated as unparseable.
+fn apply_node_offset(vcf_id: u64, offset: i64) -> Option<u64> {
+    if offset >= 0 {
+        vcf_id.checked_sub(offset as u64)
+    } else {
+        vcf_id.checked_add(offset.unsigned_abs())
+    }
 }
 
 /// Parse a node id from a CHROM string. Accepts either a plain integer (e.g., "1234")
@@ -130,127 +446,95 @@ fn parse_node_id_from_chrom(chrom: &str) -> Option<u64> {
     digits_fwd.parse::<u64>().ok()
 }
 
-/// Case-insensitive search for "chr" and extract a canonical token:
-/// - Looks for the *last* occurrence of "chr" (case-insensitive) so
-///   strings like "GRCh38.chr12_random" work.
-/// - Captures immediately following token consisting of [0-9]+ or X/Y/M (case-insensitive).
-/// - Returns (token_upper, has_suffix_after_token, found_chr)
-fn extract_chr_token(raw: &str) -> (Option<String>, bool, bool) {
-    let lower = raw.to_ascii_lowercase();
-    if let Some(idx) = lower.rfind("chr") {
-        let tail = &raw[idx + 3..];
-        // Walk tail to capture token consisting of digits or X/Y/M (case-insensitive)
-        let mut token = String::new();
-        for ch in tail.chars() {
-            let c = ch.to_ascii_uppercase();
-            if c.is_ascii_digit() || c == 'X' || c == 'Y' || c == 'M' {
-                token.push(c);
-            } else {
-                break;
-            }
-        }
-        if token.is_empty() {
-            return (None, !tail.is_empty(), true);
-        }
-        // Whether there is anything *after* the token and before hitting end
-        let has_suffix = tail.len() > token.len();
-        return (Some(token), has_suffix, true);
+/// Final CHROM for a normalized path name: `--chrom-case`, then `--escape-special`
+fn output_chrom(norm_chr: &str, opts: &StreamOptions) -> String {
+    let chrom = opts.chrom_case.apply(norm_chr);
+    if opts.escape_special {
+        crate::chrom::escape_chrom(&chrom)
+    } else {
+        chrom
     }
-    (None, false, false)
 }
 
-/// Return whether a token is a standard human chromosome label:
-/// 1..22 or X/Y/M (token must be uppercase, digits allowed).
-fn is_std_human_chr_token(token_upper: &str) -> bool {
-    match token_upper {
-        "X" | "Y" | "M" => true,
-        _ => {
-            token_upper.chars().all(|c| c.is_ascii_digit())
-                && token_upper
-                    .parse::<u32>()
-                    .map(|n| (1..=22).contains(&n))
-                    .unwrap_or(false)
-        }
-    }
-}
-
-/// Apply ignore rules 0..=5:
-/// 0: Keep as-is (no checks)
-/// 1: Keep only if string (case-insensitively) contains "chr"
-/// 2: As 1, and drop if token after "chr" is not [digits|X|Y|M]
-/// 3: As 2, and drop if there is any suffix after the token (e.g., "chr12_random")
-/// 4: Keep only standard human set {1..22, X, Y, M}. Normalize to "chr{TOKEN}" (uppercase token),
-///    stripping any extra context, e.g. "GRCh38.chr12_random" -> "chr12".
-/// 5: Same as 4, but output only "{TOKEN}" without "chr" prefix, e.g. "12", "X", "Y", "M".
-fn apply_ignore_rules(raw: &str, level: u8) -> Option<String> {
-    match level {
-        0 => Some(raw.to_string()),
-
-        1 => {
-            let contains_chr = raw.to_ascii_lowercase().contains("chr");
-            if contains_chr {
-                Some(raw.to_string())
-            } else {
-                None
-            }
-        }
-
-        2 => {
-            let (tok_opt, _has_suffix, found_chr) = extract_chr_token(raw);
-            if !found_chr {
-                return None;
-            }
-            match tok_opt {
-                Some(t) => {
-                    // digits or X/Y/M (not necessarily standard range at this level)
-                    let ok = matches!(t.as_str(), "X" | "Y" | "M")
-                        || t.chars().all(|c| c.is_ascii_digit());
-                    if ok { Some(raw.to_string()) } else { None }
-                }
-                none => none,
-            }
-        }
-
-        3 => {
-            let (tok_opt, has_suffix, found_chr) = extract_chr_token(raw);
-            if !found_chr {
-                return None;
-            }
-            if tok_opt.is_none() {
-                return None;
-            }
-            if has_suffix {
-                return None;
-            }
-            Some(raw.to_string())
-        }
+/// Reject (unless `--escape-special`) path names that would produce a malformed CHROM, listing
+/// every offending name; run before streaming so no partial output is left behind.
+pub fn check_path_names(
+    node2aln: &HashMap<u64, AlnInfo>,
+    reference: &ReferenceMaps,
+    opts: &StreamOptions,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut names: Vec<String> = node2aln
+        .values()
+        .map(|a| &a.path)
+        .chain(reference.path.values())
+        .filter_map(|p| apply_ignore_rules(p, opts.ignore_level))
+        .map(|n| opts.chrom_case.apply(&n))
+        .collect();
+    names.sort();
+    names.dedup();
+    crate::chrom::check_chrom_names(names.iter().map(String::as_str), opts.escape_special)?;
+    let special = names
+        .iter()
+        .filter(|n| crate::chrom::invalid_chrom_reason(n).is_some())
+        .count();
+    if special > 0 {
+        eprintln!(
+            "[warn] --escape-special: escaping {special} path name(s) with special characters"
+        );
+    }
+    Ok(())
+}
 
-        4 => {
-            let (tok_opt, _has_suffix, found_chr) = extract_chr_token(raw);
-            if !found_chr {
-                return None;
-            }
-            let t = tok_opt?;
-            if !is_std_human_chr_token(&t) {
-                return None;
-            }
-            Some(format!("chr{}", t))
-        }
+/// Normalize a CHROM-like INFO value (e.g. SV `CHR2=`) with the same ignore rules as CHROM.
+/// Bare tokens like "1" are retried as "chr1" at levels 4/5, which canonicalize the token anyway.
+/// Returns None when the value does not pass the ignore rules (caller keeps it untouched).
+fn normalize_info_chrom_value(val: &str, ignore_level: u8) -> Option<String> {
+    if let Some(v) = apply_ignore_rules(val, ignore_level) {
+        return Some(v);
+    }
+    if ignore_level >= 4 && !val.to_ascii_lowercase().contains("chr") {
+        return apply_ignore_rules(&format!("chr{val}"), ignore_level);
+    }
+    None
+}
 
-        5 => {
-            let (tok_opt, _has_suffix, found_chr) = extract_chr_token(raw);
-            if !found_chr {
-                return None;
-            }
-            let t = tok_opt?;
-            if !is_std_human_chr_token(&t) {
-                return None;
+/// Rewrite the values of the listed INFO keys (`--normalize-info-chrom`) through
+/// `normalize_info_chrom_value` and `output_chrom`, so they match the CHROM column.
+/// Unlisted keys, flags and values that fail normalization are left untouched.
+fn normalize_info_chrom(info: &str, opts: &StreamOptions) -> String {
+    if opts.info_chrom_keys.is_empty() || info == "." {
+        return info.to_string();
+    }
+    info.split(';')
+        .map(|item| match item.split_once('=') {
+            Some((k, v)) if opts.info_chrom_keys.contains(k) => {
+                match normalize_info_chrom_value(v, opts.ignore_level) {
+                    Some(norm) => format!("{k}={}", output_chrom(&norm, opts)),
+                    None => item.to_string(),
+                }
             }
-            Some(t)
-        }
+            _ => item.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join(";")
+}
 
-        _ => Some(raw.to_string()),
+/// Rewrite FORMAT (index 8) to `GT` and each sample column to just its GT subfield,
+/// locating GT by position in FORMAT (as in `maf_main`). Samples become "." when FORMAT has
+/// no GT. Records without FORMAT/sample columns are left untouched.
+fn keep_gt_only_fields(fields: &mut [String]) {
+    if fields.len() < 10 {
+        return;
+    }
+    let gt_idx = fields[8].split(':').position(|k| k == "GT");
+    for sample in fields.iter_mut().skip(9) {
+        let gt = gt_idx
+            .and_then(|i| sample.split(':').nth(i))
+            .unwrap_or(".")
+            .to_string();
+        *sample = gt;
     }
+    fields[8] = "GT".to_string();
 }
 
 /// Replacement strategy (new):
@@ -259,7 +543,7 @@ fn apply_ignore_rules(raw: &str, level: u8) -> Option<String> {
 /// - 若能在 alignment.tsv 找到该 node：
 ///     * CHROM := 规范化后的 path 名（按 ignore_level 过滤/归一化）。
 ///     * ID    := 原始 POS 字符串（未替换前）。
-///     * POS   := distance + position + 1（alignment.tsv）。
+///     * POS   := distance + position + 1（alignment.tsv；见 `--pos-mode`）。
 ///     * REF   := 来自 GFA 的段序列；若未提供 GFA，则回退 reference.tsv 的 seq。
 /// - 若 alignment.tsv 缺失该 node：
 ///     * 仍可用 reference.tsv 的 path/start/seq（若存在）进行回退；否则按 ignore 规则仅重写/保留 CHROM。
@@ -268,27 +552,144 @@ pub fn stream_replace_chrom_to_tmp(
     vcf_path: &str,
     tmp_out_path: &str,
     node2aln: &HashMap<u64, AlnInfo>,
-    skip: &HashSet<String>,
-    ignore_level: u8,               // 0..=5
+    reference: &ReferenceMaps,
     gfa: Option<&Gfa<u32, (), ()>>, // 如果提供GFA，优先用其序列并可忽略reference.tsv
-    oink: bool,
+    opts: &StreamOptions,
 ) -> Result<StreamStats, Box<dyn std::error::Error>> {
-    let f_in = File::open(vcf_path)?;
-    let reader = BufReader::new(f_in);
-
     let f_out = File::create(tmp_out_path)?;
-    let mut writer = BufWriter::new(f_out);
+    stream_replace_chrom(
+        vcf_path,
+        BufWriter::new(f_out),
+        node2aln,
+        reference,
+        gfa,
+        opts,
+    )
+}
+
+/// `--check-sorted`: compare a written record's POS with the last one written on the same CHROM,
+/// counting decreases (out of order) and repeats (collisions) without reordering anything.
+fn track_pos_order(
+    last_pos: &mut HashMap<String, u64>,
+    out_fields: &[String],
+    stats: &mut StreamStats,
+) {
+    let pos = match out_fields.get(1).and_then(|p| p.trim().parse::<u64>().ok()) {
+        Some(p) => p,
+        None => return,
+    };
+    match last_pos.get_mut(&out_fields[0]) {
+        Some(last) => {
+            if pos < *last {
+                stats.pos_decreasing += 1;
+            } else if pos == *last {
+                stats.pos_collisions += 1;
+            }
+            *last = pos;
+        }
+        None => {
+            last_pos.insert(out_fields[0].clone(), pos);
+        }
+    }
+}
+
+/// Count the column problems of one data line in `stats`; returns the first one, for `--strict`.
+fn validate_record(
+    fields: &[&str],
+    header_cols: Option<usize>,
+    stats: &mut StreamStats,
+) -> Option<String> {
+    let mut problem = None;
+    if fields.len() < 8 {
+        stats.short_lines += 1;
+        problem = Some(format!(
+            "{} column(s), a VCF record needs at least 8",
+            fields.len()
+        ));
+    }
+    match header_cols {
+        Some(n) if n != fields.len() => {
+            stats.column_mismatch += 1;
+            problem.get_or_insert_with(|| {
+                format!("{} column(s), the #CHROM line has {n}", fields.len())
+            });
+        }
+        _ => {}
+    }
+    if fields
+        .get(1)
+        .is_none_or(|p| p.trim().parse::<u64>().is_err())
+    {
+        stats.bad_pos += 1;
+        problem.get_or_insert_with(|| {
+            format!("POS '{}' is not a number", fields.get(1).unwrap_or(&""))
+        });
+    }
+    problem
+}
+
+/// Streaming loop behind `stream_replace_chrom_to_tmp`, writing to any sink (`--bench` uses `io::sink()`).
+fn stream_replace_chrom<W: Write>(
+    vcf_path: &str,
+    mut writer: W,
+    node2aln: &HashMap<u64, AlnInfo>,
+    reference: &ReferenceMaps,
+    gfa: Option<&Gfa<u32, (), ()>>,
+    opts: &StreamOptions,
+) -> Result<StreamStats, Box<dyn std::error::Error>> {
+    let skip = &opts.skip;
+    let ignore_level = opts.ignore_level; // 0..=5
+    let oink = opts.oink;
+    let reader = open_input(vcf_path)?;
 
     let mut stats = StreamStats::default();
+    let mut has_opos_def = false;
+    let mut last_pos: HashMap<String, u64> = HashMap::new();
+    // Only opened when requested, so the default path pays nothing for it
+    let mut unmapped_w = match &opts.unmapped_out {
+        Some(p) => {
+            let mut w = BufWriter::new(File::create(p)?);
+            write!(w, "chrom\tnode{}", eol())?;
+            Some(w)
+        }
+        None => None,
+    };
 
-    for line in reader.lines() {
+    let mut header_cols: Option<usize> = None;
+    let mut progress = Progress::new("align");
+    for (line_no, line) in reader.lines().enumerate() {
         let line = line?;
+        progress.tick(line.len());
         if line.starts_with('#') {
-            writeln!(writer, "{line}")?;
+            // --keep-gt-only: drop FORMAT definitions other than GT so the header matches the body
+            if opts.keep_gt_only
+                && line.starts_with("##FORMAT=<")
+                && !line.starts_with("##FORMAT=<ID=GT,")
+            {
+                continue;
+            }
+            if line.starts_with("##INFO=<ID=OPOS,") {
+                has_opos_def = true;
+            }
+            // Declare OPOS just before the column header so header synthesis keeps it
+            if opts.original_pos_to.to_info() && !has_opos_def && line.starts_with("#CHROM") {
+                write!(writer, "{OPOS_INFO_LINE}{}", eol())?;
+                has_opos_def = true;
+            }
+            if line.starts_with("#CHROM") {
+                header_cols = Some(line.split('\t').count());
+            }
+            write!(writer, "{line}{}", eol())?;
             continue;
         }
 
         let fields: Vec<&str> = line.split('\t').collect();
+        match validate_record(&fields, header_cols, &mut stats) {
+            Some(problem) if opts.strict => {
+                return Err(format!("--strict: {vcf_path} line {}: {problem}", line_no + 1).into());
+            }
+            _ => {}
+        }
         if fields.len() < 2 {
             continue;
         }
@@ -303,7 +704,9 @@ pub fn stream_replace_chrom_to_tmp(
                 pos_node_id_opt = Some(pos_as_u64);
             }
         }
-        let node_id_opt = chrom_node_id_opt.or(pos_node_id_opt);
+        let node_id_opt = chrom_node_id_opt
+            .or(pos_node_id_opt)
+            .and_then(|n| apply_node_offset(n, opts.node_offset));
 
         if skip_now {
             stats.skipped += 1;
@@ -314,8 +717,8 @@ pub fn stream_replace_chrom_to_tmp(
         let mut wrote = false;
 
         if let Some(node_id) = node_id_opt {
-            let path_from_ref = REF_NODE2PATH.get().and_then(|m| m.get(&node_id));
-            let seq_from_ref = REF_NODE2SEQ.get().and_then(|m| m.get(&node_id));
+            let path_from_ref = reference.path.get(&node_id);
+            let seq_from_ref = reference.seq.get(&node_id);
             let aln_info = node2aln.get(&node_id);
 
             // 选路径：alignment.tsv 优先；否则回退 reference.tsv
@@ -328,72 +731,67 @@ pub fn stream_replace_chrom_to_tmp(
                     let mut out_fields: Vec<String> =
                         fields.iter().map(|s| s.to_string()).collect();
 
-                    // 保存原始 POS 到 ID（如果有第三列）
+                    // 保存原始 POS 到 ID（如果有第三列）和/或 INFO 的 OPOS（--original-pos-to）
                     let orig_pos = out_fields.get(1).cloned().unwrap_or_default();
-                    if out_fields.len() >= 3 {
-                        out_fields[2] = orig_pos.clone();
-                        stats.replaced_id += 1;
+                    if opts.original_pos_to.to_id() && out_fields.len() >= 3 {
+                        if opts.keep_id {
+                            stats.kept_id += 1;
+                        } else {
+                            out_fields[2] = orig_pos.clone();
+                            stats.replaced_id += 1;
+                        }
+                    }
+                    if opts.original_pos_to.to_info() && out_fields.len() >= 8 {
+                        append_info(&mut out_fields[7], &format!("OPOS={orig_pos}"));
                     }
                     // 1) CHROM 由规范化的 path 名得到
-                    out_fields[0] = norm_chr.clone();
+                    out_fields[0] = output_chrom(&norm_chr, opts);
                     stats.replaced_chrom += 1;
 
                     // 2) REF：优先从 GFA 取段序列；若无 GFA，则回退到 reference.tsv 的 seq
-                    let ref_set = false;
+                    let mut ref_set = false;
+                    // Node length: GFA segment, else the reference.tsv seq, else end - start
+                    let mut node_len: Option<u64> = seq_from_ref
+                        .map(|s| s.len() as u64)
+                        .or_else(|| reference.node_len(node_id));
                     if let Some(g) = gfa {
                         let nid_u32 = node_id as u32;
                         if nid_u32 as usize >= g.get_index_low()
                             && nid_u32 as usize <= g.get_index_high()
                         {
                             let seq = g.get_sequence_by_id(&nid_u32);
+                            node_len = Some(seq.len() as u64);
                             if out_fields.len() >= 4 {
-                                out_fields[3] = seq.to_string();
-                                stats.replaced_ref += 1;
+                                rewrite_ref(&mut out_fields[3], seq, opts.ref_rewrite, &mut stats);
                             }
+                            ref_set = true;
                         }
                     }
                     if !ref_set {
                         if let Some(seq) = seq_from_ref {
                             if out_fields.len() >= 4 {
-                                out_fields[3] = seq.to_string();
-                                stats.replaced_ref += 1;
+                                rewrite_ref(&mut out_fields[3], seq, opts.ref_rewrite, &mut stats);
                             }
                         } else {
                             stats.missing_seq += 1;
                         }
                     }
 
-                    // 3) POS：来自 alignment.tsv 的 distance + position + 1；若缺失则回退到原始 POS 或 reference start
+                    // 3) POS：按 --pos-mode 由 alignment.tsv 计算（默认 distance + position + 1）；若缺失则回退到 reference start
                     let mut pos_set = false;
-                    if let Some(a) = aln_info {
-                        // POS calculation rules:
-                        // - If distance is extremely large (> 1_000_000_000), use position directly.
-                        // - Otherwise, compute (distance + 1) as unsigned offset and add to position.
-                        //   Special case: distance == -1 => offset = 0 (same node), so POS = position.
-                        //   Any distance < -1 is clamped to behave like -1 to avoid u64 wrap.
-                        let new_pos: u64 = if a.distance > 1_000_000_000 {
-                            a.position
-                        } else {
-                            let offset = if a.distance >= -1 {
-                                (a.distance + 1) as u64
-                            } else {
-                                0u64
-                            };
-                            offset.saturating_add(a.position)
-                        };
+                    if let Some(new_pos) = aln_info.and_then(|a| opts.pos_mode.aligned_pos(a)) {
                         if out_fields.len() >= 2 {
-                            out_fields[1] = new_pos.to_string();
+                            out_fields[1] = opts.aln_coord_base.to_vcf_pos(new_pos).to_string();
                             stats.replaced_pos += 1;
                             pos_set = true;
                         }
                         stats.used_aln_map += 1;
                     }
                     if !pos_set {
-                        if let Some(start_val) =
-                            REF_NODE2START.get().and_then(|m| m.get(&node_id)).copied()
-                        {
+                        if let Some(start_val) = reference.start.get(&node_id).copied() {
+                            // extract writes 0-based starts: a node at start 0 is POS 1
                             if out_fields.len() >= 2 {
-                                out_fields[1] = start_val.to_string();
+                                out_fields[1] = CoordBase::Zero.to_vcf_pos(start_val).to_string();
                                 stats.replaced_pos += 1;
                                 pos_set = true;
                             }
@@ -404,6 +802,17 @@ pub fn stream_replace_chrom_to_tmp(
                         stats.missing_start += 1;
                     }
 
+                    // --use-end: SV records move from the node's first base to its last
+                    if opts.use_end && pos_set && is_sv_record(&out_fields) {
+                        match (node_len, out_fields[1].parse::<u64>()) {
+                            (Some(len), Ok(start)) if len > 0 => {
+                                out_fields[1] = (start + len - 1).to_string();
+                                stats.end_pos += 1;
+                            }
+                            _ => stats.end_missing_len += 1,
+                        }
+                    }
+
                     // --oink: if this node is not on reference (distance != -1), set ALT to "oink"
                     if oink {
                         if let Some(a) = aln_info {
@@ -415,7 +824,19 @@ pub fn stream_replace_chrom_to_tmp(
                         }
                     }
 
-                    writeln!(writer, "{}", out_fields.join("\t"))?;
+                    // INFO 中的 CHROM 类字段（如 CHR2）按同样规则规范化
+                    if out_fields.len() > 7 {
+                        out_fields[7] = normalize_info_chrom(&out_fields[7], opts);
+                    }
+
+                    if opts.keep_gt_only {
+                        keep_gt_only_fields(&mut out_fields);
+                    }
+
+                    if opts.check_sorted {
+                        track_pos_order(&mut last_pos, &out_fields, &mut stats);
+                    }
+                    write!(writer, "{}{}", out_fields.join("\t"), eol())?;
                     stats.replaced += 1;
                     wrote = true;
                 } else {
@@ -426,49 +847,209 @@ pub fn stream_replace_chrom_to_tmp(
         }
 
         if !wrote {
-            match apply_ignore_rules(fields[0], ignore_level) {
-                Some(norm_chr) => {
-                    let mut out_line = String::new();
-                    out_line.push_str(&norm_chr);
-                    for f in &fields[1..] {
-                        out_line.push('\t');
-                        out_line.push_str(f);
+            // --on-unmapped: the record to write, if any
+            let out_fields: Option<Vec<String>> = match opts.on_unmapped {
+                OnUnmapped::Keep => match apply_ignore_rules(fields[0], ignore_level) {
+                    Some(norm_chr) => {
+                        let mut out_fields: Vec<String> =
+                            fields.iter().map(|s| s.to_string()).collect();
+                        out_fields[0] = opts.chrom_case.apply(&norm_chr);
+                        if out_fields.len() > 7 {
+                            out_fields[7] = normalize_info_chrom(&out_fields[7], opts);
+                        }
+                        if opts.keep_gt_only {
+                            keep_gt_only_fields(&mut out_fields);
+                        }
+                        Some(out_fields)
+                    }
+                    None => {
+                        stats.skipped += 1;
+                        None
                     }
-                    writeln!(writer, "{out_line}")?;
-                    stats.unmapped += 1;
+                },
+                OnUnmapped::Drop => {
+                    stats.unmapped_dropped += 1;
+                    None
                 }
-                _none => {
-                    stats.skipped += 1;
+                OnUnmapped::Passthrough => Some(fields.iter().map(|s| s.to_string()).collect()),
+            };
+            // --unmapped-out lists dropped unmapped records too, but not those --ignore rejects
+            let listed = out_fields.is_some() || opts.on_unmapped == OnUnmapped::Drop;
+            if let Some(out_fields) = out_fields {
+                if opts.check_sorted {
+                    track_pos_order(&mut last_pos, &out_fields, &mut stats);
                 }
+                write!(writer, "{}{}", out_fields.join("\t"), eol())?;
+                stats.unmapped += 1;
+            }
+            if let (true, Some(w)) = (listed, unmapped_w.as_mut()) {
+                let node = node_id_opt.map_or(".".to_string(), |n| n.to_string());
+                write!(w, "{}\t{}{}", fields[0], node, eol())?;
             }
         }
     }
 
-    eprintln!(
-        "[summary] total={} replaced={} unmapped={} skipped={} chrom={} pos={} id={} ref={} missing_start={} missing_seq={} via_ref={} via_aln={}",
-        stats.total,
-        stats.replaced,
-        stats.unmapped,
-        stats.skipped,
-        stats.replaced_chrom,
-        stats.replaced_pos,
-        stats.replaced_id,
-        stats.replaced_ref,
-        stats.missing_start,
-        stats.missing_seq,
-        stats.used_ref_map,
-        stats.used_aln_map
-    );
+    progress.finish();
+
+    if opts.check_sorted && stats.pos_decreasing + stats.pos_collisions > 0 {
+        eprintln!(
+            "[warn] --check-sorted: {} record(s) with POS below the previous record on the same CHROM, {} with the same POS; run `sort` before indexing",
+            stats.pos_decreasing, stats.pos_collisions
+        );
+    }
+
+    let malformed = stats.short_lines + stats.column_mismatch + stats.bad_pos;
+    if malformed > 0 {
+        eprintln!(
+            "[warn] Malformed data lines: {} with fewer than 8 columns, {} with a column count different from #CHROM, {} with a non-numeric POS; use --strict to stop at the first one",
+            stats.short_lines, stats.column_mismatch, stats.bad_pos
+        );
+    }
+
+    let mut summary = vec![
+        ("total", stats.total),
+        ("replaced", stats.replaced),
+        ("unmapped", stats.unmapped),
+        ("skipped", stats.skipped),
+        ("chrom", stats.replaced_chrom),
+        ("pos", stats.replaced_pos),
+        ("id", stats.replaced_id),
+        ("kept_id", stats.kept_id),
+        ("ref", stats.replaced_ref),
+        ("ref_matching", stats.ref_matching),
+        ("missing_start", stats.missing_start),
+        ("missing_seq", stats.missing_seq),
+        ("via_ref", stats.used_ref_map),
+        ("via_aln", stats.used_aln_map),
+        ("short_lines", stats.short_lines),
+        ("column_mismatch", stats.column_mismatch),
+        ("bad_pos", stats.bad_pos),
+    ];
+    if opts.on_unmapped == OnUnmapped::Drop {
+        summary.push(("unmapped_dropped", stats.unmapped_dropped));
+    }
+    if opts.use_end {
+        summary.push(("end_pos", stats.end_pos));
+        summary.push(("end_missing_len", stats.end_missing_len));
+    }
+    if opts.check_sorted {
+        summary.push(("pos_decreasing", stats.pos_decreasing));
+        summary.push(("pos_collisions", stats.pos_collisions));
+    }
+    crate::stats::emit("align", &summary);
 
     writer.flush()?;
+    if let Some(mut w) = unmapped_w {
+        w.flush()?;
+    }
     Ok(stats)
 }
 
+/// Whether a (trimmed) TSV line is a comment under `--tsv-comment-prefix`; empty prefix disables.
+fn is_comment_line(trimmed: &str, comment_prefix: &str) -> bool {
+    !comment_prefix.is_empty() && trimmed.starts_with(comment_prefix)
+}
+
+/// Throughput of one `--bench` phase
+fn report_throughput(phase: &str, bytes: u64, lines: u64, secs: f64) {
+    let secs = secs.max(1e-9);
+    println!(
+        "[bench] {phase}: {:.1} MB, {} lines in {:.3}s -> {:.1} MB/s, {:.0} lines/s",
+        bytes as f64 / 1e6,
+        lines,
+        secs,
+        bytes as f64 / 1e6 / secs,
+        lines as f64 / secs
+    );
+}
+
+/// `align --dry-run`: the full transform with every write discarded (`--unmapped-out` included),
+/// returning the same stats a real run would report.
+pub fn dry_run_stream(
+    vcf_path: &str,
+    node2aln: &HashMap<u64, AlnInfo>,
+    reference: &ReferenceMaps,
+    gfa: Option<&Gfa<u32, (), ()>>,
+    opts: &StreamOptions,
+) -> Result<StreamStats, Box<dyn std::error::Error>> {
+    let opts = &StreamOptions {
+        unmapped_out: None,
+        ..opts.clone()
+    };
+    stream_replace_chrom(vcf_path, std::io::sink(), node2aln, reference, gfa, opts)
+}
+
+/// `align --bench`: time a read-only pass (line split + CHROM/POS parse) and the full
+/// transform with the write step discarded, without producing any output file.
+/// Both phases run on the single-threaded streaming loop, so `--threads` does not change them.
+pub fn bench_stream(
+    vcf_path: &str,
+    node2aln: &HashMap<u64, AlnInfo>,
+    reference: &ReferenceMaps,
+    gfa: Option<&Gfa<u32, (), ()>>,
+    opts: &StreamOptions,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if crate::io_utils::is_stdin(vcf_path) {
+        return Err("--bench reads the input twice and needs a file --vcf, not stdin".into());
+    }
+    let file_bytes = std::fs::metadata(vcf_path)?.len();
+
+    // Phase 1: read + minimal parse
+    let t0 = Instant::now();
+    let reader = open_input(vcf_path)?;
+    let mut lines: u64 = 0;
+    let mut parsed: u64 = 0;
+    for line in reader.lines() {
+        let line = line?;
+        lines += 1;
+        if line.starts_with('#') {
+            continue;
+        }
+        let mut it = line.split('\t');
+        let chrom = it.next().unwrap_or("");
+        let pos_ok = it
+            .next()
+            .map(|p| p.trim().parse::<u64>().is_ok())
+            .unwrap_or(false);
+        if parse_node_id_from_chrom(chrom).is_some() || pos_ok {
+            parsed += 1;
+        }
+    }
+    report_throughput(
+        "read",
+        file_bytes,
+        lines,
+        t0.elapsed().as_secs_f64(),
+    );
+    println!("[bench] read: {parsed} record(s) with a parseable node id");
+
+    // Phase 2: full transform, output discarded
+    let t1 = Instant::now();
+    // No files are written while benchmarking
+    let stats = dry_run_stream(vcf_path, node2aln, reference, gfa, opts)?;
+    report_throughput(
+        "transform",
+        file_bytes,
+        lines,
+        t1.elapsed().as_secs_f64(),
+    );
+    println!(
+        "[bench] transform: total={}, replaced={}, unmapped={}",
+        stats.total, stats.replaced, stats.unmapped
+    );
+    println!("[bench] both phases are single-threaded; --threads does not affect these rates");
+    Ok(())
+}
+
 /// 读取 alignment.tsv（首行为表头或数据）。
 /// 需要列：node、path，以及可选列 distance、position；
 /// - 自动探测列名（不区分大小写）。
 /// - 若无表头，则按默认索引解析：node(0), distance(1), position(2), path(4)。
-pub fn read_alignment_tsv(path: &str) -> Result<HashMap<u64, AlnInfo>, Box<dyn std::error::Error>> {
+/// - 以 `comment_prefix` 开头的行（任意位置）视为注释并跳过；空前缀表示不识别注释。
+pub fn read_alignment_tsv(
+    path: &str,
+    comment_prefix: &str,
+) -> Result<HashMap<u64, AlnInfo>, Box<dyn std::error::Error>> {
     let f = File::open(path)?;
     let reader = BufReader::new(f);
 
@@ -485,7 +1066,7 @@ pub fn read_alignment_tsv(path: &str) -> Result<HashMap<u64, AlnInfo>, Box<dyn s
     for (_line_no, line) in reader.lines().enumerate() {
         let line = line?;
         let trimmed = line.trim();
-        if trimmed.is_empty() {
+        if trimmed.is_empty() || is_comment_line(trimmed, comment_prefix) {
             continue;
         }
 
@@ -558,24 +1139,40 @@ pub fn read_alignment_tsv(path: &str) -> Result<HashMap<u64, AlnInfo>, Box<dyn s
 /// 读取 reference.tsv（extract产物），支持4列和6列格式：
 /// 4列: node, start, end, path
 /// 6列: node, start, end, seq, length, path
-/// - 返回 node->path（用于向后兼容）
-/// - 同时写入全局 OnceLock：node->start, node->seq, node->path（供流式替换使用）
-pub fn read_reference_tsv(path: &str) -> Result<HashMap<u64, String>, Box<dyn std::error::Error>> {
+/// - 返回 `ReferenceMaps`：node->path, node->start, node->seq（每次调用独立，供流式替换使用）
+/// - 以 `comment_prefix` 开头的行（任意位置）视为注释并跳过；空前缀表示不识别注释。
+pub fn read_reference_tsv(
+    path: &str,
+    comment_prefix: &str,
+) -> Result<ReferenceMaps, Box<dyn std::error::Error>> {
     let f = File::open(path)?;
     let reader = BufReader::new(f);
 
     let mut map_path: HashMap<u64, String> = HashMap::new();
     let mut map_start: HashMap<u64, u64> = HashMap::new();
+    let mut map_end: HashMap<u64, u64> = HashMap::new();
     let mut map_seq: HashMap<u64, String> = HashMap::new();
 
-    for (i, line) in reader.lines().enumerate() {
+    let mut first_row = true;
+    // `extract --with-strand` / `--with-gc` 在 path 之后追加列（按表头计数）
+    let mut trailing_cols = 0usize;
+    for line in reader.lines() {
         let line = line?;
         let trimmed = line.trim();
-        if trimmed.is_empty() {
+        if trimmed.is_empty() || is_comment_line(trimmed, comment_prefix) {
             continue;
         }
-        // 跳过表头（以 "node\t" 开头）
-        if i == 0 && trimmed.to_ascii_lowercase().starts_with("node\t") {
+        // 跳过表头（首个非注释行，以 "node\t" 开头）
+        let is_first = std::mem::replace(&mut first_row, false);
+        if is_first && trimmed.to_ascii_lowercase().starts_with("node\t") {
+            let cols: Vec<String> = trimmed
+                .split('\t')
+                .map(|c| c.trim().to_ascii_lowercase())
+                .collect();
+            trailing_cols = cols
+                .iter()
+                .rposition(|c| c == "path")
+                .map_or(0, |i| cols.len() - 1 - i);
             continue;
         }
         let fields: Vec<&str> = trimmed.split('\t').collect();
@@ -594,8 +1191,13 @@ pub fn read_reference_tsv(path: &str) -> Result<HashMap<u64, String>, Box<dyn st
             map_start.insert(node, s);
         }
 
-        // path 在最后一列
-        let path_col = fields.len() - 1;
+        // end 在第3列（索引2）
+        if let Ok(e) = fields[2].trim().parse::<u64>() {
+            map_end.insert(node, e);
+        }
+
+        // path 在最后一列（之后有追加列时向前偏移）
+        let path_col = fields.len().saturating_sub(1 + trailing_cols);
         let path_val = fields[path_col].trim().to_string();
         if !path_val.is_empty() {
             map_path.insert(node, path_val);
@@ -610,10 +1212,48 @@ pub fn read_reference_tsv(path: &str) -> Result<HashMap<u64, String>, Box<dyn st
         }
     }
 
-    // 写入 OnceLock（若已存在则保持原样）
-    let _ = REF_NODE2PATH.set(map_path.clone());
-    let _ = REF_NODE2START.set(map_start);
-    let _ = REF_NODE2SEQ.set(map_seq);
+    Ok(ReferenceMaps {
+        path: map_path,
+        start: map_start,
+        end: map_end,
+        seq: map_seq,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn aln(distance: i64, position: u64) -> AlnInfo {
+        AlnInfo {
+            path: "chr1".to_string(),
+            distance,
+            position,
+        }
+    }
+
+    #[test]
+    fn sum_on_path_uses_position() {
+        assert_eq!(PosMode::Sum.aligned_pos(&aln(-1, 100)), Some(100));
+        // below -1 is clamped instead of wrapping
+        assert_eq!(PosMode::Sum.aligned_pos(&aln(-7, 100)), Some(100));
+        assert_eq!(PosMode::Sum.aligned_pos(&aln(3, 200)), Some(204));
+    }
+
+    #[test]
+    fn sum_ignores_unaligned_distances() {
+        let at_limit = PosMode::Sum.aligned_pos(&aln(UNALIGNED_DISTANCE, 50));
+        assert_eq!(at_limit, Some(UNALIGNED_DISTANCE as u64 + 51));
+        assert_eq!(
+            PosMode::Sum.aligned_pos(&aln(UNALIGNED_DISTANCE + 1, 50)),
+            Some(50)
+        );
+        assert_eq!(PosMode::Sum.aligned_pos(&aln(i64::MAX, 50)), Some(50));
+    }
 
-    Ok(map_path)
+    #[test]
+    fn other_modes_ignore_distance() {
+        assert_eq!(PosMode::Position.aligned_pos(&aln(i64::MAX, 50)), Some(50));
+        assert_eq!(PosMode::Start.aligned_pos(&aln(3, 50)), None);
+    }
 }
@@ -0,0 +1,103 @@
+use flate2::read::MultiGzDecoder;
+use flate2::write::GzEncoder;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+/// `--vcf -`: read the VCF from stdin
+pub const STDIN_PATH: &str = "-";
+
+pub fn is_stdin(path: &str) -> bool {
+    path == STDIN_PATH
+}
+
+/// Open an input VCF as a line reader.
+///
+/// `-` is stdin (gzip is detected from the magic bytes, so `bcftools view -Oz ... | ... -v -`
+/// works); a `.gz` path is decompressed on the fly (multi-member, so bgzip too); anything else
+/// is read as plain text.
+pub fn open_input(path: &str) -> std::io::Result<Box<dyn BufRead>> {
+    if is_stdin(path) {
+        let mut stdin = BufReader::new(std::io::stdin().lock());
+        let is_gz = stdin.fill_buf()?.starts_with(&[0x1f, 0x8b]);
+        return Ok(if is_gz {
+            Box::new(BufReader::new(MultiGzDecoder::new(stdin)))
+        } else {
+            Box::new(stdin)
+        });
+    }
+    let f = File::open(path)?;
+    let ext = Path::new(path)
+        .extension()
+        .and_then(|s| s.to_str())
+        .unwrap_or("");
+    if ext.eq_ignore_ascii_case("gz") {
+        Ok(Box::new(BufReader::new(MultiGzDecoder::new(f))))
+    } else {
+        Ok(Box::new(BufReader::new(f)))
+    }
+}
+
+/// File name used to derive default output names; stdin has none, so it reads as `stdin.vcf`
+/// (e.g. `sort -v -` writes `./stdin.sorted.vcf`).
+pub fn name_for_defaults(path: &str) -> &str {
+    if is_stdin(path) { "stdin.vcf" } else { path }
+}
+
+/// Path of a temp/spool file for `out_path`: `<out_path><suffix>` next to the output, or in
+/// `$TMPDIR` when the input is stdin.
+pub fn temp_path(input: &str, out_path: &str, suffix: &str) -> String {
+    if !is_stdin(input) {
+        return format!("{out_path}{suffix}");
+    }
+    let name = Path::new(out_path)
+        .file_name()
+        .and_then(|s| s.to_str())
+        .unwrap_or("output.vcf");
+    std::env::temp_dir()
+        .join(format!("{name}.{}{suffix}", std::process::id()))
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// A final output file, gzip-compressed while it is written when `gzip` is set (`align --gzip`),
+/// so no plain copy is left to compress afterwards.
+pub enum OutputFile {
+    Plain(BufWriter<File>),
+    Gz(GzEncoder<BufWriter<File>>),
+}
+
+impl OutputFile {
+    pub fn create(path: &str, gzip: bool) -> std::io::Result<Self> {
+        let w = BufWriter::new(File::create(path)?);
+        Ok(if gzip {
+            OutputFile::Gz(GzEncoder::new(w, flate2::Compression::default()))
+        } else {
+            OutputFile::Plain(w)
+        })
+    }
+
+    /// Flush the file, writing the gzip trailer first when compressing
+    pub fn finish(self) -> std::io::Result<()> {
+        match self {
+            OutputFile::Plain(mut w) => w.flush(),
+            OutputFile::Gz(enc) => enc.finish()?.flush(),
+        }
+    }
+}
+
+impl Write for OutputFile {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match self {
+            OutputFile::Plain(w) => w.write(buf),
+            OutputFile::Gz(w) => w.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self {
+            OutputFile::Plain(w) => w.flush(),
+            OutputFile::Gz(w) => w.flush(),
+        }
+    }
+}
@@ -0,0 +1,93 @@
+//! Library side of `gfa2bin-aligner`: the VCF <-> graph path transforms behind the CLI, callable
+//! from other Rust programs without shelling out.
+//!
+//! The items re-exported at the crate root are the stable API and take plain paths and option
+//! structs (no `clap` types):
+//! - `align`: [`read_reference_tsv`] (a [`ReferenceMaps`]) + [`read_alignment_tsv`] build the node
+//!   map, then [`stream_replace_chrom_to_tmp`] rewrites a VCF with [`StreamOptions`] and returns
+//!   [`StreamStats`]; each call uses only the maps it is given, so several references can be
+//!   loaded in one process
+//! - `header`: [`header_run`] with [`HeaderOptions`], returning a [`HeaderReport`]
+//! - `unique`: [`run_cleanning`]
+//! - `rename`: [`run_rename`] with [`RenameOptions`]
+//!
+//! Process-wide settings (line separator, stats format, progress, `--autosomes`, `--chrom-map`,
+//! `--quiet`) are set once, before the first call, through [`set_line_sep`], [`set_stats_format`],
+//! [`set_progress_interval`], [`set_autosomes`], [`load_chrom_map`] and [`set_quiet`]. Unlike the
+//! reference maps they cannot differ between calls: [`set_autosomes`] and [`load_chrom_map`]
+//! return an error instead of silently keeping the first value.
+
+// First, so `log_info!` is in scope in every module below
+#[macro_use]
+mod quiet;
+
+mod chrom;
+mod header;
+mod io_stream;
+mod io_utils;
+mod line_sep;
+mod name;
+mod progress;
+mod stats;
+mod unique;
+
+pub use chrom::{ChromCase, apply_ignore_rules, load_chrom_map, set_autosomes};
+pub use header::{HeaderOptions, HeaderReport, header_run};
+pub use io_stream::{
+    AlnInfo, CoordBase, OnUnmapped, OriginalPosTo, PosMode, RefRewrite, ReferenceMaps, SkipMatcher,
+    StreamOptions, StreamStats, UNALIGNED_DISTANCE, build_skip_matchers, dry_run_stream,
+    read_alignment_tsv, read_reference_tsv, stream_replace_chrom_to_tmp,
+};
+pub use line_sep::set_line_sep;
+pub use name::{QtlKey, RenameOptions, run_rename};
+pub use progress::set_interval as set_progress_interval;
+pub use quiet::{is_quiet, set_quiet};
+pub use stats::set_format as set_stats_format;
+pub use unique::{UniqueMode, run_cleanning};
+
+/// Crate version, shown by `--version` and written to the `##source` header line
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Helpers shared with the `gfa2bin-aligner` binary, whose subcommand entry points (the only
+/// code that touches `clap`) live in the binary crate. Clap-free, but not part of the stable API.
+#[doc(hidden)]
+pub mod bin_support {
+    pub mod chrom {
+        pub use crate::chrom::{
+            ChromCase, apply_ignore_rules, autosomes, check_chrom_names, escape_chrom,
+            extract_chr_token, invalid_chrom_reason, is_std_autosome, load_chrom_map,
+            set_autosomes,
+        };
+    }
+    pub mod header {
+        pub use crate::header::{HeaderOptions, default_headed_path, header_run, info_items};
+    }
+    pub mod io_stream {
+        pub use crate::io_stream::{
+            AlnInfo, CoordBase, OnUnmapped, OriginalPosTo, PosMode, RefRewrite, ReferenceMaps,
+            StreamOptions, bench_stream, build_skip_matchers, check_path_names, dry_run_stream,
+            read_alignment_tsv, read_reference_tsv, stream_replace_chrom_to_tmp,
+        };
+    }
+    pub mod io_utils {
+        pub use crate::io_utils::{OutputFile, is_stdin, name_for_defaults, open_input, temp_path};
+    }
+    pub mod line_sep {
+        pub use crate::line_sep::{eol, set_line_sep};
+    }
+    pub mod name {
+        pub use crate::name::{QtlKey, RenameOptions, make_key, run_rename};
+    }
+    pub mod progress {
+        pub use crate::progress::{Progress, set_interval};
+    }
+    pub mod quiet {
+        pub use crate::quiet::set_quiet;
+    }
+    pub mod stats {
+        pub use crate::stats::{emit, set_format};
+    }
+    pub mod unique {
+        pub use crate::unique::{UniqueMode, run_cleanning};
+    }
+}
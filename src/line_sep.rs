@@ -0,0 +1,18 @@
+use std::sync::OnceLock;
+
+static LINE_SEP: OnceLock<&'static str> = OnceLock::new();
+
+/// Set the process-wide output line terminator from the `--line-sep` value (first call wins)
+pub fn set_line_sep(value: &str) {
+    let sep = if value.eq_ignore_ascii_case("crlf") {
+        "\r\n"
+    } else {
+        "\n"
+    };
+    let _ = LINE_SEP.set(sep);
+}
+
+/// Terminator appended to every output line: "\n" (default) or "\r\n"
+pub fn eol() -> &'static str {
+    LINE_SEP.get().copied().unwrap_or("\n")
+}
@@ -1,8 +1,120 @@
+use crate::line_sep::eol;
 use log::info;
+use rayon::prelude::*;
 use std::collections::HashSet;
 use std::fs::File;
-use std::io::{BufRead, BufReader, BufWriter, Write};
-use rayon::prelude::*;
+use std::io::{BufRead, BufWriter, Write};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Per-site genotype class counts gathered while filtering; one row of the `--gt-counts` report.
+#[derive(Debug, Clone, Copy, Default)]
+struct GtCounts {
+    c_00: usize,
+    c_01: usize,
+    c_10: usize,
+    c_11: usize,
+    denom: usize,
+}
+
+/// Records filtered per parallel block; bounds memory to one block instead of the whole VCF
+const MAF_BLOCK_LINES: usize = 100_000;
+
+/// Contigs treated as haploid/hemizygous by `--ploidy-from-contig` unless `--haploid-contigs` is given.
+/// Names are compared as `contig_key`s: chrX, chrY and chrM (incl. MT).
+const DEFAULT_HAPLOID_CONTIGS: &[&str] = &["X", "Y", "M"];
+
+/// Key under which contig names are compared (body CHROM, `##contig` ID, `--haploid-contigs`),
+/// so `chr1`, `1`, `CHR1`, `GRCh38#0#chr1` (or a `--chrom-map` alias) all meet: the level-5
+/// `apply_ignore_rules` name, also tried with a `chr` prefix for bare names such as `Y` or `MT`;
+/// non-standard contigs keep their trimmed name.
+fn contig_key(raw: &str) -> String {
+    let raw = raw.trim();
+    crate::chrom::apply_ignore_rules(raw, 5)
+        .or_else(|| crate::chrom::apply_ignore_rules(&format!("chr{raw}"), 5))
+        .unwrap_or_else(|| raw.to_string())
+}
+
+/// Whether `cnt / denom` lies within the closed interval [thresh, 1 - thresh]
+fn in_band(cnt: usize, denom: usize, thresh: f64) -> bool {
+    let p = (cnt as f64) / (denom as f64);
+    p >= thresh && p <= (1.0 - thresh)
+}
+
+/// Multiallelic site (some call has an allele index above 1): count every allele index over the
+/// valid calls and apply the [thresh, 1 - thresh] rule to each observed allele's frequency.
+/// Returns (valid calls, keep). Diploid calls contribute two alleles, hemizygous calls on
+/// haploid contigs one.
+fn multiallelic_keep(samples: &[&str], gt_idx: usize, haploid: bool, thresh: f64) -> (usize, bool) {
+    let mut allele_counts: Vec<usize> = Vec::new();
+    let mut n_alleles = 0usize;
+    let mut calls = 0usize;
+    for sample in samples {
+        let gt = match sample.split(':').nth(gt_idx) {
+            Some(gt) if !gt.is_empty() => gt,
+            _ => continue,
+        };
+        let alleles: Option<Vec<usize>> = gt.split(['/', '|']).map(|a| a.parse().ok()).collect();
+        let alleles = match alleles {
+            Some(v) if v.len() == 2 || (haploid && v.len() == 1) => v,
+            _ => continue, // missing or malformed
+        };
+        calls += 1;
+        for a in alleles {
+            if a >= allele_counts.len() {
+                allele_counts.resize(a + 1, 0);
+            }
+            allele_counts[a] += 1;
+            n_alleles += 1;
+        }
+    }
+    let keep = calls > 0
+        && allele_counts
+            .iter()
+            .filter(|c| **c > 0)
+            .all(|c| in_band(*c, n_alleles, thresh));
+    (calls, keep)
+}
+
+/// Exact Hardy-Weinberg test p-value for one biallelic site (Wigginton, Cutler & Abecasis 2005):
+/// the probability, given the allele counts, of a het count at most as likely as the observed one.
+fn hwe_exact_p(hets: usize, hom_a: usize, hom_b: usize) -> f64 {
+    let (hom_rare, hom_common) = (hom_a.min(hom_b), hom_a.max(hom_b));
+    let n = hets + hom_rare + hom_common;
+    if n == 0 {
+        return 1.0;
+    }
+    let rare = 2 * hom_rare + hets;
+    let mut probs = vec![0.0f64; rare + 1];
+
+    // Start at the most likely het count (same parity as `rare`) and walk outwards
+    let mut mid = rare * (2 * n - rare) / (2 * n);
+    if mid % 2 != rare % 2 {
+        mid += 1;
+    }
+    probs[mid] = 1.0;
+    let mut sum = 1.0;
+
+    let (mut h, mut hr, mut hc) = (mid, (rare - mid) / 2, n - mid - (rare - mid) / 2);
+    while h >= 2 {
+        probs[h - 2] = probs[h] * (h * (h - 1)) as f64 / (4 * (hr + 1) * (hc + 1)) as f64;
+        sum += probs[h - 2];
+        h -= 2;
+        hr += 1;
+        hc += 1;
+    }
+    let (mut h, mut hr, mut hc) = (mid, (rare - mid) / 2, n - mid - (rare - mid) / 2);
+    while h + 2 <= rare {
+        probs[h + 2] = probs[h] * (4 * hr * hc) as f64 / ((h + 2) * (h + 1)) as f64;
+        sum += probs[h + 2];
+        h += 2;
+        hr -= 1;
+        hc -= 1;
+    }
+
+    let observed = probs[hets];
+    let p: f64 = probs.iter().filter(|p| **p <= observed).sum::<f64>() / sum;
+    p.min(1.0)
+}
 
 /// Filter VCF by per-GT proportions and print to stdout.
 ///
@@ -12,136 +124,368 @@ use rayon::prelude::*;
 /// {"0/0","0/1","1/0","1/1"} that actually appears (count > 0), compute its proportion p = count / denom.
 /// Keep the line iff every appearing category's p lies within the closed interval [thresh, 1 - thresh].
 /// Missing (./.) and non-{0,1} allele genotypes are excluded from denom.
-pub fn maf_main(matches: &clap::ArgMatches, output_path: &str) -> Result<(), Box<dyn std::error::Error>> {
+/// This allele-order-preserving rule is the default; `--unordered-het` merges 0/1 and 1/0 into a
+/// single het category (count 0/1 + 1/0) before the check, as usual for unphased data.
+///
+/// Multiallelic sites (any call with an allele index above 1, e.g. 0/2 or 1/2) switch to allele
+/// frequencies instead: every observed allele's frequency over all called alleles must lie in
+/// [thresh, 1 - thresh], and denom counts every valid call. Biallelic sites keep the genotype rule.
+///
+/// With `--ploidy-from-contig`, sites on haploid contigs (default chrX, chrY, chrM; override with
+/// `--haploid-contigs`) also count hemizygous single-allele calls: "0" as 0/0 and "1" as 1/1.
+/// Diploid calls on those contigs are still counted as usual.
+///
+/// With `--min-call-rate f`, a site is also dropped when denom / #samples < f (missing and
+/// non-{0,1} calls at biallelic sites count as uncalled); such sites are counted as
+/// `low_call_rate` in the summary.
+///
+/// With `--hwe p`, biallelic sites whose exact Hardy-Weinberg p-value (0/0, 0/1+1/0, 1/1 counts)
+/// is below `p` are dropped as well (`low_hwe` in the summary). Multiallelic sites and sites on
+/// haploid contigs (`--ploidy-from-contig`) are not tested.
+///
+/// Records are streamed in blocks of `MAF_BLOCK_LINES`, each filtered in parallel; kept lines
+/// are spooled to `<output>.body.tmp` and copied after the header, so memory stays at one block.
+pub fn maf_main(
+    matches: &clap::ArgMatches,
+    output_path: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
     let vcf = matches.get_one::<String>("vcf").unwrap().as_str();
     let thresh = matches
         .get_one::<String>("thresh")
         .map(|s| s.parse::<f64>().unwrap_or(0.05))
         .unwrap_or(0.05);
-    let _threads = matches.get_one::<String>("threads").map(|s| s.as_str()).unwrap_or("1");
+    let threads = matches
+        .get_one::<String>("threads")
+        .map(|s| s.as_str())
+        .unwrap_or("1");
+    let num_threads: usize = threads.parse().unwrap_or(1).max(1);
+    let gt_counts_path = matches.get_one::<String>("gt-counts").map(|s| s.as_str());
+    let ploidy_from_contig = matches.get_flag("ploidy-from-contig");
+    let min_call_rate: Option<f64> = match matches.get_one::<String>("min-call-rate") {
+        Some(s) => match s.parse::<f64>() {
+            Ok(f) if (0.0..=1.0).contains(&f) => Some(f),
+            _ => {
+                return Err(
+                    format!("--min-call-rate expects a fraction in [0, 1], got '{s}'").into(),
+                );
+            }
+        },
+        None => None,
+    };
+    let low_call_rate = AtomicU64::new(0);
+    let hwe_min_p: Option<f64> = match matches.get_one::<String>("hwe") {
+        Some(s) => match s.parse::<f64>() {
+            Ok(p) if (0.0..=1.0).contains(&p) => Some(p),
+            _ => return Err(format!("--hwe expects a p-value in [0, 1], got '{s}'").into()),
+        },
+        None => None,
+    };
+    let low_hwe = AtomicU64::new(0);
+    let unordered_het = matches.get_flag("unordered-het");
+    let haploid_contigs: HashSet<String> = match matches.get_one::<String>("haploid-contigs") {
+        Some(list) => list
+            .split(',')
+            .map(|x| x.trim())
+            .filter(|x| !x.is_empty())
+            .map(contig_key)
+            .collect(),
+        None => DEFAULT_HAPLOID_CONTIGS
+            .iter()
+            .map(|s| s.to_string())
+            .collect(),
+    };
 
     info!("Running 'gfa2bin-aligner maf'");
     // [INFO] style logging for arguments, aligned to match align_main example
-    println!("[INFO]     --vcf     = {}", vcf);
-    println!("[INFO]     --thresh  = {}", thresh);
-    println!("[INFO]     --threads = {}", _threads);
-    println!("[INFO]     --output  = {}", output_path);
-    println!("[INFO]     rule     = for GT in {{0/0,0/1,1/0,1/1}} that appear: each proportion in [thresh, 1-thresh]");
-
-    if vcf.ends_with(".gz") {
-        return Err(Box::new(std::io::Error::new(
-            std::io::ErrorKind::InvalidInput,
-            "Compressed VCF (.gz) is not supported in this subcommand yet. Please decompress first (e.g., `bgzip -d file.vcf.gz`).",
-        )));
+    log_info!("[INFO]     --vcf     = {}", vcf);
+    log_info!("[INFO]     --thresh  = {}", thresh);
+    log_info!("[INFO]     --threads = {}", num_threads);
+    log_info!("[INFO]     --output  = {}", output_path);
+    log_info!(
+        "[INFO]     --gt-counts = {}",
+        gt_counts_path.unwrap_or("None")
+    );
+    log_info!("[INFO]     --ploidy-from-contig = {}", ploidy_from_contig);
+    log_info!("[INFO]     --min-call-rate = {:?}", min_call_rate);
+    log_info!("[INFO]     --hwe = {:?}", hwe_min_p);
+    log_info!("[INFO]     --unordered-het = {}", unordered_het);
+    if ploidy_from_contig {
+        let mut hap: Vec<&String> = haploid_contigs.iter().collect();
+        hap.sort();
+        log_info!("[INFO]     haploid contigs = {:?}", hap);
     }
+    log_info!(
+        "[INFO]     rule     = for GT in {{0/0,0/1,1/0,1/1}} that appear: each proportion in [thresh, 1-thresh]"
+    );
+    log_info!("[INFO]     multiallelic = each observed allele's frequency in [thresh, 1-thresh]");
 
-    let file = File::open(vcf)?;
-    let reader = BufReader::new(file);
-    let all_lines: Vec<String> = reader.lines().collect::<Result<_, _>>()?;
+    // Per-record decision: None for lines without samples or GT, else the counts and keep/drop.
+    // Each line is evaluated independently of the others, so blocks can be split across threads.
+    let classify = |line: &str| -> Option<(GtCounts, bool)> {
+        let cols: Vec<&str> = line.split('\t').collect();
+        if cols.len() < 10 {
+            return None; // no samples
+        }
 
-    let mut header_lines: Vec<String> = Vec::new();
-    let mut variants: Vec<(usize, String)> = Vec::new();
-    for (idx, line) in all_lines.into_iter().enumerate() {
-        if line.starts_with('#') {
-            header_lines.push(line);
-        } else {
-            variants.push((idx, line));
+        // FORMAT column and GT index
+        let format = cols[8];
+        let mut gt_idx_opt: Option<usize> = None;
+        for (i, key) in format.split(':').enumerate() {
+            if key == "GT" {
+                gt_idx_opt = Some(i);
+                break;
+            }
         }
-    }
+        let gt_idx = if let Some(i) = gt_idx_opt {
+            i
+        } else {
+            return None;
+        };
 
-    // Process variant lines in parallel, but keep (idx) to restore original order
-    let kept: Vec<(usize, String, String)> = variants
-        .par_iter()
-        .filter_map(|(idx, line)| {
-            let cols: Vec<&str> = line.split('\t').collect();
-            if cols.len() < 10 {
-                return None; // no samples
-            }
-
-            // FORMAT column and GT index
-            let format = cols[8];
-            let mut gt_idx_opt: Option<usize> = None;
-            for (i, key) in format.split(':').enumerate() {
-                if key == "GT" {
-                    gt_idx_opt = Some(i);
-                    break;
-                }
+        // Haploid contig: accept hemizygous single-allele calls as well
+        let haploid = ploidy_from_contig && haploid_contigs.contains(&contig_key(cols[0]));
+
+        let mut denom = 0usize; // # valid non-missing GT with alleles in {0,1}
+        let mut c_00 = 0usize;
+        let mut c_01 = 0usize; // strictly 0/1
+        let mut c_10 = 0usize; // strictly 1/0
+        let mut c_11 = 0usize;
+        // Set when a call has an allele index above 1; handled after the fast loop
+        let mut multiallelic = false;
+
+        for sample in cols.iter().skip(9) {
+            if sample.is_empty() {
+                continue;
             }
-            let gt_idx = if let Some(i) = gt_idx_opt {
-                i
+            let parts: Vec<&str> = sample.split(':').collect();
+            if gt_idx >= parts.len() {
+                continue;
+            }
+            let gt = parts[gt_idx];
+
+            // Skip missing GT
+            if gt == "./." || gt == ".|." || gt == "." {
+                continue;
+            }
+
+            // Normalize phased to unphased (keep allele order) without borrowing a temporary
+            let gt_norm: String = if gt.contains('|') {
+                gt.replace('|', "/")
             } else {
-                return None;
+                gt.to_string()
             };
 
-            let mut denom = 0usize; // # valid non-missing GT with alleles in {0,1}
-            let mut c_00 = 0usize;
-            let mut c_01 = 0usize; // strictly 0/1
-            let mut c_10 = 0usize; // strictly 1/0
-            let mut c_11 = 0usize;
-
-            for sample in cols.iter().skip(9) {
-                if sample.is_empty() { continue; }
-                let parts: Vec<&str> = sample.split(':').collect();
-                if gt_idx >= parts.len() { continue; }
-                let gt = parts[gt_idx];
-
-                // Skip missing GT
-                if gt == "./." || gt == ".|." || gt == "." { continue; }
-
-                // Normalize phased to unphased (keep allele order) without borrowing a temporary
-                let gt_norm: String = if gt.contains('|') { gt.replace('|', "/") } else { gt.to_string() };
-
-                let ab: Vec<&str> = gt_norm.split('/').collect();
-                if ab.len() != 2 { continue; }
-                let a = ab[0];
-                let b = ab[1];
-
-                // Only keep biallelic {0,1} diploid genotypes
-                if (a != "0" && a != "1") || (b != "0" && b != "1") { continue; }
-
-                denom += 1;
-                if a == "0" && b == "0" {
-                    c_00 += 1;
-                } else if a == "0" && b == "1" {
-                    c_01 += 1;
-                } else if a == "1" && b == "0" {
-                    c_10 += 1;
-                } else if a == "1" && b == "1" {
-                    c_11 += 1;
+            let ab: Vec<&str> = gt_norm.split('/').collect();
+            if haploid && ab.len() == 1 {
+                match ab[0] {
+                    "0" => {
+                        denom += 1;
+                        c_00 += 1;
+                    }
+                    "1" => {
+                        denom += 1;
+                        c_11 += 1;
+                    }
+                    other if other.parse::<usize>().is_ok() => multiallelic = true,
+                    _ => {}
                 }
+                continue;
+            }
+            if ab.len() != 2 {
+                continue;
+            }
+            let a = ab[0];
+            let b = ab[1];
+
+            // Only keep biallelic {0,1} diploid genotypes
+            if (a != "0" && a != "1") || (b != "0" && b != "1") {
+                multiallelic |= a.parse::<usize>().is_ok() && b.parse::<usize>().is_ok();
+                continue;
             }
 
-            if denom == 0 { return None; }
+            denom += 1;
+            if a == "0" && b == "0" {
+                c_00 += 1;
+            } else if a == "0" && b == "1" {
+                c_01 += 1;
+            } else if a == "1" && b == "0" {
+                c_10 += 1;
+            } else if a == "1" && b == "1" {
+                c_11 += 1;
+            }
+        }
 
-            let check = |cnt: usize, denom: usize, thresh: f64| -> bool {
-                let p = (cnt as f64) / (denom as f64);
-                p >= thresh && p <= (1.0 - thresh)
+        let (counts, keep) = if multiallelic {
+            let (calls, keep) = multiallelic_keep(&cols[9..], gt_idx, haploid, thresh);
+            (
+                GtCounts {
+                    c_00,
+                    c_01,
+                    c_10,
+                    c_11,
+                    denom: calls,
+                },
+                keep,
+            )
+        } else if denom == 0 {
+            (
+                GtCounts {
+                    c_00,
+                    c_01,
+                    c_10,
+                    c_11,
+                    denom,
+                },
+                false,
+            )
+        } else {
+            // For each category that appears, require its proportion to be within [thresh, 1-thresh]
+            // (--unordered-het: 0/1 and 1/0 form a single het category)
+            let hets_ok = if unordered_het {
+                let het = c_01 + c_10;
+                het == 0 || in_band(het, denom, thresh)
+            } else {
+                (c_01 == 0 || in_band(c_01, denom, thresh))
+                    && (c_10 == 0 || in_band(c_10, denom, thresh))
             };
+            let keep = hets_ok
+                && (c_00 == 0 || in_band(c_00, denom, thresh))
+                && (c_11 == 0 || in_band(c_11, denom, thresh));
+            (
+                GtCounts {
+                    c_00,
+                    c_01,
+                    c_10,
+                    c_11,
+                    denom,
+                },
+                keep,
+            )
+        };
 
-            // For each category that appears, require its proportion to be within [thresh, 1-thresh]
-            if (c_00 > 0 && !check(c_00, denom, thresh))
-                || (c_01 > 0 && !check(c_01, denom, thresh))
-                || (c_10 > 0 && !check(c_10, denom, thresh))
-                || (c_11 > 0 && !check(c_11, denom, thresh))
+        // --hwe: exact test on the biallelic genotype counts
+        let keep = match hwe_min_p {
+            Some(min_p)
+                if keep
+                    && !multiallelic
+                    && !haploid
+                    && hwe_exact_p(c_01 + c_10, c_00, c_11) < min_p =>
             {
-                return None;
+                low_hwe.fetch_add(1, Ordering::Relaxed);
+                false
+            }
+            _ => keep,
+        };
+
+        // --min-call-rate: too few called samples drops the site whatever its frequencies
+        let n_samples = cols.len() - 9;
+        let keep = match min_call_rate {
+            Some(min) if (counts.denom as f64) < min * n_samples as f64 => {
+                low_call_rate.fetch_add(1, Ordering::Relaxed);
+                false
             }
+            _ => keep,
+        };
 
-            // Keep: return (idx, chrom, line)
-            Some((*idx, cols[0].to_string(), line.clone()))
-        })
-        .collect::<Vec<(usize, String, String)>>();
+        Some((counts, keep))
+    };
 
-    // Restore original VCF order and gather kept chroms
-    let mut kept = kept; // make mutable
-    kept.sort_by_key(|(idx, _, _)| *idx);
+    // Dedicated pool so --threads is honored; results do not depend on its size (see below)
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(num_threads)
+        .build()?;
 
+    // Optional per-site genotype count report: chrom pos id c00 c01 c10 c11 denom kept
+    let mut report = match gt_counts_path {
+        Some(path) => {
+            let mut w = BufWriter::new(File::create(path)?);
+            write!(
+                w,
+                "chrom\tpos\tid\tc00\tc01\tc10\tc11\tdenom\tkept{}",
+                eol()
+            )?;
+            Some(w)
+        }
+        None => None,
+    };
+
+    // Stream the records in blocks: each block is filtered in parallel and its kept lines are
+    // spooled in input order, so only one block is in memory at a time. The header is written
+    // last because its ##contig lines depend on which CHROMs kept a record.
+    let spool_path = format!("{output_path}.body.tmp");
+    let mut spool = BufWriter::new(File::create(&spool_path)?);
+    let mut header_lines: Vec<String> = Vec::new();
     let mut kept_chroms: HashSet<String> = HashSet::new();
-    for (_, chrom, _) in kept.iter() {
-        kept_chroms.insert(chrom.clone());
+    let mut n_variants: u64 = 0;
+    let mut n_considered: u64 = 0;
+    let mut n_kept: u64 = 0;
+    let mut block: Vec<String> = Vec::with_capacity(MAF_BLOCK_LINES);
+    let mut lines = crate::io_utils::open_input(vcf)?.lines();
+    let mut at_end = false;
+    while !at_end {
+        match lines.next().transpose()? {
+            Some(line) if line.starts_with('#') => {
+                header_lines.push(line);
+                continue;
+            }
+            Some(line) => {
+                block.push(line);
+                if block.len() < MAF_BLOCK_LINES {
+                    continue;
+                }
+            }
+            None => at_end = true,
+        }
+
+        // Results come back in block order, so the output is identical for any --threads
+        let results: Vec<Option<(GtCounts, bool)>> =
+            pool.install(|| block.par_iter().map(|line| classify(line)).collect());
+        n_variants += block.len() as u64;
+        for (line, res) in block.drain(..).zip(results) {
+            let (c, keep) = match res {
+                Some(r) => r,
+                None => continue,
+            };
+            n_considered += 1;
+            let mut cols = line.split('\t');
+            let chrom = cols.next().unwrap_or(".");
+            if let Some(w) = report.as_mut() {
+                let pos = cols.next().unwrap_or(".");
+                let id = cols.next().unwrap_or(".");
+                write!(
+                    w,
+                    "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}{}",
+                    chrom,
+                    pos,
+                    id,
+                    c.c_00,
+                    c.c_01,
+                    c.c_10,
+                    c.c_11,
+                    c.denom,
+                    if keep { 1 } else { 0 },
+                    eol()
+                )?;
+            }
+            if keep {
+                n_kept += 1;
+                // Normalized, so a `##contig=<ID=1>` header still matches `chr1` records (and vice versa)
+                kept_chroms.insert(contig_key(chrom));
+                write!(spool, "{}{}", line, eol())?;
+            }
+        }
     }
+    spool.flush()?;
+    drop(spool);
 
-    let variant_lines: Vec<String> = kept.into_iter().map(|(_, _, line)| line).collect();
+    if let (Some(mut w), Some(path)) = (report, gt_counts_path) {
+        w.flush()?;
+        log_info!(
+            "[INFO] Wrote genotype counts for {} sites to {}",
+            n_considered,
+            path
+        );
+    }
 
     let file_out = File::create(output_path)?;
     let mut out = BufWriter::new(file_out);
@@ -151,20 +495,32 @@ pub fn maf_main(matches: &clap::ArgMatches, output_path: &str) -> Result<(), Box
             // Extract ID from contig line
             if let Some(id_start) = header.find("ID=") {
                 let id_sub = &header[id_start + 3..];
-                let id_end = id_sub.find(|c: char| c == ',' || c == '>').unwrap_or(id_sub.len());
+                let id_end = id_sub
+                    .find(|c: char| c == ',' || c == '>')
+                    .unwrap_or(id_sub.len());
                 let chrom_id = &id_sub[..id_end];
-                if !kept_chroms.contains(chrom_id) {
+                if !kept_chroms.contains(&contig_key(chrom_id)) {
                     continue;
                 }
             }
         }
-        writeln!(out, "{}", header)?;
-    }
-
-    for variant_line in variant_lines {
-        writeln!(out, "{}", variant_line)?;
+        write!(out, "{}{}", header, eol())?;
     }
 
+    std::io::copy(&mut File::open(&spool_path)?, &mut out)?;
     out.flush()?;
+    let _ = std::fs::remove_file(&spool_path);
+
+    crate::stats::emit(
+        "maf",
+        &[
+            ("variants", n_variants),
+            ("considered", n_considered),
+            ("kept", n_kept),
+            ("low_call_rate", low_call_rate.load(Ordering::Relaxed)),
+            ("low_hwe", low_hwe.load(Ordering::Relaxed)),
+            ("dropped", n_variants - n_kept),
+        ],
+    );
     Ok(())
 }
@@ -1,12 +1,23 @@
+// `log_info!` / `elog_info!` for the subcommand modules below
+#[macro_use]
+extern crate gfa2bin_aligner;
+
+// Subcommands that parse `clap` matches live in the binary; the library stays clap-free
 mod extract;
-mod header;
-mod io_stream;
+mod header_main;
 mod maf;
-mod name;
 mod nearest_main;
+mod query;
+mod reheader;
 mod sort_main;
+mod tabix;
 
 use gfa_reader::Gfa;
+use gfa2bin_aligner::VERSION;
+use gfa2bin_aligner::bin_support::{
+    chrom, header, io_stream, io_utils, line_sep, name, progress, quiet, stats, unique,
+};
+use line_sep::eol;
 
 use clap::{Arg, Command};
 use std::collections::{HashMap, HashSet};
@@ -16,8 +27,49 @@ use std::path::Path;
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let app = Command::new("gfa2bin-aligner")
-        .version("0.0.4")
+        .version(VERSION)
         .about("Graph VCF toolkit extends from `gfa2bin`: align and extract modes. Additionally, when reference.tsv is provided, CHROM is set to path, POS may be replaced by the node’s start coordinate from reference.tsv, and ID may be set to the original POS (implementation depends on io_stream).")
+        .arg(
+            Arg::new("stats-format")
+                .long("stats-format")
+                .help("Format of the final stats printed on stderr by align/header/extract/maf: text ([summary] key=value) or json (one JSON object per line)")
+                .value_parser(["text", "json"])
+                .default_value("text")
+                .global(true),
+        )
+        .arg(
+            Arg::new("line-sep")
+                .long("line-sep")
+                .help("Line terminator of every output file: lf or crlf")
+                .value_parser(["lf", "crlf"])
+                .default_value("lf")
+                .global(true),
+        )
+        .arg(
+            Arg::new("autosomes")
+                .long("autosomes")
+                .help("Number of numbered chromosomes treated as standard (1..N, plus X, Y, M) by --ignore 4/5, rename keys and sort's chromosome order, e.g. 19 for mouse")
+                .value_parser(clap::value_parser!(u32).range(1..=1000))
+                .default_value("22")
+                .global(true),
+        )
+        .arg(
+            Arg::new("progress")
+                .long("progress")
+                .help("Print lines processed and MB/s to stderr every SECS seconds (default 5) while align/sort/header stream their input")
+                .value_name("SECS")
+                .num_args(0..=1)
+                .default_missing_value("5")
+                .value_parser(clap::value_parser!(u64).range(1..))
+                .global(true),
+        )
+        .arg(
+            Arg::new("quiet")
+                .long("quiet")
+                .help("Suppress [info] messages (stdout and stderr); [warn]/[error] lines, stats and --progress still go to stderr")
+                .action(clap::ArgAction::SetTrue)
+                .global(true),
+        )
         .subcommand(
             Command::new("align")
                 .about("Align VCF with alignment TSV, replacing #CHROM by path, with filter/sort/threads. Optionally use reference.tsv as fallback.")
@@ -26,25 +78,66 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                 .arg(Arg::new("reference").short('r').long("reference").help("reference.tsv for CHROM mapping/header synthesis; required unless --no-header. Supports 4- or 6-column TSV (last column always path); optionally uses third column start to set VCF POS during alignment.").num_args(1).required_unless_present("no-header"))
                 .arg(Arg::new("gfa").short('g').long("gfa").help("GFA file to source REF sequences; if provided, REF bases are taken from GFA and reference.tsv sequence is ignored").num_args(1))
                 .arg(Arg::new("skip").short('s').long("skip").help("Comma-separated substrings. A record is dropped if its raw #CHROM contains any of them.").num_args(1))
-                .arg(Arg::new("ignore").long("ignore").help("Ignore/normalize CHROM level [0-5] (applied after --skip): 0=keep, 1=has 'chr', 2=token [0-9XYM], 3=no suffix, 4=only chr{1..22,X,Y,M}, 5=only {1..22,X,Y,M}").num_args(1).default_value("4"))
+                .arg(Arg::new("skip-ci").long("skip-ci").help("Match --skip substrings ignoring case").action(clap::ArgAction::SetTrue))
+                .arg(Arg::new("skip-regex").long("skip-regex").help("Regex; a record is dropped if its raw #CHROM matches it anywhere (e.g. '^chrUn_' or '_random$'). Repeat for several patterns").num_args(1).action(clap::ArgAction::Append))
+                .arg(Arg::new("ignore").long("ignore").help("Ignore/normalize CHROM level [0-5] (applied after --skip): 0=keep, 1=has 'chr', 2=token [0-9XYM], 3=no suffix, 4=only chr{1..N,X,Y,M}, 5=only {1..N,X,Y,M} with 1..N (--autosomes, default 22)").num_args(1).default_value("4"))
+                .arg(Arg::new("chrom-map").long("chrom-map").help("TSV of raw<TAB>canonical CHROM aliases (e.g. CM000663.2<TAB>chr1) applied before --ignore; names not in the map fall back to --ignore").num_args(1))
                 .arg(Arg::new("output").short('o').long("output").help("Output VCF file path (default: <input>.replaced.vcf)"))
+                .arg(Arg::new("output-dir").long("output-dir").help("Directory for default-named outputs (<input>.replaced.vcf, .sorted.vcf, .headed.vcf) instead of the input's directory").num_args(1).conflicts_with("output"))
                 .arg(Arg::new("sort").long("sort").help("Sort VCF records (default by POS ascending)").action(clap::ArgAction::SetTrue))
-                .arg(Arg::new("prefix").short('p').long("prefix").help("Column to sort by: keyword (CHROM, POS, ID, REF, ALT, QUAL, FILTER, INFO, FORMAT) or 0-based index (default: POS)").default_value("POS"))
-                .arg(Arg::new("reverse").long("reverse").help("Sort descending (big to small)").action(clap::ArgAction::SetTrue))
+                .arg(Arg::new("prefix").short('p').long("prefix").help("Comma-separated columns to sort by, compared left to right: keyword (CHROM, POS, ID, REF, ALT, QUAL, FILTER, INFO, FORMAT) or 0-based index, each optionally suffixed :asc or :desc, e.g. CHROM,POS:desc (default: POS)").default_value("POS"))
+                .arg(Arg::new("reverse").long("reverse").help("Sort descending (big to small); flips the direction of every --prefix key").action(clap::ArgAction::SetTrue))
                 .arg(Arg::new("threads").short('T').long("threads").help("Number of threads for Rayon").num_args(1))
                 .arg(Arg::new("no-header").long("no-header").help("Do not synthesize a header on the combined VCF (by default, header is added using reference.tsv)").action(clap::ArgAction::SetTrue))
+                .arg(Arg::new("require-map-coverage").long("require-map-coverage").help("Fail if replaced/total records is below this fraction (0.0-1.0)").num_args(1))
+                .arg(Arg::new("tsv-comment-prefix").long("tsv-comment-prefix").help("Lines of alignment/reference TSVs starting with this prefix are comments (empty string disables)").default_value("#"))
+                .arg(Arg::new("node-offset").long("node-offset").help("Constant by which VCF node ids exceed graph node ids (e.g. 1 for a 1-based VCF against a 0-based graph); VCF id N is looked up as N - offset in alignment/reference/GFA, whose ids are used as-is").num_args(1).allow_negative_numbers(true).default_value("0"))
+                .arg(Arg::new("ref-rewrite").long("ref-rewrite").help("When to overwrite REF with the GFA/reference.tsv sequence: always, if-different (case-insensitive compare first, keeps soft-masked REF) or never").value_parser(["always", "if-different", "never"]).default_value("always"))
+                .arg(Arg::new("bench").long("bench").help("Benchmark only: time reading and the full transform (output discarded) and report MB/s and lines/s; writes no files. Both phases are single-threaded, so the rates do not scale with --threads").action(clap::ArgAction::SetTrue))
+                .arg(Arg::new("dry-run").long("dry-run").help("Run the full mapping/normalization pass and print the stats summary without writing any file (no output, temp, --unmapped-out or header step)").action(clap::ArgAction::SetTrue))
+                .arg(Arg::new("gzip").short('z').long("gzip").help("Gzip-compress the final output file and append .gz to its name").action(clap::ArgAction::SetTrue))
+                .arg(Arg::new("escape-special").long("escape-special").help("Percent-encode tabs, whitespace and characters illegal in VCF CHROM in path names instead of rejecting them").action(clap::ArgAction::SetTrue))
+                .arg(Arg::new("check-sorted").long("check-sorted").help("Count records whose rewritten POS decreases or repeats within their CHROM and warn (nothing is reordered; follow with `sort` if needed)").action(clap::ArgAction::SetTrue))
+                .arg(Arg::new("unmapped-out").long("unmapped-out").help("Write a TSV (chrom, node) of the records whose node id found no path, to diagnose gaps in alignment.tsv/reference.tsv; node is '.' when none could be parsed").num_args(1))
+                .arg(Arg::new("keep-id").long("keep-id").help("Keep the original ID of rewritten records instead of overwriting it with the original POS (CHROM/POS are still rewritten; --original-pos-to info still adds OPOS)").action(clap::ArgAction::SetTrue))
+                .arg(Arg::new("pos-mode").long("pos-mode").help("POS of mapped records: sum (alignment distance + position + 1; distance -1 = on the path), position (alignment position only) or start (node start from reference.tsv)").value_parser(["sum", "position", "start"]).default_value("sum"))
+                .arg(Arg::new("use-end").long("use-end").help("For SV records (symbolic ALT or INFO SVTYPE), move POS to the last base of the node: POS + node length - 1, with the length from the GFA, the reference.tsv seq or its end - start").action(clap::ArgAction::SetTrue))
+                .arg(Arg::new("on-unmapped").long("on-unmapped").help("Records whose node has no path: keep (CHROM rewritten by --ignore, dropped when rejected), drop, or passthrough (original line untouched)").value_parser(["keep", "drop", "passthrough"]).default_value("keep"))
+                .arg(Arg::new("strict").long("strict").help("Stop with an error at the first malformed data line (fewer than 8 columns, column count different from #CHROM, non-numeric POS) instead of counting it in the summary").action(clap::ArgAction::SetTrue))
+                .arg(Arg::new("coord-base").long("coord-base").help("Coordinate base of the alignment TSV positions: 1 (used as is) or 0 (1 is added to the POS). reference.tsv starts are always 0-based and get 1 added").value_parser(["0", "1"]).default_value("1"))
+                .arg(Arg::new("original-pos-to").long("original-pos-to").help("Where to record the original POS of rewritten records: id (ID := POS), info (INFO OPOS=<pos>, declared in the header), both or none").value_parser(["id", "info", "both", "none"]).default_value("id"))
                 .arg(Arg::new("oink").short('k').long("oink").help("Oink the node if it is not on reference").action(clap::ArgAction::SetTrue))
+                .arg(Arg::new("chrom-case").long("chrom-case").help("Casing of the final CHROM (body and synthesized contig lines): keep, lower or upper").value_parser(["keep", "lower", "upper"]).default_value("keep"))
+                .arg(Arg::new("keep-gt-only").long("keep-gt-only").help("Rewrite FORMAT to GT and keep only the GT subfield of every sample (drops AD, PL, GL, ...)").action(clap::ArgAction::SetTrue))
+                .arg(Arg::new("normalize-info-chrom").long("normalize-info-chrom").help("Comma-separated INFO keys (e.g. CHR2) whose values are normalized with the same --ignore rules as CHROM").num_args(1))
         )
         .subcommand(
             Command::new("extract")
                 .about("Extract all paths (P & converted W) from a GFA into TSV (4 or 6 columns): node, start, end, [seq, length,] path. Supports --ignore to filter/normalize path names. Default output: <dir_of_gfa>/reference.tsv")
-                .arg(Arg::new("gfa").short('g').long("gfa").help("Input GFA file").required(true))
+                .arg(Arg::new("gfa").short('g').long("gfa").help("Input GFA file (plain or .gz)").required(true))
                 .arg(Arg::new("output").short('o').long("output").help("Output TSV file (4 or 6 columns). Last column is path. Default: <dir_of_gfa>/reference.tsv") )
                 .arg(Arg::new("threads").short('T').long("threads").help("Number of threads for parsing GFA").num_args(1))
+                .arg(Arg::new("format").long("format").help("Output format: tsv (node, start, end, seq, length, path) or bed (path, start, end, node; 0-based half-open, no header; default name reference.bed)").value_parser(["tsv", "bed"]).default_value("tsv"))
+                .arg(Arg::new("unordered").long("unordered").help("Write each path as soon as it is rendered (less memory, nondeterministic path order) instead of in GFA path order").action(clap::ArgAction::SetTrue))
+                .arg(Arg::new("low-mem").long("low-mem").help("Stream the GFA in two passes instead of loading the graph (bounded memory, single-threaded, slower); segment sequences are spooled to <output>.seq.tmp").action(clap::ArgAction::SetTrue).conflicts_with_all(["report-duplicate-nodes", "unordered"]))
+                .arg(Arg::new("with-strand").long("with-strand").help("Append a strand column (+/-) with the orientation of each node step in its path").action(clap::ArgAction::SetTrue))
+                .arg(Arg::new("with-gc").long("with-gc").help("Append gc_frac (G/C among non-N bases) and n_count columns computed from each node's sequence (TSV only)").action(clap::ArgAction::SetTrue))
+                .arg(Arg::new("revcomp").long("revcomp").help("Write the reverse complement of the seq column for nodes traversed in reverse").action(clap::ArgAction::SetTrue))
+                .arg(Arg::new("report-ignore-drops").long("report-ignore-drops").help("Print how many paths --ignore kept and dropped, broken down by reason (no_chr, bad_token, suffix, non_standard)").action(clap::ArgAction::SetTrue))
+                .arg(Arg::new("escape-special").long("escape-special").help("Percent-encode tabs, whitespace and characters illegal in VCF CHROM in path names instead of rejecting them").action(clap::ArgAction::SetTrue))
+                .arg(Arg::new("chrom-map").long("chrom-map").help("TSV of raw<TAB>canonical CHROM aliases (e.g. CM000663.2<TAB>chr1) applied before --ignore; names not in the map fall back to --ignore").num_args(1))
+                .arg(Arg::new("samples").long("samples").help("Comma-separated PanSN selectors: keep only paths whose leading #-fields match one of them, e.g. HG002 (every haplotype) or HG002#1").num_args(1).conflicts_with("samples-regex"))
+                .arg(Arg::new("samples-regex").long("samples-regex").help("Keep only paths whose full name matches this regex, e.g. '^HG00[23]#1#'").num_args(1))
+                .arg(Arg::new("min-node-length").long("min-node-length").help("Write no row for nodes shorter than this many bp (the node still counts towards the start/end of later nodes unless --reindex)").num_args(1))
+                .arg(Arg::new("max-node-length").long("max-node-length").help("Write no row for nodes longer than this many bp (the node still counts towards the start/end of later nodes unless --reindex)").num_args(1))
+                .arg(Arg::new("strict-coords").long("strict-coords").help("Fail (and remove the output) when a path's coordinates cannot be trusted: a node without sequence (missing segment), a W line whose SeqStart-SeqEnd differs from the summed node lengths, or a node/orientation count mismatch. Without it these are [warn]ings").action(clap::ArgAction::SetTrue))
+                .arg(Arg::new("reindex").long("reindex").help("With --min/--max-node-length: compute start/end as if filtered nodes were absent from the path (coordinates no longer match the full path)").action(clap::ArgAction::SetTrue))
+                .arg(Arg::new("summary").long("summary").help("Write a per-path TSV (gfa_path, path, nodes, length) of the paths kept by --ignore instead of listing them on stderr").num_args(1))
+                .arg(Arg::new("report-duplicate-nodes").long("report-duplicate-nodes").help("Write a TSV of node ids whose sequence/length differs between occurrences (corrupt graph check)").num_args(1))
                 .arg(
                     Arg::new("ignore")
                         .long("ignore")
-                        .help("Ignore/normalize CHROM level [0-5] applied to path names: 0=keep, 1=has 'chr', 2=token [0-9XYM], 3=no suffix, 4=only chr{1..22,X,Y,M}, 5=only {1..22,X,Y,M}")
+                        .help("Ignore/normalize CHROM level [0-5] applied to path names: 0=keep, 1=has 'chr', 2=token [0-9XYM], 3=no suffix, 4=only chr{1..N,X,Y,M}, 5=only {1..N,X,Y,M} with 1..N (--autosomes, default 22)")
                         .num_args(1)
                         .default_value("4")
                 )
@@ -54,23 +147,61 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                 .about("Synthesize a VCF header using reference.tsv (columns: node, start, end[, seq, length], path) and keys inferred from the VCF body; writes a new VCF with merged header")
                 .arg(Arg::new("vcf").short('v').long("vcf").help("Input VCF/VCF-like file (plain text)").required(true))
                 .arg(Arg::new("reference").short('r').long("reference").help("reference.tsv produced by 'extract' (4 or 6 cols: node, start, end[, seq, length], path; last column is path)").required(true))
-                .arg(Arg::new("output").short('o').long("output").help("Output VCF path (default: <input>.withheader.vcf)"))
+                .arg(Arg::new("output").short('o').long("output").help("Output VCF path (default: <input>.headed.vcf)"))
+                .arg(Arg::new("output-dir").long("output-dir").help("Directory for the default-named output (<input>.headed.vcf) instead of the input's directory").num_args(1).conflicts_with("output"))
+                .arg(Arg::new("chrom-case").long("chrom-case").help("Casing of the final CHROM (body and ##contig IDs): keep, lower or upper").value_parser(["keep", "lower", "upper"]).default_value("keep"))
+                .arg(Arg::new("contig-length-override").long("contig-length-override").help("TSV of name<TAB>length (e.g. a .genome file) whose lengths supersede reference.tsv for ##contig lines; names go through --ignore first").num_args(1))
+                .arg(Arg::new("chrom-map").long("chrom-map").help("TSV of raw<TAB>canonical CHROM aliases (e.g. CM000663.2<TAB>chr1) applied before --ignore; names not in the map fall back to --ignore").num_args(1))
+                .arg(Arg::new("dict").long("dict").help("TSV of ID<TAB>Number<TAB>Type<TAB>Description whose entries replace the inferred ##INFO/##FORMAT definitions for those IDs").num_args(1))
+                .arg(Arg::new("fileformat").long("fileformat").help("##fileformat version to write, e.g. VCFv4.3 (default: the input's, else VCFv4.2); overrides the input's with a warning").num_args(1))
+                .arg(Arg::new("extra-header").long("extra-header").help("File of ## lines (##reference=, ##PEDIGREE, ##ALT, ...) inserted verbatim before #CHROM; lines already in the header are skipped").num_args(1))
+                .arg(Arg::new("infer-samples").long("infer-samples").help("Scan at most N sample columns per record when inferring FORMAT Number/Type (default: all); lowers the cost on wide VCFs").num_args(1).value_parser(clap::value_parser!(u64).range(1..)))
+                .arg(Arg::new("contig-length-from-pos").long("contig-length-from-pos").help("For contigs seen in the body but missing from reference.tsv, declare the max POS seen as their length instead of omitting it").action(clap::ArgAction::SetTrue))
+                .arg(Arg::new("drop-untyped-info").long("drop-untyped-info").help("Omit inferred INFO definitions for keys that only ever appear with an empty value (no type can be inferred) instead of emitting an empty-description placeholder").action(clap::ArgAction::SetTrue))
                 .arg(Arg::new("threads").short('T').long("threads").help("Number of threads for Rayon").num_args(1))
                 .arg(
                     Arg::new("ignore")
                         .long("ignore")
-                        .help("Ignore/normalize CHROM level [0-5] (applied after --skip): 0=keep, 1=has 'chr', 2=token [0-9XYM], 3=no suffix, 4=only chr{1..22,X,Y,M}, 5=only {1..22,X,Y,M}")
+                        .help("Ignore/normalize CHROM level [0-5] (applied after --skip): 0=keep, 1=has 'chr', 2=token [0-9XYM], 3=no suffix, 4=only chr{1..N,X,Y,M}, 5=only {1..N,X,Y,M} with 1..N (--autosomes, default 22)")
                         .num_args(1)
                         .default_value("4")
                 )
         )
         .subcommand(
             Command::new("sort")
-                .about("Sort a VCF file; default is by POS (ascending). Use --prefix to choose column and --reverse for descending.")
+                .about("Sort a VCF file; default is chromosome-aware by CHROM, POS, ID (ascending). Use --prefix to choose columns and --reverse for descending.")
                 .arg(Arg::new("vcf").short('v').long("vcf").help("Input VCF file").required(true))
-                .arg(Arg::new("prefix").short('p').long("prefix").help("Column to sort by: keyword (CHROM, POS, ID, REF, ALT, QUAL, FILTER, INFO, FORMAT) or 0-based index (default: POS)").default_value("POS"))
-                .arg(Arg::new("reverse").long("reverse").help("Sort descending").action(clap::ArgAction::SetTrue))
+                .arg(Arg::new("prefix").short('p').long("prefix").help("Comma-separated columns to sort by, compared left to right: keyword (CHROM, POS, ID, REF, ALT, QUAL, FILTER, INFO, FORMAT) or 0-based index, each optionally suffixed :asc or :desc. POS alone (the default) keeps the chromosome-aware CHROM, POS, ID order").default_value("POS"))
+                .arg(Arg::new("reverse").long("reverse").help("Sort descending; flips the direction of every --prefix key (and of --info-key)").action(clap::ArgAction::SetTrue))
+                .arg(Arg::new("info-key").long("info-key").help("Sort first by the numeric value of this INFO field (e.g. AF, DP; first value of a list), optionally suffixed :asc or :desc; ties keep the --prefix order").num_args(1))
+                .arg(Arg::new("info-missing").long("info-missing").help("Where records without a numeric --info-key value go").value_parser(["first", "last"]).default_value("last").requires("info-key"))
                 .arg(Arg::new("output").short('o').long("output").help("Output VCF path (default: <input>.sorted.vcf)"))
+                .arg(Arg::new("output-dir").long("output-dir").help("Directory for the sorted output instead of the input's directory").num_args(1))
+                .arg(Arg::new("chrom-case").long("chrom-case").help("Casing of the final CHROM (body and ##contig IDs): keep, lower or upper").value_parser(["keep", "lower", "upper"]).default_value("keep"))
+                .arg(Arg::new("collapse-equal-alt").long("collapse-equal-alt").help("Merge sorted records with identical CHROM/POS/REF/ALT (matched among the records of one position), taking the union of their INFO keys").action(clap::ArgAction::SetTrue))
+                .arg(Arg::new("collapse-keep").long("collapse-keep").help("Which INFO value wins on a key conflict when collapsing").value_parser(["first", "last"]).default_value("last").requires("collapse-equal-alt"))
+                .arg(Arg::new("dedup").long("dedup").help("Drop sorted records with the same CHROM/POS/REF/ALT as another record at that position, keeping one of them unchanged").action(clap::ArgAction::SetTrue).conflicts_with("collapse-equal-alt"))
+                .arg(Arg::new("dedup-keep").long("dedup-keep").help("Which duplicate --dedup keeps: the first in sort order or the one with the highest QUAL (ties and missing QUAL keep the earlier one)").value_parser(["first", "max-qual"]).default_value("first").requires("dedup"))
+                .arg(Arg::new("tabix").long("tabix").help("Write the sorted output as BGZF (<output>.gz) with a tabix .tbi index next to it; requires the default coordinate order").action(clap::ArgAction::SetTrue))
+                .arg(Arg::new("max-mem").long("max-mem").help("Memory budget in bytes for sorting: bodies (estimated uncompressed) at or above it use the external merge sort, with sorted runs of at most this size").num_args(1))
+                .arg(Arg::new("buffered-sort-threshold").long("buffered-sort-threshold").help("Input size in bytes (gz inputs estimated x4) at or above which an external merge sort is used instead of sorting in memory (default: 2 GiB)").num_args(1))
+                .arg(Arg::new("threads").short('T').long("threads").help("Threads for the in-memory sort (bodies of 100,000+ records; needs the rayon feature); the output is the same for any value").default_value("1"))
+        )
+        .subcommand(
+            Command::new("query")
+                .about("Extract the records of one region (CHROM[:START[-END]], 1-based, inclusive) from a VCF (plain or .gz), keeping the header")
+                .arg(Arg::new("vcf").short('v').long("vcf").help("Input VCF file (plain or .gz)").required(true))
+                .arg(Arg::new("region").short('r').long("region").help("Region CHROM[:START[-END]], e.g. chr1:10000-20000").required(true))
+                .arg(Arg::new("output").short('o').long("output").help("Output VCF path (default: <input>.query.vcf)"))
+                .arg(Arg::new("assume-sorted").long("assume-sorted").help("Input is coordinate-sorted: stop reading once past the end of the region instead of scanning the whole file").action(clap::ArgAction::SetTrue))
+        )
+        .subcommand(
+            Command::new("reheader")
+                .about("Replace the header of a VCF wholesale: write the lines of --header followed by the body of --vcf (plain or .gz)")
+                .arg(Arg::new("header").long("header").help("File with the new header lines (## meta lines and #CHROM), plain or .gz").required(true))
+                .arg(Arg::new("vcf").short('v').long("vcf").help("Input VCF whose body is kept (plain or .gz)").required(true))
+                .arg(Arg::new("output").short('o').long("output").help("Output VCF path (default: <input>.reheaded.vcf)"))
+                .arg(Arg::new("force").long("force").help("Write even if the #CHROM column count does not match the body").action(clap::ArgAction::SetTrue))
         )
         .subcommand(
             Command::new("nearest")
@@ -96,6 +227,18 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                     .long("thresh")
                     .help("Threshold proportion for non-0/0 genotypes")
                     .default_value("0.05"))
+                .arg(Arg::new("min-call-rate")
+                    .long("min-call-rate")
+                    .help("Drop sites whose fraction of called samples (valid genotypes / samples) is below this value")
+                    .num_args(1))
+                .arg(Arg::new("hwe")
+                    .long("hwe")
+                    .help("Drop biallelic sites whose exact Hardy-Weinberg equilibrium p-value is below this value (off by default)")
+                    .num_args(1))
+                .arg(Arg::new("unordered-het")
+                    .long("unordered-het")
+                    .help("Treat 0/1 and 1/0 as one heterozygous category in the proportion rule (default keeps allele order)")
+                    .action(clap::ArgAction::SetTrue))
                 .arg(Arg::new("threads")
                     .short('T')
                     .long("threads")
@@ -105,6 +248,19 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                     .short('o')
                     .long("output")
                     .help("Output VCF file path (default: <input>.filtered.vcf)"))
+                .arg(Arg::new("ploidy-from-contig")
+                    .long("ploidy-from-contig")
+                    .help("Count hemizygous single-allele calls (0 as 0/0, 1 as 1/1) on haploid contigs (default chrX, chrY, chrM)")
+                    .action(clap::ArgAction::SetTrue))
+                .arg(Arg::new("haploid-contigs")
+                    .long("haploid-contigs")
+                    .help("Comma-separated contigs treated as haploid by --ploidy-from-contig ('chr' prefix optional, MT = M; default: X,Y,M)")
+                    .num_args(1)
+                    .requires("ploidy-from-contig"))
+                .arg(Arg::new("gt-counts")
+                    .long("gt-counts")
+                    .help("Write per-site genotype class counts (chrom, pos, id, c00, c01, c10, c11, denom, kept) to this TSV")
+                    .num_args(1))
         )
         .subcommand(
             Command::new("rename")
@@ -112,8 +268,8 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                 .arg(Arg::new("vcf")
                     .short('v')
                     .long("vcf")
-                    .help("Input VCF file with variant information")
-                    .required(true))
+                    .help("Input VCF file with variant information; not read when --map is given")
+                    .required_unless_present("map"))
                 .arg(Arg::new("qtl")
                     .short('q')
                     .long("qtl")
@@ -124,18 +280,85 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                     .long("threads")
                     .help("Number of threads for processing")
                     .default_value("1"))
+                .arg(Arg::new("id-delim")
+                    .long("id-delim")
+                    .help("Separator used in CHROM<d>POS<d>REF<d>ALT variant keys, for both the VCF-derived map and QTL tokens (e.g. ':' or '_')")
+                    .default_value(":"))
+                .arg(Arg::new("map-mode")
+                    .long("map-mode")
+                    .help("Keys stored in the VCF-derived map: ids-only (numeric VCF IDs only) or all (also normalized CHROM/POS/REF/ALT and POS keys)")
+                    .value_parser(["ids-only", "all"])
+                    .default_value("ids-only"))
+                .arg(Arg::new("map")
+                    .long("map")
+                    .help("Precomputed key<TAB>normalized map (e.g. a map.tsv from an earlier run) to use instead of building one from the VCF"))
+                .arg(Arg::new("key-cols")
+                    .long("key-cols")
+                    .help("1-based QTL column(s) holding the variant key: one column with the whole token (default 2), or four chrom,pos,ref,alt columns joined with --id-delim (the mapped value replaces the chrom column)"))
+                .arg(Arg::new("misses")
+                    .long("misses")
+                    .help("Write QTL tokens that matched no map entry (with their line number) to this TSV"))
+                .arg(Arg::new("unordered")
+                    .long("unordered")
+                    .action(clap::ArgAction::SetTrue)
+                    .help("Write renamed rows as soon as they are ready (fastest; row order no longer follows the QTL file)"))
                 .arg(Arg::new("output")
                     .short('o')
                     .long("output")
                     .help("Output TSV file path (default: <qtl_dir>/qtl.renamed.tsv)"))
+        )
+        .subcommand(
+            Command::new("unique")
+                .about("Trim duplicated trailing sample columns: cut the VCF at the first sample column that equals its left neighbour on the first --same variant lines (plain or .gz input)")
+                .arg(Arg::new("vcf")
+                    .short('v')
+                    .long("vcf")
+                    .help("Input VCF file (plain or .gz)")
+                    .required(true))
+                .arg(Arg::new("same")
+                    .short('n')
+                    .long("same")
+                    .help("Number of leading variant lines a column must equal its left neighbour on to be cut; 0 passes the file through untouched")
+                    .default_value("0"))
+                .arg(Arg::new("mode")
+                    .long("mode")
+                    .help("adjacent: cut at the first column equal to its left neighbour; groups: keep one column per group of identical columns, wherever they are")
+                    .value_parser(["adjacent", "groups"])
+                    .default_value("adjacent"))
+                .arg(Arg::new("keep-samples")
+                    .long("keep-samples")
+                    .help("Comma-separated sample names to keep (plus the 9 fixed columns), instead of detecting duplicates with --same/--mode; every name must be in the #CHROM header")
+                    .conflicts_with_all(["same", "mode"]))
+                .arg(Arg::new("report")
+                    .long("report")
+                    .help("Write a TSV of the dropped sample columns (sample, sample_index, column); written even when nothing is dropped"))
+                .arg(Arg::new("output")
+                    .short('o')
+                    .long("output")
+                    .help("Output VCF path (default: stdout)"))
         );
     let matches = app.get_matches();
+    if let Some(fmt) = matches.get_one::<String>("stats-format") {
+        stats::set_format(fmt);
+    }
+    if let Some(sep) = matches.get_one::<String>("line-sep") {
+        line_sep::set_line_sep(sep);
+    }
+    if let Some(n) = matches.get_one::<u32>("autosomes") {
+        chrom::set_autosomes(*n)?;
+    }
+    if let Some(secs) = matches.get_one::<u64>("progress") {
+        progress::set_interval(*secs);
+    }
+    quiet::set_quiet(matches.get_flag("quiet"));
 
     match matches.subcommand() {
         Some(("align", sub_m)) => align_main(sub_m),
         Some(("extract", sub_m)) => extract::extract_main(sub_m),
-        Some(("header", sub_m)) => header::header_main(sub_m),
+        Some(("header", sub_m)) => header_main::header_main(sub_m),
         Some(("sort", sub_m)) => sort_main::sort_main(sub_m),
+        Some(("query", sub_m)) => query::query_main(sub_m),
+        Some(("reheader", sub_m)) => reheader::reheader_main(sub_m),
         Some(("nearest", sub_m)) => nearest_main::nearest_main(sub_m),
         Some(("maf", sub_m)) => {
             // Handle --output default: <input>.filtered.vcf
@@ -147,13 +370,16 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                 .get_one::<String>("output")
                 .cloned()
                 .unwrap_or_else(|| {
-                    let p = Path::new(vcf_path);
+                    let p = Path::new(io_utils::name_for_defaults(vcf_path));
                     let parent = p.parent().unwrap_or_else(|| Path::new("."));
                     let file_name = p
                         .file_name()
                         .and_then(|s| s.to_str())
                         .unwrap_or("output.vcf");
-                    let filtered_name = if let Some(stripped) = file_name.strip_suffix(".vcf") {
+                    let filtered_name = if let Some(stripped) = file_name
+                        .strip_suffix(".vcf.gz")
+                        .or_else(|| file_name.strip_suffix(".vcf"))
+                    {
                         format!("{}.filtered.vcf", stripped)
                     } else {
                         format!("{}.filtered.vcf", file_name)
@@ -172,7 +398,8 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             maf::maf_main(sub_m, &output_path)
         }
         Some(("rename", sub_m)) => {
-            let vcf_path = sub_m.get_one::<String>("vcf").expect("VCF file required");
+            // clap requires --vcf unless --map is given, and then the VCF is never read
+            let vcf_path = sub_m.get_one::<String>("vcf").map_or("", |s| s.as_str());
             let qtl_path = sub_m.get_one::<String>("qtl").expect("QTL file required");
 
             // Handle --output default: <qtl_dir>/qtl.renamed.tsv
@@ -191,18 +418,96 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                 .get_one::<String>("threads")
                 .and_then(|s| s.parse().ok())
                 .unwrap_or(1);
+            let id_delim = sub_m
+                .get_one::<String>("id-delim")
+                .map(|s| s.as_str())
+                .unwrap_or(":");
 
-            println!("[info] [rename] Running with arguments:");
-            println!("    --vcf   : {}", vcf_path);
-            println!("    --qtl   : {}", qtl_path);
-            println!("    --output: {}", output_path);
-            println!("    --threads: {}", threads);
+            log_info!("[info] [rename] Running with arguments:");
+            log_info!("    --vcf   : {}", vcf_path);
+            log_info!("    --qtl   : {}", qtl_path);
+            log_info!("    --output: {}", output_path);
+            log_info!("    --threads: {}", threads);
+            let map_mode = sub_m
+                .get_one::<String>("map-mode")
+                .map(|s| s.as_str())
+                .unwrap_or("ids-only");
+            log_info!("    --id-delim: {}", id_delim);
+            log_info!("    --map-mode: {}", map_mode);
+            let key = match sub_m.get_one::<String>("key-cols") {
+                Some(spec) => name::QtlKey::parse(spec)?,
+                None => name::QtlKey::default(),
+            };
+            log_info!("    --key-cols: {:?}", key);
+            let misses = sub_m.get_one::<String>("misses").cloned();
+            if let Some(p) = &misses {
+                log_info!("    --misses: {}", p);
+            }
+            let unordered = sub_m.get_flag("unordered");
+            log_info!("    --unordered: {}", unordered);
+            let map = sub_m.get_one::<String>("map").cloned();
+            log_info!("    --map: {:?}", map);
+            let opts = name::RenameOptions {
+                key,
+                misses,
+                unordered,
+                map,
+            };
 
-            name::run_rename(vcf_path, qtl_path, threads)
+            name::run_rename(vcf_path, qtl_path, threads, id_delim, map_mode, &opts)
+        }
+        Some(("unique", sub_m)) => {
+            let vcf_path = sub_m.get_one::<String>("vcf").expect("VCF file required");
+            let same_raw = sub_m.get_one::<String>("same").unwrap();
+            let same: usize = same_raw
+                .trim()
+                .parse()
+                .map_err(|_| format!("--same expects a non-negative integer, got '{same_raw}'"))?;
+            let output = sub_m.get_one::<String>("output");
+
+            let mode = unique::UniqueMode::from_arg(sub_m.get_one::<String>("mode"));
+            let report = sub_m.get_one::<String>("report").map(|s| s.as_str());
+            let keep_samples: Option<Vec<String>> =
+                sub_m.get_one::<String>("keep-samples").map(|s| {
+                    s.split(',')
+                        .map(|x| x.trim().to_string())
+                        .filter(|x| !x.is_empty())
+                        .collect()
+                });
+            // Log to stderr: the VCF itself may be going to stdout
+            elog_info!("[info] [unique] --vcf {vcf_path}");
+            elog_info!("[info] [unique] --same {same}");
+            elog_info!("[info] [unique] --mode {:?}", mode);
+            elog_info!("[info] [unique] --report {}", report.unwrap_or("None"));
+            elog_info!("[info] [unique] --keep-samples {:?}", keep_samples);
+            elog_info!(
+                "[info] [unique] --output {}",
+                output.map(|s| s.as_str()).unwrap_or("stdout")
+            );
+
+            match output {
+                Some(path) => unique::run_cleanning(
+                    vcf_path,
+                    &same,
+                    mode,
+                    report,
+                    keep_samples.as_deref(),
+                    fs::File::create(path)?,
+                )?,
+                None => unique::run_cleanning(
+                    vcf_path,
+                    &same,
+                    mode,
+                    report,
+                    keep_samples.as_deref(),
+                    std::io::stdout().lock(),
+                )?,
+            }
+            Ok(())
         }
         _ => {
             println!(
-                "Please choose a subcommand: align, extract, header, sort, or nearest. Use --help for details."
+                "Please choose a subcommand: align, extract, header, query, reheader, sort, nearest, maf, rename or unique. Use --help for details."
             );
             Ok(())
         }
@@ -217,6 +522,11 @@ fn align_main(matches: &clap::ArgMatches) -> Result<(), Box<dyn std::error::Erro
 
     let gfa_path_opt: Option<&String> = matches.get_one::<String>("gfa");
 
+    // helper: append ".gz" to a final output name written through a GzEncoder (--gzip)
+    fn gz_name(p: String, gzip: bool) -> String {
+        if gzip { format!("{p}.gz") } else { p }
+    }
+
     // helper: insert ".sorted" before trailing ".vcf"; if no .vcf, append ".sorted.vcf"
     fn with_sorted_suffix(p: &str) -> String {
         if let Some(stripped) = p.strip_suffix(".vcf") {
@@ -226,13 +536,17 @@ fn align_main(matches: &clap::ArgMatches) -> Result<(), Box<dyn std::error::Erro
         }
     }
 
-    // helper: build a headed output path in the *original VCF's directory*,
+    // helper: build a headed output path in the *original VCF's directory* (or --output-dir),
     // inserting ".headed" before ".vcf" and preserving the candidate's base name.
-    fn headed_in_original_dir(original_vcf: &str, candidate_path: &str) -> String {
+    fn headed_in_original_dir(
+        original_vcf: &str,
+        candidate_path: &str,
+        output_dir: Option<&str>,
+    ) -> String {
         use std::path::Path;
-        let orig_dir = Path::new(original_vcf)
-            .parent()
-            .map(|p| p.to_path_buf())
+        let orig_dir = output_dir
+            .map(|d| Path::new(d).to_path_buf())
+            .or_else(|| Path::new(original_vcf).parent().map(|p| p.to_path_buf()))
             .unwrap_or_else(|| Path::new(".").to_path_buf());
         let candidate_name = Path::new(candidate_path)
             .file_name()
@@ -246,10 +560,22 @@ fn align_main(matches: &clap::ArgMatches) -> Result<(), Box<dyn std::error::Erro
         orig_dir.join(name).to_string_lossy().into_owned()
     }
 
+    let dry_run = matches.get_flag("dry-run");
+    let output_dir = matches.get_one::<String>("output-dir").map(|s| s.as_str());
+    match output_dir {
+        Some(d) if !dry_run => fs::create_dir_all(d)?,
+        _ => {}
+    }
     let default_output = {
-        let p = Path::new(vcf_path);
+        let p = Path::new(io_utils::name_for_defaults(vcf_path));
         if let Some(stem) = p.file_stem().and_then(|s| s.to_str()) {
-            if let Some(parent) = p.parent() {
+            // x.vcf.gz -> x.replaced.vcf
+            let stem = if vcf_path.ends_with(".gz") {
+                stem.strip_suffix(".vcf").unwrap_or(stem)
+            } else {
+                stem
+            };
+            if let Some(parent) = output_dir.map(Path::new).or_else(|| p.parent()) {
                 parent
                     .join(format!("{stem}.replaced.vcf"))
                     .to_str()
@@ -262,7 +588,7 @@ fn align_main(matches: &clap::ArgMatches) -> Result<(), Box<dyn std::error::Erro
             "output.vcf".to_owned()
         }
     };
-    let output_path = matches
+    let mut output_path = matches
         .get_one::<String>("output")
         .map(|s| s.to_owned())
         .unwrap_or(default_output);
@@ -293,16 +619,66 @@ fn align_main(matches: &clap::ArgMatches) -> Result<(), Box<dyn std::error::Erro
         .unwrap_or_else(|| "POS".to_string());
     let reverse = matches.get_flag("reverse");
     let no_header = matches.get_flag("no-header");
+    // --gzip compresses whichever file is final: the headed output, or the aligned/sorted one
+    // with --no-header. Intermediates stay plain text.
+    let gzip = matches.get_flag("gzip");
+    let gzip_final = gzip && no_header;
     let oink = matches.get_flag("oink");
+    let keep_gt_only = matches.get_flag("keep-gt-only");
+    let chrom_case = chrom::ChromCase::from_arg(matches.get_one::<String>("chrom-case"));
+    let require_map_coverage: Option<f64> = match matches.get_one::<String>("require-map-coverage")
+    {
+        Some(s) => match s.trim().parse::<f64>() {
+            Ok(f) if (0.0..=1.0).contains(&f) => Some(f),
+            _ => {
+                return Err(format!(
+                    "--require-map-coverage expects a fraction between 0 and 1, got '{s}'"
+                )
+                .into());
+            }
+        },
+        None => None,
+    };
+    let node_offset: i64 = {
+        let raw = matches.get_one::<String>("node-offset").unwrap();
+        raw.trim()
+            .parse()
+            .map_err(|_| format!("--node-offset expects an integer, got '{raw}'"))?
+    };
+    let ref_rewrite = io_stream::RefRewrite::from_arg(matches.get_one::<String>("ref-rewrite"));
+    let pos_mode = io_stream::PosMode::from_arg(matches.get_one::<String>("pos-mode"));
+    let aln_coord_base = io_stream::CoordBase::from_arg(matches.get_one::<String>("coord-base"));
+    let strict = matches.get_flag("strict");
+    let use_end = matches.get_flag("use-end");
+    let on_unmapped = io_stream::OnUnmapped::from_arg(matches.get_one::<String>("on-unmapped"));
+    let original_pos_to =
+        io_stream::OriginalPosTo::from_arg(matches.get_one::<String>("original-pos-to"));
+    let escape_special = matches.get_flag("escape-special");
+    let keep_id = matches.get_flag("keep-id");
+    let unmapped_out = matches.get_one::<String>("unmapped-out").cloned();
+    let check_sorted = matches.get_flag("check-sorted");
+    let tsv_comment_prefix = matches
+        .get_one::<String>("tsv-comment-prefix")
+        .cloned()
+        .unwrap_or_default();
+    let info_chrom_keys: HashSet<String> = matches
+        .get_one::<String>("normalize-info-chrom")
+        .map(|s| {
+            s.split(',')
+                .map(|x| x.trim().to_string())
+                .filter(|s| !s.is_empty())
+                .collect()
+        })
+        .unwrap_or_default();
 
     // Note: we will later pull `start` for POS replacement and swap POS→ID in io_stream
     let reference_path = matches.get_one::<String>("reference").map(|s| s.as_str());
 
-    println!("[info] [align] Running with arguments:");
-    println!("    --vcf      : {vcf_path}");
-    println!("    --alignment: {tsv_path}");
-    println!("    --reference: {:?}", reference_path);
-    println!(
+    log_info!("[info] [align] Running with arguments:");
+    log_info!("    --vcf      : {vcf_path}");
+    log_info!("    --alignment: {tsv_path}");
+    log_info!("    --reference: {:?}", reference_path);
+    log_info!(
         "    --output   : {output_path}{}",
         if sort_enabled {
             "  -> final: ".to_string() + &final_output_path
@@ -310,15 +686,45 @@ fn align_main(matches: &clap::ArgMatches) -> Result<(), Box<dyn std::error::Erro
             String::new()
         }
     );
-    println!("    --skip     : {skip_keywords}");
-    println!("    --ignore   : {ignore_level}");
-    println!("    --sort     : {sort_enabled}");
-    println!("    --threads  : {threads}");
-    println!("    --prefix   : {prefix_key}");
-    println!("    --reverse  : {reverse}");
-    println!("    --no-header: {}", no_header);
-    println!("    --oink     : {}", oink);
-    println!(
+    log_info!("    --output-dir: {:?}", output_dir);
+    log_info!("    --skip     : {skip_keywords}");
+    log_info!("    --skip-ci  : {}", matches.get_flag("skip-ci"));
+    log_info!(
+        "    --skip-regex: {:?}",
+        matches
+            .get_many::<String>("skip-regex")
+            .map(|v| v.collect::<Vec<_>>())
+            .unwrap_or_default()
+    );
+    log_info!("    --ignore   : {ignore_level}");
+    if let Some(p) = matches.get_one::<String>("chrom-map") {
+        let n = chrom::load_chrom_map(p)?;
+        log_info!("    --chrom-map: {p} ({n} aliases)");
+    }
+    log_info!("    --sort     : {sort_enabled}");
+    log_info!("    --threads  : {threads}");
+    log_info!("    --prefix   : {prefix_key}");
+    log_info!("    --reverse  : {reverse}");
+    log_info!("    --no-header: {}", no_header);
+    log_info!("    --oink     : {}", oink);
+    log_info!("    --keep-gt-only: {}", keep_gt_only);
+    log_info!("    --chrom-case: {:?}", chrom_case);
+    log_info!("    --require-map-coverage: {:?}", require_map_coverage);
+    log_info!("    --node-offset: {}", node_offset);
+    log_info!("    --ref-rewrite: {:?}", ref_rewrite);
+    log_info!("    --original-pos-to: {:?}", original_pos_to);
+    log_info!("    --pos-mode: {:?}", pos_mode);
+    log_info!("    --coord-base: {:?}", aln_coord_base);
+    log_info!("    --use-end: {}", use_end);
+    log_info!("    --strict: {}", strict);
+    log_info!("    --on-unmapped: {:?}", on_unmapped);
+    log_info!("    --keep-id: {}", keep_id);
+    log_info!("    --unmapped-out: {:?}", unmapped_out);
+    log_info!("    --check-sorted: {}", check_sorted);
+    log_info!("    --escape-special: {}", escape_special);
+    log_info!("    --tsv-comment-prefix: {:?}", tsv_comment_prefix);
+    log_info!("    --normalize-info-chrom: {:?}", info_chrom_keys);
+    log_info!(
         "    #CHROM will be replaced by path (alignment.tsv prioritized). ID := original POS; POS := distance+position+1 when available; REF from GFA if provided, else reference.tsv."
     );
 
@@ -330,13 +736,13 @@ fn align_main(matches: &clap::ArgMatches) -> Result<(), Box<dyn std::error::Erro
             .num_threads(n)
             .build_global()
             .unwrap();
-        println!("[info] Rayon thread pool set to {n} threads");
+        log_info!("[info] Rayon thread pool set to {n} threads");
     }
 
     let mut gfa_loaded: Option<Gfa<u32, (), ()>> = None;
     if let Some(gfa_path) = gfa_path_opt {
         let n_threads = num_threads.unwrap_or_else(|| rayon::current_num_threads());
-        println!(
+        log_info!(
             "[info] Reading GFA and extracting path information... {}",
             gfa_path
         );
@@ -344,101 +750,162 @@ fn align_main(matches: &clap::ArgMatches) -> Result<(), Box<dyn std::error::Erro
         let converted_before = g.walk.len();
         g.walk_to_path("#");
         if converted_before > 0 {
-            println!(
+            log_info!(
                 "[info] Converted {} walks (W) into path entries",
                 converted_before
             );
         } else {
-            println!("[info] No walks (W) found to convert; proceeding with native P paths only");
+            log_info!("[info] No walks (W) found to convert; proceeding with native P paths only");
         }
         gfa_loaded = Some(g);
     }
 
-    let skip_keywords_set: HashSet<String> = matches
-        .get_one::<String>("skip")
-        .map(|s| {
-            s.split(',')
-                .map(|x| x.trim().to_string())
-                .filter(|s| !s.is_empty())
-                .collect()
-        })
+    let skip_regexes: Vec<String> = matches
+        .get_many::<String>("skip-regex")
+        .map(|v| v.cloned().collect())
         .unwrap_or_default();
+    let skip_matchers = io_stream::build_skip_matchers(
+        matches.get_one::<String>("skip").map(|s| s.as_str()),
+        matches.get_flag("skip-ci"),
+        &skip_regexes,
+    )?;
     let mut node2aln: HashMap<u64, io_stream::AlnInfo> = HashMap::new();
+    let mut reference = io_stream::ReferenceMaps::default();
 
     if let Some(ref_path) = reference_path {
-        println!("[info] Reading reference.tsv first: {ref_path}");
-        let ref_map = io_stream::read_reference_tsv(ref_path)?; // seeds REF_NODE2* caches too
-        let ref_count = ref_map.len();
-        for (node, path) in ref_map.into_iter() {
-            node2aln.insert(
-                node,
-                io_stream::AlnInfo {
-                    path,
-                    distance: 0,
-                    position: 0,
-                },
-            );
-        }
-        println!(
-            "[info] reference.tsv loaded: {} node-paths (as AlnInfo)",
-            ref_count
+        log_info!("[info] Reading reference.tsv first: {ref_path}");
+        // Nodes without an alignment row fall back to reference.tsv path/start while streaming
+        reference = io_stream::read_reference_tsv(ref_path, &tsv_comment_prefix)?;
+        log_info!(
+            "[info] reference.tsv loaded: {} node-paths",
+            reference.path.len()
         );
     } else {
-        println!("[info] No reference.tsv provided; will rely on alignment TSV for path mapping");
+        log_info!("[info] No reference.tsv provided; will rely on alignment TSV for path mapping");
     }
 
-    println!("[info] Reading alignment TSV and merging: {tsv_path}");
-    let aln_map = io_stream::read_alignment_tsv(tsv_path)?;
-    let mut merged = 0usize;
-    for (node, a) in aln_map.into_iter() {
-        // alignment has priority for path + provides distance/position
-        node2aln.insert(node, a);
-        merged += 1;
-    }
-    println!(
+    log_info!("[info] Reading alignment TSV and merging: {tsv_path}");
+    let aln_map = io_stream::read_alignment_tsv(tsv_path, &tsv_comment_prefix)?;
+    let merged = aln_map.len();
+    // alignment has priority for path + provides distance/position
+    node2aln.extend(aln_map);
+    log_info!(
         "[info] alignment.tsv loaded/merged: {} nodes (alignment takes priority)",
         merged
     );
 
     // --- Streaming pass to temp file ---
     let tmp_out = format!("{output_path}.tmp");
-    println!(
-        "[info] Streaming CHROM replacement & CHROM-skip to temp: {}",
-        tmp_out
-    );
+    if !dry_run {
+        log_info!(
+            "[info] Streaming CHROM replacement & CHROM-skip to temp: {}",
+            tmp_out
+        );
+    }
+    let stream_opts = io_stream::StreamOptions {
+        skip: skip_matchers,
+        ignore_level,
+        oink,
+        info_chrom_keys,
+        keep_gt_only,
+        chrom_case,
+        node_offset,
+        ref_rewrite,
+        original_pos_to,
+        escape_special,
+        keep_id,
+        check_sorted,
+        unmapped_out,
+        pos_mode,
+        aln_coord_base,
+        strict,
+        on_unmapped,
+        use_end,
+    };
+    io_stream::check_path_names(&node2aln, &reference, &stream_opts)?;
+    if matches.get_flag("bench") {
+        return io_stream::bench_stream(
+            vcf_path,
+            &node2aln,
+            &reference,
+            gfa_loaded.as_ref(),
+            &stream_opts,
+        );
+    }
+    if dry_run {
+        let stats = io_stream::dry_run_stream(
+            vcf_path,
+            &node2aln,
+            &reference,
+            gfa_loaded.as_ref(),
+            &stream_opts,
+        )?;
+        log_info!(
+            "[info] Dry run complete (no files written): total={}, replaced={}, skipped={}, unmapped={}",
+            stats.total,
+            stats.replaced,
+            stats.skipped,
+            stats.unmapped
+        );
+        return Ok(());
+    }
     let stats = io_stream::stream_replace_chrom_to_tmp(
         vcf_path,
         &tmp_out,
         &node2aln,
-        &skip_keywords_set,
-        ignore_level,
+        &reference,
         gfa_loaded.as_ref(),
-        oink,
+        &stream_opts,
     )?;
-    println!(
+    log_info!(
         "[info] Streaming complete: total={}, replaced={}, skipped={}, unmapped={}",
-        stats.total, stats.replaced, stats.skipped, stats.unmapped
+        stats.total,
+        stats.replaced,
+        stats.skipped,
+        stats.unmapped
     );
 
+    // QC gate: too few records mapped is a hard failure
+    if let Some(min_frac) = require_map_coverage {
+        let coverage = if stats.total == 0 {
+            0.0
+        } else {
+            stats.replaced as f64 / stats.total as f64
+        };
+        if coverage < min_frac {
+            let _ = fs::remove_file(&tmp_out);
+            let needed = (min_frac * stats.total as f64).ceil() as u64;
+            return Err(format!(
+                "Map coverage {:.4} ({}/{}) is below --require-map-coverage {}; short by {} record(s)",
+                coverage,
+                stats.replaced,
+                stats.total,
+                min_frac,
+                needed.saturating_sub(stats.replaced)
+            )
+            .into());
+        }
+        log_info!(
+            "[info] Map coverage {:.4} ({}/{}) meets --require-map-coverage {}",
+            coverage,
+            stats.replaced,
+            stats.total,
+            min_frac
+        );
+    }
+
     // --- Sort or finalize ---
     if sort_enabled {
-        // Determine column index from prefix keyword or numeric index
-        let col_index: usize = match prefix_key.as_str() {
-            "CHROM" | "chrom" | "#CHROM" | "#chrom" => 0,
-            "POS" | "pos" => 1,
-            "ID" | "id" => 2,
-            "REF" | "ref" => 3,
-            "ALT" | "alt" => 4,
-            "QUAL" | "qual" => 5,
-            "FILTER" | "filter" => 6,
-            "INFO" | "info" => 7,
-            "FORMAT" | "format" => 8,
-            other => other.parse::<usize>().unwrap_or(1), // fallback to POS
-        };
-        println!(
-            "[info] Sorting temp VCF by column '{}' -> index {}{}",
+        // Columns (keyword or numeric index) compared left to right, each asc or desc
+        let sort_keys = sort_main::parse_sort_keys(&prefix_key, reverse)?;
+        let key_desc: Vec<String> = sort_keys
+            .iter()
+            .map(|k| format!("{}:{}", k.col, if k.desc { "desc" } else { "asc" }))
+            .collect();
+        log_info!(
+            "[info] Sorting temp VCF by '{}' -> index {}{}",
             prefix_key,
-            col_index,
+            key_desc.join(","),
             if reverse { " (reverse)" } else { "" }
         );
 
@@ -456,28 +923,20 @@ fn align_main(matches: &clap::ArgMatches) -> Result<(), Box<dyn std::error::Erro
             }
         }
 
-        // Sort data lines by the chosen column
-        data_lines.sort_by(|a, b| {
-            let a_fields: Vec<&str> = a.split('\t').collect();
-            let b_fields: Vec<&str> = b.split('\t').collect();
-            let a_key = a_fields.get(col_index).copied().unwrap_or("");
-            let b_key = b_fields.get(col_index).copied().unwrap_or("");
-            let ord = match (a_key.parse::<f64>(), b_key.parse::<f64>()) {
-                (Ok(na), Ok(nb)) => na.partial_cmp(&nb).unwrap_or(std::cmp::Ordering::Equal),
-                _ => a_key.cmp(b_key),
-            };
-            if reverse { ord.reverse() } else { ord }
-        });
+        // Sort data lines by the chosen columns
+        data_lines.sort_by(|a, b| sort_main::cmp_by_keys(a, b, &sort_keys));
 
-        // Write headers + sorted records to output
-        let mut out = std::io::BufWriter::new(std::fs::File::create(&final_output_path)?);
+        // Write headers + sorted records to output (the final file, gzipped, without a header step)
+        final_output_path = gz_name(final_output_path, gzip_final);
+        let mut out = io_utils::OutputFile::create(&final_output_path, gzip_final)?;
         for h in header_lines {
-            writeln!(out, "{}", h)?;
+            write!(out, "{}{}", h, eol())?;
         }
         for d in data_lines {
-            writeln!(out, "{}", d)?;
+            write!(out, "{}{}", d, eol())?;
         }
-        println!(
+        out.finish()?;
+        log_info!(
             "[info] Sorting done: wrote {} records to {}",
             stats.total - stats.skipped,
             final_output_path
@@ -485,6 +944,16 @@ fn align_main(matches: &clap::ArgMatches) -> Result<(), Box<dyn std::error::Erro
 
         // Remove temp
         let _ = fs::remove_file(&tmp_out);
+    } else if gzip_final {
+        // The temp file is the only plain copy: compress it into the final name
+        output_path = gz_name(output_path, true);
+        let mut out = io_utils::OutputFile::create(&output_path, true)?;
+        std::io::copy(
+            &mut std::io::BufReader::new(fs::File::open(&tmp_out)?),
+            &mut out,
+        )?;
+        out.finish()?;
+        fs::remove_file(&tmp_out)?;
     } else {
         fs::rename(&tmp_out, &output_path)?;
     }
@@ -504,18 +973,27 @@ fn align_main(matches: &clap::ArgMatches) -> Result<(), Box<dyn std::error::Erro
         let threads_opt: Option<usize> = matches
             .get_one::<String>("threads")
             .and_then(|s| s.parse().ok());
-        let headed_output = headed_in_original_dir(vcf_path, &align_out);
-        println!(
+        let headed_output = gz_name(
+            headed_in_original_dir(vcf_path, &align_out, output_dir),
+            gzip,
+        );
+        log_info!(
             "[info] Auto-running 'header' on aligned output: {}",
             headed_output
         );
-        let _hdr_out = header::header_run(
+        header::header_run(
             &align_out,
             ref_path,
             threads_opt,
             Some(&headed_output),
-            ignore_level,
-        )?;
+            &header::HeaderOptions {
+                ignore: ignore_level,
+                chrom_case,
+                gzip,
+                ..Default::default()
+            },
+        )?
+        .print();
         // Remove the intermediate replaced/sorted file after headering
         if let Err(e) = fs::remove_file(&align_out) {
             eprintln!(
@@ -525,11 +1003,11 @@ fn align_main(matches: &clap::ArgMatches) -> Result<(), Box<dyn std::error::Erro
         }
         headed_output
     } else {
-        println!("[info] --no-header set: skipping automatic header synthesis");
+        log_info!("[info] --no-header set: skipping automatic header synthesis");
         align_out
     };
 
-    println!("[info] All operations complete. Output written to {output_path_log}.");
+    log_info!("[info] All operations complete. Output written to {output_path_log}.");
 
     Ok(())
 }
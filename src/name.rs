@@ -1,3 +1,4 @@
+use crate::line_sep::eol;
 use std::collections::{HashMap, HashSet};
 use std::error::Error;
 use std::fs::{File, rename};
@@ -27,48 +28,114 @@ fn norm_chr(raw: &str) -> Option<String> {
         "Mt" | "MT" | "mt" => "M",
         other => other,
     };
-    // Only allow 1..22, X, Y, M
+    // Only allow 1..N (N = --autosomes, default 22), X, Y, M
     if c == "X" || c == "Y" || c == "M" {
         return Some(c.to_string());
     }
     if let Ok(n) = c.parse::<u32>() {
-        if (1..=22).contains(&n) {
+        if crate::chrom::is_std_autosome(n) {
             return Some(n.to_string());
         }
     }
     None
 }
 
-fn make_key(chrom: &str, pos: &str, ref_allele: &str, alt_allele: &str) -> Option<String> {
+pub fn make_key(
+    chrom: &str,
+    pos: &str,
+    ref_allele: &str,
+    alt_allele: &str,
+    delim: &str,
+) -> Option<String> {
     let chr = norm_chr(chrom)?;
-    Some(format!("{}:{}:{}:{}", chr, pos, ref_allele, alt_allele))
+    Some(format!(
+        "{}{d}{}{d}{}{d}{}",
+        chr,
+        pos,
+        ref_allele,
+        alt_allele,
+        d = delim
+    ))
 }
 
-fn is_probable_header_tsv(line: &str) -> bool {
+fn is_probable_header_tsv(line: &str, key: &QtlKey) -> bool {
     let cols: Vec<&str> = line.split('\t').collect();
-    if cols.len() < 2 {
-        return false;
+    match key {
+        QtlKey::Column(c) => cols.get(*c).is_some_and(|second| {
+            second.eq_ignore_ascii_case("snp")
+                || second.eq_ignore_ascii_case("variant")
+                || second.eq_ignore_ascii_case("node")
+        }),
+        // A header row has a non-numeric POS column
+        QtlKey::Composite(k) => cols
+            .get(k[1])
+            .is_some_and(|pos| pos.trim().parse::<u64>().is_err()),
     }
-    let second = cols[1];
-    second.eq_ignore_ascii_case("snp")
-        || second.eq_ignore_ascii_case("variant")
-        || second.eq_ignore_ascii_case("node")
 }
 
-fn try_normalize_token(token: &str) -> Option<String> {
-    // Accept forms like (chr)10:12910:G:A
+/// Which QTL column(s) hold the variant key to rewrite (`--key-cols`)
+#[derive(Debug, Clone)]
+pub enum QtlKey {
+    /// One column holding the whole token (FastQTL: column 2); 0-based
+    Column(usize),
+    /// CHROM, POS, REF, ALT columns (0-based) joined with `--id-delim` into the lookup token;
+    /// the mapped value replaces the CHROM column, the other three are left as they are
+    Composite([usize; 4]),
+}
+
+impl Default for QtlKey {
+    fn default() -> Self {
+        QtlKey::Column(1)
+    }
+}
+
+impl QtlKey {
+    /// Parse `--key-cols`: one 1-based column, or four (`chrom,pos,ref,alt`)
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let cols: Vec<usize> = spec
+            .split(',')
+            .map(|c| match c.trim().parse::<usize>() {
+                Ok(n) if n >= 1 => Ok(n - 1),
+                _ => Err(format!("--key-cols: '{c}' is not a 1-based column number")),
+            })
+            .collect::<Result<_, _>>()?;
+        match cols.as_slice() {
+            [c] => Ok(QtlKey::Column(*c)),
+            [a, b, c, d] => Ok(QtlKey::Composite([*a, *b, *c, *d])),
+            _ => Err(format!(
+                "--key-cols expects 1 column or 4 (chrom,pos,ref,alt), got '{spec}'"
+            )),
+        }
+    }
+}
+
+/// Behavior switches for `run_rename` (set from `rename` CLI flags)
+#[derive(Debug, Clone, Default)]
+pub struct RenameOptions {
+    /// `--key-cols`: where the variant key lives in the QTL file
+    pub key: QtlKey,
+    /// `--misses`: TSV of QTL tokens that matched neither exactly nor after normalization
+    pub misses: Option<String>,
+    /// `--unordered`: write rows as workers finish them instead of in input order
+    pub unordered: bool,
+    /// `--map`: precomputed key -> normalized TSV used as is; the VCF is not read
+    pub map: Option<String>,
+}
+
+fn try_normalize_token(token: &str, delim: &str) -> Option<String> {
+    // Accept forms like (chr)10:12910:G:A (with `delim` in place of ':')
     let t = token.trim();
     if t.is_empty() {
         return None;
     }
     // Some FastQTL SNPs are like 10:12910:G:A, others can be rsIDs; normalize only when it's the former
-    let parts: Vec<&str> = t.split(':').collect();
+    let parts: Vec<&str> = t.split(delim).collect();
     if parts.len() >= 4 {
         let chrom = parts[0];
         let pos = parts[1];
         let r#ref = parts[2];
         let alt = parts[3];
-        return make_key(chrom, pos, r#ref, alt);
+        return make_key(chrom, pos, r#ref, alt, delim);
     }
     None
 }
@@ -90,25 +157,68 @@ fn load_map_from_tsv(path: &Path) -> std::io::Result<HashMap<String, String>> {
     Ok(map)
 }
 
-pub fn run_rename(vcf_path: &str, qtl_path: &str, threads: usize) -> Result<(), Box<dyn Error>> {
-    let t0 = Instant::now();
-    eprintln!(
-        "[INFO] rename: start vcf='{}' qtl='{}' threads={}",
-        vcf_path, qtl_path, threads
-    );
-
-    // Check for --map-mode flag in args
-    let args: Vec<String> = std::env::args().collect();
-    let mut map_mode = "ids-only".to_string();
-    let mut i = 0;
-    while i < args.len() {
-        if args[i] == "--map-mode" && i + 1 < args.len() {
-            map_mode = args[i + 1].clone();
-            i += 1;
+/// `--map`: a user-supplied key<TAB>normalized file; every non-comment line must have exactly
+/// two tab-separated, non-empty columns
+fn load_user_map(path: &str) -> Result<HashMap<String, String>, Box<dyn Error>> {
+    let r = BufReader::new(File::open(path).map_err(|e| format!("--map {path}: {e}"))?);
+    let mut map = HashMap::with_capacity(1_000_000);
+    for (i, line) in r.lines().enumerate() {
+        let line = line?;
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        match line.split('\t').collect::<Vec<&str>>().as_slice() {
+            [k, v] if !k.is_empty() && !v.is_empty() => {
+                map.insert(k.to_string(), v.to_string());
+            }
+            cols => {
+                return Err(format!(
+                    "--map {path} line {}: expected 2 tab-separated columns (key, normalized), got {}",
+                    i + 1,
+                    cols.len()
+                )
+                .into());
+            }
         }
-        i += 1;
     }
-    eprintln!("[INFO] Map mode: {}", map_mode);
+    Ok(map)
+}
+
+/// First line of a `map.tsv` built from the VCF: records the settings its keys depend on, so a
+/// cached map is only reused by a run with the same `--id-delim` and `--map-mode`
+fn map_header(id_delim: &str, map_mode: &str) -> String {
+    format!("#map id_delim={id_delim:?} map_mode={map_mode}")
+}
+
+/// True when the cached map at `path` starts with `expected` (see `map_header`)
+fn map_header_matches(path: &Path, expected: &str) -> std::io::Result<bool> {
+    let mut first = String::new();
+    BufReader::new(File::open(path)?).read_line(&mut first)?;
+    Ok(first.trim_end_matches(['\n', '\r']) == expected)
+}
+
+pub fn run_rename(
+    vcf_path: &str,
+    qtl_path: &str,
+    threads: usize,
+    id_delim: &str,
+    map_mode: &str,
+    opts: &RenameOptions,
+) -> Result<(), Box<dyn Error>> {
+    if id_delim.is_empty() {
+        return Err("--id-delim must not be empty".into());
+    }
+    let key = &opts.key;
+    let t0 = Instant::now();
+    elog_info!(
+        "[INFO] rename: start vcf='{}' qtl='{}' threads={} id_delim='{}'",
+        vcf_path,
+        qtl_path,
+        threads,
+        id_delim
+    );
+    elog_info!("[INFO] Map mode: {}", map_mode);
+    elog_info!("[INFO] QTL key: {:?}", key);
 
     #[cfg(feature = "rayon")]
     {
@@ -123,24 +233,45 @@ pub fn run_rename(vcf_path: &str, qtl_path: &str, threads: usize) -> Result<(),
         .unwrap_or_else(|| Path::new("."));
     let map_final = qtl_dir.join("map.tsv");
     let map_tmp = qtl_dir.join("map.tsv.tmp");
+    let header = map_header(id_delim, map_mode);
+    let cached = opts.map.is_none()
+        && map_final.exists()
+        && !map_tmp.exists()
+        && map_header_matches(&map_final, &header)?;
+    if opts.map.is_none() && map_final.exists() && !map_tmp.exists() && !cached {
+        elog_info!(
+            "[INFO] existing map {} was built with a different --id-delim/--map-mode; rebuilding",
+            map_final.display()
+        );
+    }
 
-    // If a stable map exists and no tmp in progress, reuse it. Otherwise build from VCF and write atomically.
-    let id_key_map: HashMap<String, String> = if map_final.exists() && !map_tmp.exists() {
-        eprintln!("[INFO] detected existing map: {}", map_final.display());
+    // --map wins; else if a stable map exists and no tmp in progress, reuse it. Otherwise build
+    // from VCF and write atomically.
+    let id_key_map: HashMap<String, String> = if let Some(p) = &opts.map {
+        let m = load_user_map(p)?;
+        elog_info!(
+            "[INFO] --map {}: loaded {} entries; skipping the VCF pass",
+            p,
+            m.len()
+        );
+        m
+    } else if cached {
+        elog_info!("[INFO] detected existing map: {}", map_final.display());
         let m = load_map_from_tsv(&map_final)?;
-        eprintln!("[INFO] loaded existing map entries: {}", m.len());
+        elog_info!("[INFO] loaded existing map entries: {}", m.len());
         m
     } else {
-        eprintln!("[INFO] building key→normalized map from VCF ...");
+        elog_info!("[INFO] building key→normalized map from VCF ...");
         let mut map: HashMap<String, String> = HashMap::with_capacity(1_000_000);
         let mut pos_map: HashMap<String, String> = HashMap::with_capacity(1_000_000);
         let mut pos_dupe: HashSet<String> = HashSet::with_capacity(64);
 
         // Create temp map file and write as we go
         let tmp_path = qtl_dir.join("map.tsv.tmp");
-        eprintln!("[INFO] creating temp map at {}", tmp_path.display());
+        elog_info!("[INFO] creating temp map at {}", tmp_path.display());
         let tmp_file = File::create(&tmp_path)?;
         let mut map_writer = BufWriter::new(tmp_file);
+        write!(map_writer, "{header}{}", eol())?;
         let mut map_lines_written: usize = 0;
         let mut first_entries: usize = 0;
 
@@ -179,7 +310,7 @@ pub fn run_rename(vcf_path: &str, qtl_path: &str, threads: usize) -> Result<(),
                         let r#ref = cols[3];
                         let alt_all = cols[4];
                         let alt = alt_all.split(',').next().unwrap_or(alt_all);
-                        if let Some(norm) = make_key(chrom, pos, r#ref, alt) {
+                        if let Some(norm) = make_key(chrom, pos, r#ref, alt, id_delim) {
                             let id_pair = if id != "." && !id.is_empty() {
                                 Some((id.to_string(), norm.clone()))
                             } else {
@@ -212,7 +343,7 @@ pub fn run_rename(vcf_path: &str, qtl_path: &str, threads: usize) -> Result<(),
                         let r#ref = cols[3];
                         let alt_all = cols[4];
                         let alt = alt_all.split(',').next().unwrap_or(alt_all);
-                        if let Some(norm) = make_key(chrom, pos, r#ref, alt) {
+                        if let Some(norm) = make_key(chrom, pos, r#ref, alt, id_delim) {
                             let id_pair = if id != "." && !id.is_empty() {
                                 Some((id.to_string(), norm.clone()))
                             } else {
@@ -241,12 +372,12 @@ pub fn run_rename(vcf_path: &str, qtl_path: &str, threads: usize) -> Result<(),
                                 !idk.is_empty() && idk.chars().all(|c| c.is_ascii_digit());
                             if id_is_numeric {
                                 if map.insert(idk.clone(), v.clone()).is_none() {
-                                    writeln!(map_writer, "{}\t{}", idk, v)?;
+                                    write!(map_writer, "{}\t{}{}", idk, v, eol())?;
                                     map_lines_written += 1;
                                     local_ids_added += 1;
                                     vcf_ids_added += 1;
                                     if first_entries < 5 {
-                                        eprintln!("[INFO] Writing map entry: {} -> {}", idk, v);
+                                        elog_info!("[INFO] Writing map entry: {} -> {}", idk, v);
                                         first_entries += 1;
                                     }
                                 }
@@ -262,12 +393,12 @@ pub fn run_rename(vcf_path: &str, qtl_path: &str, threads: usize) -> Result<(),
                         }
                         if let Some((idk, v)) = id_opt.clone() {
                             if map.insert(idk.clone(), v.clone()).is_none() {
-                                writeln!(map_writer, "{}\t{}", idk, v)?;
+                                write!(map_writer, "{}\t{}{}", idk, v, eol())?;
                                 map_lines_written += 1;
                                 local_ids_added += 1;
                                 vcf_ids_added += 1;
                                 if first_entries < 5 {
-                                    eprintln!("[INFO] Writing map entry: {} -> {}", idk, v);
+                                    elog_info!("[INFO] Writing map entry: {} -> {}", idk, v);
                                     first_entries += 1;
                                 }
                             }
@@ -292,7 +423,7 @@ pub fn run_rename(vcf_path: &str, qtl_path: &str, threads: usize) -> Result<(),
                 }
 
                 vcf_body_lines += chunk_in.len();
-                eprintln!(
+                elog_info!(
                     "[INFO] map-chunk #{}, lines={}, ids+={}, keys+={}, pos_unique+={}, pos_amb+={}",
                     chunk_idx,
                     chunk_in.len(),
@@ -321,28 +452,32 @@ pub fn run_rename(vcf_path: &str, qtl_path: &str, threads: usize) -> Result<(),
                 if chunk.len() >= MAP_CHUNK_LINES {
                     process_chunk(&chunk)?;
                     chunk = Vec::with_capacity(MAP_CHUNK_LINES);
-                    eprintln!("[INFO] map-chunk dropped to release memory");
+                    elog_info!("[INFO] map-chunk dropped to release memory");
                 }
             }
             if !chunk.is_empty() {
                 process_chunk(&chunk)?;
-                eprintln!("[INFO] final map-chunk dropped to release memory");
+                elog_info!("[INFO] final map-chunk dropped to release memory");
             }
 
             // Do not write POS-only entries to the map file
             map_writer.flush()?;
-            eprintln!("[INFO] temp map lines written: {}", map_lines_written);
+            elog_info!("[INFO] temp map lines written: {}", map_lines_written);
         }
-        eprintln!(
+        elog_info!(
             "[INFO] VCF pass: lines={} ids_mapped={} keys_mapped={} pos_unique={} pos_ambiguous={}",
-            vcf_body_lines, vcf_ids_added, vcf_keys_added, vcf_pos_added, vcf_pos_ambiguous
+            vcf_body_lines,
+            vcf_ids_added,
+            vcf_keys_added,
+            vcf_pos_added,
+            vcf_pos_ambiguous
         );
         if map_mode == "ids-only" {
-            eprintln!(
+            elog_info!(
                 "[INFO] map.tsv policy: only numeric VCF IDs are written as keys; normalized and POS keys are not stored"
             );
         } else {
-            eprintln!("[INFO] map.tsv policy: all mappings (id, normalized, pos) are stored");
+            elog_info!("[INFO] map.tsv policy: all mappings (id, normalized, pos) are stored");
         }
 
         // Merge pos_map into map for a unified lookup (only if not ids-only)
@@ -352,18 +487,20 @@ pub fn run_rename(vcf_path: &str, qtl_path: &str, threads: usize) -> Result<(),
             }
         }
 
-        eprintln!("[INFO] Map build complete: {} entries", map.len());
+        elog_info!("[INFO] Map build complete: {} entries", map.len());
         // Atomic rename temp -> final
         let final_map_path = qtl_dir.join("map.tsv");
         rename(&tmp_path, &final_map_path)?;
-        eprintln!("[INFO] wrote map to {}", final_map_path.display());
-        eprintln!("[INFO] map finalized; starting QTL phase");
+        elog_info!("[INFO] wrote map to {}", final_map_path.display());
+        elog_info!("[INFO] map finalized; starting QTL phase");
         map
     };
-    eprintln!("[INFO] active map size: {} entries", id_key_map.len());
-    eprintln!(
+    elog_info!("[INFO] active map size: {} entries", id_key_map.len());
+    elog_info!(
         "[INFO] map build mode: {}",
-        if map_final.exists() && !map_tmp.exists() {
+        if opts.map.is_some() {
+            "user-supplied --map"
+        } else if cached {
             "reused existing map (no temp file)"
         } else {
             "rebuilt from VCF (temp existed until rename)"
@@ -376,17 +513,34 @@ pub fn run_rename(vcf_path: &str, qtl_path: &str, threads: usize) -> Result<(),
     let writer = Arc::new(Mutex::new(BufWriter::new(File::create(&out_path)?)));
     let replaced_ctr = Arc::new(AtomicUsize::new(0));
     let unchanged_ctr = Arc::new(AtomicUsize::new(0));
+    let misses_writer = match &opts.misses {
+        Some(p) => {
+            let mut mw = BufWriter::new(File::create(p)?);
+            write!(mw, "#line\ttoken{}", eol())?;
+            Some(Mutex::new(mw))
+        }
+        None => None,
+    };
+    let misses_ctr = AtomicUsize::new(0);
+    // Log an unmatched token with its 1-based QTL line number
+    let record_miss = |lineno: usize, out: &ReplaceOut| {
+        if let (Some(mw), Some(tok)) = (&misses_writer, &out.miss) {
+            let mut mw = mw.lock().unwrap();
+            let _ = write!(mw, "{}\t{}{}", lineno, tok, eol());
+            misses_ctr.fetch_add(1, Ordering::Relaxed);
+        }
+    };
 
     // Detect and write header unchanged, if present
     let mut wrote_header = false;
     let mut first_line = String::new();
     if qtl_reader.read_line(&mut first_line)? > 0 {
         let trimmed = first_line.trim_end_matches(['\n', '\r']).to_string();
-        if is_probable_header_tsv(&trimmed) {
+        if is_probable_header_tsv(&trimmed, key) {
             wrote_header = true;
-            eprintln!("[INFO] QTL header detected; preserving first line as-is");
+            elog_info!("[INFO] QTL header detected; preserving first line as-is");
             let mut w = writer.lock().unwrap();
-            writeln!(w, "{}", trimmed)?;
+            write!(w, "{}{}", trimmed, eol())?;
         } else if !trimmed.is_empty() {
             // process this first data row immediately as a single-item chunk
             #[cfg(feature = "rayon")]
@@ -396,9 +550,10 @@ pub fn run_rename(vcf_path: &str, qtl_path: &str, threads: usize) -> Result<(),
                 let rep = Arc::clone(&replaced_ctr);
                 let unc = Arc::clone(&unchanged_ctr);
                 [trimmed].par_iter().for_each(|row| {
-                    let out = replace_col2_with_map(row, map_ref);
+                    let out = replace_col2_with_map(row, map_ref, id_delim, key);
+                    record_miss(1, &out);
                     let mut wlock = w.lock().unwrap();
-                    writeln!(wlock, "{}", out.line).unwrap();
+                    write!(wlock, "{}{}", out.line, eol()).unwrap();
                     if out.changed {
                         rep.fetch_add(1, Ordering::Relaxed);
                     } else {
@@ -408,9 +563,10 @@ pub fn run_rename(vcf_path: &str, qtl_path: &str, threads: usize) -> Result<(),
             }
             #[cfg(not(feature = "rayon"))]
             {
-                let out = replace_col2_with_map(&trimmed, &id_key_map);
+                let out = replace_col2_with_map(&trimmed, &id_key_map, id_delim, key);
+                record_miss(1, &out);
                 let mut wlock = writer.lock().unwrap();
-                writeln!(wlock, "{}", out.line)?;
+                write!(wlock, "{}{}", out.line, eol())?;
                 if out.changed {
                     replaced_ctr.fetch_add(1, Ordering::Relaxed);
                 } else {
@@ -420,26 +576,27 @@ pub fn run_rename(vcf_path: &str, qtl_path: &str, threads: usize) -> Result<(),
         }
     }
 
-    // QTL replacement streaming with par_bridge
+    // QTL replacement: ordered chunks by default, par_bridge with --unordered
     use std::io::BufRead;
     let mut _total_rows: usize = 0;
-    eprintln!("[INFO] Starting QTL replacement stream...");
+    elog_info!("[INFO] Starting QTL replacement stream...");
     #[cfg(feature = "rayon")]
-    {
+    if opts.unordered {
         use rayon::iter::ParallelBridge;
         let w = Arc::clone(&writer);
         let map_ref = &id_key_map;
         let rep = Arc::clone(&replaced_ctr);
         let unc = Arc::clone(&unchanged_ctr);
-        let lines = qtl_reader.lines();
-        lines.par_bridge().for_each(|res| {
+        let lines = qtl_reader.lines().enumerate();
+        lines.par_bridge().for_each(|(i, res)| {
             if let Ok(line) = res {
                 if line.trim().is_empty() {
                     return;
                 }
-                let replaced = replace_col2_with_map(&line, map_ref);
+                let replaced = replace_col2_with_map(&line, map_ref, id_delim, key);
+                record_miss(i + 2, &replaced);
                 let mut lock = w.lock().unwrap();
-                let _ = writeln!(lock, "{}", replaced.line);
+                let _ = write!(lock, "{}{}", replaced.line, eol());
                 if replaced.changed {
                     rep.fetch_add(1, Ordering::Relaxed);
                 } else {
@@ -448,17 +605,47 @@ pub fn run_rename(vcf_path: &str, qtl_path: &str, threads: usize) -> Result<(),
             }
         });
         _total_rows = replaced_ctr.load(Ordering::Relaxed) + unchanged_ctr.load(Ordering::Relaxed);
+    } else {
+        // Replace each chunk in parallel, then write it back in input order
+        let mut lines = qtl_reader.lines().enumerate();
+        loop {
+            let chunk: Vec<(usize, String)> = lines
+                .by_ref()
+                .take(MAP_CHUNK_LINES)
+                .map(|(i, res)| res.map(|l| (i, l)))
+                .collect::<Result<_, _>>()?;
+            if chunk.is_empty() {
+                break;
+            }
+            let outs: Vec<(usize, ReplaceOut)> = chunk
+                .par_iter()
+                .filter(|(_, l)| !l.trim().is_empty())
+                .map(|(i, l)| (*i, replace_col2_with_map(l, &id_key_map, id_delim, key)))
+                .collect();
+            let mut wlock = writer.lock().unwrap();
+            for (i, out) in outs {
+                record_miss(i + 2, &out);
+                write!(wlock, "{}{}", out.line, eol())?;
+                if out.changed {
+                    replaced_ctr.fetch_add(1, Ordering::Relaxed);
+                } else {
+                    unchanged_ctr.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+        }
+        _total_rows = replaced_ctr.load(Ordering::Relaxed) + unchanged_ctr.load(Ordering::Relaxed);
     }
     #[cfg(not(feature = "rayon"))]
     {
-        for l in qtl_reader.lines() {
+        for (i, l) in qtl_reader.lines().enumerate() {
             let s = l?;
             if s.trim().is_empty() {
                 continue;
             }
-            let out = replace_col2_with_map(&s, &id_key_map);
+            let out = replace_col2_with_map(&s, &id_key_map, id_delim, key);
+            record_miss(i + 2, &out);
             let mut wlock = writer.lock().unwrap();
-            writeln!(wlock, "{}", out.line)?;
+            write!(wlock, "{}{}", out.line, eol())?;
             if out.changed {
                 replaced_ctr.fetch_add(1, Ordering::Relaxed);
             } else {
@@ -470,11 +657,19 @@ pub fn run_rename(vcf_path: &str, qtl_path: &str, threads: usize) -> Result<(),
 
     // Finish
     writer.lock().unwrap().flush()?;
-    eprintln!(
+    if let (Some(mw), Some(p)) = (&misses_writer, &opts.misses) {
+        mw.lock().unwrap().flush()?;
+        elog_info!(
+            "[INFO] unmatched tokens: {} written to {}",
+            misses_ctr.load(Ordering::Relaxed),
+            p
+        );
+    }
+    elog_info!(
         "[INFO] Replacement done: {} lines processed",
         replaced_ctr.load(Ordering::Relaxed) + unchanged_ctr.load(Ordering::Relaxed)
     );
-    eprintln!(
+    elog_info!(
         "[INFO] QTL replaced: rows={} replaced={} unchanged={} header_written={} output={}",
         replaced_ctr.load(Ordering::Relaxed) + unchanged_ctr.load(Ordering::Relaxed),
         replaced_ctr.load(Ordering::Relaxed),
@@ -482,47 +677,68 @@ pub fn run_rename(vcf_path: &str, qtl_path: &str, threads: usize) -> Result<(),
         wrote_header,
         out_path
     );
-    eprintln!("[INFO] total elapsed: {:.2?}", t0.elapsed());
+    elog_info!("[INFO] total elapsed: {:.2?}", t0.elapsed());
     Ok(())
 }
 
 struct ReplaceOut {
     line: String,
     changed: bool,
+    /// Lookup token that found no map entry (`None` when replaced or the key columns are missing)
+    miss: Option<String>,
 }
 
-fn replace_col2_with_map(line: &str, id_key_map: &HashMap<String, String>) -> ReplaceOut {
+/// Rewrite the key of one QTL row through the map: column 2 by default, or the column(s)
+/// chosen with `--key-cols`.
+fn replace_col2_with_map(
+    line: &str,
+    id_key_map: &HashMap<String, String>,
+    id_delim: &str,
+    key: &QtlKey,
+) -> ReplaceOut {
     let mut cols: Vec<&str> = line.split('\t').collect();
-    if cols.len() < 2 {
-        return ReplaceOut {
-            line: line.to_string(),
-            changed: false,
-        };
-    }
-    let col2 = cols[1].trim();
+    let unchanged = |miss: Option<String>| ReplaceOut {
+        line: line.to_string(),
+        changed: false,
+        miss,
+    };
+    // Token to look up, and the column that receives the mapped value
+    let (token, target): (String, usize) = match key {
+        QtlKey::Column(c) => match cols.get(*c) {
+            Some(v) => (v.trim().to_string(), *c),
+            None => return unchanged(None),
+        },
+        QtlKey::Composite(k) => {
+            let parts: Option<Vec<&str>> =
+                k.iter().map(|&i| cols.get(i).map(|v| v.trim())).collect();
+            match parts {
+                Some(p) => (p.join(id_delim), k[0]),
+                None => return unchanged(None),
+            }
+        }
+    };
 
     // 1) Try exact token in map
-    if let Some(norm) = id_key_map.get(col2) {
-        cols[1] = norm;
+    if let Some(norm) = id_key_map.get(&token) {
+        cols[target] = norm;
         return ReplaceOut {
             line: cols.join("\t"),
             changed: true,
+            miss: None,
         };
     }
 
     // 2) Try normalized token (e.g. strip 'chr', normalize MT/M)
-    if let Some(norm2) = try_normalize_token(col2) {
+    if let Some(norm2) = try_normalize_token(&token, id_delim) {
         if let Some(norm) = id_key_map.get(&norm2) {
-            cols[1] = norm;
+            cols[target] = norm;
             return ReplaceOut {
                 line: cols.join("\t"),
                 changed: true,
+                miss: None,
             };
         }
     }
 
-    ReplaceOut {
-        line: line.to_string(),
-        changed: false,
-    }
+    unchanged(Some(token))
 }
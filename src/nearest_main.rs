@@ -1,5 +1,6 @@
 /// A Modified Version of the nearest node algorithm(including nodes on reference paths)
 /// With the original version from `gfa2bin` (hhttps://github.com/MoinSebi/gfa2bin)
+use crate::line_sep::eol;
 use clap::ArgMatches;
 use gfa_reader::Gfa;
 use log::info;
@@ -33,10 +34,10 @@ pub fn nearest_main(matches: &ArgMatches) -> Result<(), Box<dyn std::error::Erro
 
     let threads = matches.get_one::<String>("threads").unwrap().as_str();
 
-    println!("    --gfa      : {}", graph_file);
-    println!("    --output   : {}", output_file);
-    println!("    --threads  : {}", threads);
-    println!(
+    log_info!("    --gfa      : {}", graph_file);
+    log_info!("    --output   : {}", output_file);
+    log_info!("    --threads  : {}", threads);
+    log_info!(
         "    --nodes    : {}",
         if matches.contains_id("nodes") {
             matches.get_one::<String>("nodes").unwrap()
@@ -44,7 +45,7 @@ pub fn nearest_main(matches: &ArgMatches) -> Result<(), Box<dyn std::error::Erro
             "All nodes"
         }
     );
-    println!(
+    log_info!(
         "    --references: {}",
         if matches.contains_id("references") {
             matches.get_one::<String>("references").unwrap()
@@ -52,7 +53,7 @@ pub fn nearest_main(matches: &ArgMatches) -> Result<(), Box<dyn std::error::Erro
             "None"
         }
     );
-    println!(
+    log_info!(
         "    --prefix   : {}",
         if matches.contains_id("prefix") {
             matches.get_one::<String>("prefix").unwrap()
@@ -60,7 +61,7 @@ pub fn nearest_main(matches: &ArgMatches) -> Result<(), Box<dyn std::error::Erro
             "None"
         }
     );
-    println!(
+    log_info!(
         "    --keep-ref : {}",
         if matches.contains_id("keep-ref") {
             "true"
@@ -68,7 +69,7 @@ pub fn nearest_main(matches: &ArgMatches) -> Result<(), Box<dyn std::error::Erro
             "false"
         }
     );
-    println!(
+    log_info!(
         "    #CHROM will be replaced by path (alignment.tsv prioritized). ID := original POS; POS := distance+position+1 when available; REF from GFA if provided, else reference.tsv."
     );
 
@@ -333,7 +334,11 @@ pub fn write_file(
     // Create file
     let file_out = File::create(output).expect("Unable to create file");
     let mut output_reader = BufWriter::new(file_out);
-    writeln!(output_reader, "node\tref_node\tdistance\tposition\tpath")?;
+    write!(
+        output_reader,
+        "node\tref_node\tdistance\tposition\tpath{}",
+        eol()
+    )?;
 
     for reference_name in names.iter() {
         // Have multiple pos for a single node
@@ -360,14 +365,15 @@ pub fn write_file(
                 // Directly generate combinations of indices for the matching value
                 for index1 in start_i..i {
                     for index2 in start_j..j {
-                        writeln!(
+                        write!(
                             output_reader,
-                            "{}\t{}\t{}\t{}\t{}",
+                            "{}\t{}\t{}\t{}\t{}{}",
                             result[index1].0,
                             result[index1].1,
                             result[index1].2,
                             pos1[index2].1,
-                            reference_name
+                            reference_name,
+                            eol()
                         )?;
                     }
                 }
@@ -0,0 +1,75 @@
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+static INTERVAL: OnceLock<Duration> = OnceLock::new();
+
+/// Lines between two clock reads while `--progress` is on
+const CHECK_EVERY: u64 = 1 << 16;
+
+/// Turn on `--progress`: report every `secs` seconds (first call wins)
+pub fn set_interval(secs: u64) {
+    let _ = INTERVAL.set(Duration::from_secs(secs.max(1)));
+}
+
+/// Time-throttled line/byte counter for the streaming loops (`--progress`). When progress is off
+/// `tick` is a single branch; when on, the clock is only read every `CHECK_EVERY` lines and a
+/// `[progress]` line (lines, MB, MB/s, lines/s) goes to stderr at most once per interval.
+pub struct Progress {
+    label: &'static str,
+    every: Option<Duration>,
+    lines: u64,
+    bytes: u64,
+    start: Instant,
+    last: Instant,
+}
+
+impl Progress {
+    pub fn new(label: &'static str) -> Self {
+        let now = Instant::now();
+        Progress {
+            label,
+            every: INTERVAL.get().copied(),
+            lines: 0,
+            bytes: 0,
+            start: now,
+            last: now,
+        }
+    }
+
+    /// Count one line of `len` bytes (newline excluded)
+    #[inline]
+    pub fn tick(&mut self, len: usize) {
+        if let Some(every) = self.every {
+            self.lines += 1;
+            self.bytes += len as u64 + 1;
+            if self.lines.is_multiple_of(CHECK_EVERY) {
+                let now = Instant::now();
+                if now.duration_since(self.last) >= every {
+                    self.last = now;
+                    self.report("");
+                }
+            }
+        }
+    }
+
+    /// Final `[progress]` line with the totals (nothing when progress is off)
+    pub fn finish(&self) {
+        if self.every.is_some() {
+            self.report(" done");
+        }
+    }
+
+    fn report(&self, suffix: &str) {
+        let secs = self.start.elapsed().as_secs_f64().max(1e-9);
+        let mb = self.bytes as f64 / 1_000_000.0;
+        eprintln!(
+            "[progress] [{}]{} {} lines, {:.1} MB, {:.1} MB/s, {:.0} lines/s",
+            self.label,
+            suffix,
+            self.lines,
+            mb,
+            mb / secs,
+            self.lines as f64 / secs
+        );
+    }
+}
@@ -0,0 +1,150 @@
+use crate::io_utils::open_input;
+use crate::line_sep::eol;
+use clap::ArgMatches;
+use std::fs::File;
+use std::io::{BufRead, BufWriter, Write};
+use std::path::Path;
+
+/// A 1-based, closed region `CHROM[:START[-END]]`
+struct Region {
+    chrom: String,
+    start: u64,
+    end: u64,
+}
+
+fn parse_region(s: &str) -> Result<Region, String> {
+    let (chrom, range) = match s.rsplit_once(':') {
+        Some((c, r)) => (c, Some(r)),
+        None => (s, None),
+    };
+    if chrom.is_empty() {
+        return Err(format!("Invalid region '{s}': empty CHROM"));
+    }
+    let (start, end) = match range {
+        None => (1, u64::MAX),
+        Some(r) => {
+            let r = r.replace(',', "");
+            let (a, b) = match r.split_once('-') {
+                Some((a, b)) => (a.to_string(), Some(b.to_string())),
+                None => (r.clone(), None),
+            };
+            let start = a
+                .parse::<u64>()
+                .map_err(|_| format!("Invalid region '{s}': bad start '{a}'"))?;
+            let end = match b {
+                Some(b) if !b.is_empty() => b
+                    .parse::<u64>()
+                    .map_err(|_| format!("Invalid region '{s}': bad end '{b}'"))?,
+                Some(_) => u64::MAX,
+                None => start,
+            };
+            (start, end)
+        }
+    };
+    if start > end {
+        return Err(format!("Invalid region '{s}': start > end"));
+    }
+    Ok(Region {
+        chrom: chrom.to_string(),
+        start,
+        end,
+    })
+}
+
+/// Default output: <same-dir>/<basename-without-.vcf[.gz]>.query.vcf
+fn default_query_path(vcf_in: &str) -> String {
+    let in_path = Path::new(vcf_in);
+    let parent = in_path.parent().unwrap_or(Path::new("."));
+    let fname = in_path
+        .file_name()
+        .and_then(|s| s.to_str())
+        .unwrap_or("output");
+    let stem = fname
+        .strip_suffix(".vcf.gz")
+        .or_else(|| fname.strip_suffix(".vcf"))
+        .unwrap_or(fname);
+    parent
+        .join(format!("{}.query.vcf", stem))
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// Extract the records of one region from a VCF (plain or .gz), keeping the header.
+///
+/// With `--assume-sorted` the scan stops as soon as it passes the end of the region on the
+/// matching contig (or leaves that contig); otherwise the whole file is scanned.
+pub fn query_main(matches: &ArgMatches) -> Result<(), Box<dyn std::error::Error>> {
+    let vcf_in = matches.get_one::<String>("vcf").unwrap();
+    let region_str = matches.get_one::<String>("region").unwrap();
+    let assume_sorted = matches.get_flag("assume-sorted");
+    let out_path = matches
+        .get_one::<String>("output")
+        .cloned()
+        .unwrap_or_else(|| default_query_path(vcf_in));
+    let region = parse_region(region_str)?;
+
+    log_info!("[info] [query] --vcf {vcf_in}");
+    log_info!("[info] [query] --region {region_str}");
+    log_info!("[info] [query] --assume-sorted {assume_sorted}");
+    log_info!("[info] [query] --output {out_path}");
+
+    let reader = open_input(vcf_in)?;
+
+    let mut out = BufWriter::new(File::create(&out_path)?);
+    let mut lines_read: u64 = 0;
+    let mut emitted: u64 = 0;
+    let mut on_contig = false;
+    let mut stopped_early = false;
+
+    for line in reader.lines() {
+        let l = line?;
+        if l.starts_with('#') {
+            write!(out, "{}{}", l, eol())?;
+            continue;
+        }
+        lines_read += 1;
+
+        let mut it = l.split('\t');
+        let chrom = it.next().unwrap_or("");
+        let pos = it.next().and_then(|p| p.trim().parse::<u64>().ok());
+
+        if chrom != region.chrom {
+            if assume_sorted && on_contig {
+                // Sorted input: the matching contig is finished
+                stopped_early = true;
+                break;
+            }
+            continue;
+        }
+        on_contig = true;
+
+        let pos = match pos {
+            Some(p) => p,
+            None => continue,
+        };
+        if pos > region.end {
+            if assume_sorted {
+                stopped_early = true;
+                break;
+            }
+            continue;
+        }
+        if pos >= region.start {
+            write!(out, "{}{}", l, eol())?;
+            emitted += 1;
+        }
+    }
+    out.flush()?;
+
+    log_info!(
+        "[info] [query] Done: read {} records, emitted {}{} → {out_path}",
+        lines_read,
+        emitted,
+        if stopped_early {
+            " (stopped early: passed region end)"
+        } else {
+            ""
+        }
+    );
+    Ok(())
+}
@@ -0,0 +1,35 @@
+use std::sync::OnceLock;
+
+static QUIET: OnceLock<bool> = OnceLock::new();
+
+/// Turn `--quiet` on or off for the process (first call wins)
+pub fn set_quiet(on: bool) {
+    let _ = QUIET.set(on);
+}
+
+/// True when `--quiet` suppresses `[info]` output
+pub fn is_quiet() -> bool {
+    QUIET.get().copied().unwrap_or(false)
+}
+
+/// `println!` for progress/parameter messages; silent under `--quiet`. Warnings and errors go
+/// through `eprintln!` directly and are never suppressed.
+#[macro_export]
+macro_rules! log_info {
+    ($($arg:tt)*) => {
+        if !$crate::is_quiet() {
+            println!($($arg)*);
+        }
+    };
+}
+
+/// `eprintln!` for `[info]` messages kept off stdout (e.g. when the data goes there); silent
+/// under `--quiet`
+#[macro_export]
+macro_rules! elog_info {
+    ($($arg:tt)*) => {
+        if !$crate::is_quiet() {
+            eprintln!($($arg)*);
+        }
+    };
+}
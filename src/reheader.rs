@@ -0,0 +1,124 @@
+use crate::io_utils::open_input;
+use crate::line_sep::eol;
+use clap::ArgMatches;
+use std::fs::File;
+use std::io::{BufRead, BufWriter, Write};
+use std::path::Path;
+
+/// Default output: <same-dir>/<basename-without-.vcf[.gz]>.reheaded.vcf
+fn default_reheaded_path(vcf_in: &str) -> String {
+    let in_path = Path::new(vcf_in);
+    let parent = in_path.parent().unwrap_or(Path::new("."));
+    let fname = in_path
+        .file_name()
+        .and_then(|s| s.to_str())
+        .unwrap_or("output");
+    let stem = fname
+        .strip_suffix(".vcf.gz")
+        .or_else(|| fname.strip_suffix(".vcf"))
+        .unwrap_or(fname);
+    parent
+        .join(format!("{}.reheaded.vcf", stem))
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// Replace the header of a VCF wholesale.
+///
+/// Writes every line of `--header` followed by the body of `--vcf` (everything after its
+/// leading `#`-prefixed lines). Unlike `header`, nothing is inferred or merged.
+/// Unless `--force`, the `#CHROM` line of the new header must have as many columns as the
+/// first body record.
+pub fn reheader_main(matches: &ArgMatches) -> Result<(), Box<dyn std::error::Error>> {
+    let header_in = matches.get_one::<String>("header").unwrap();
+    let vcf_in = matches.get_one::<String>("vcf").unwrap();
+    let force = matches.get_flag("force");
+    let out_path = matches
+        .get_one::<String>("output")
+        .cloned()
+        .unwrap_or_else(|| default_reheaded_path(vcf_in));
+
+    log_info!("[info] [reheader] --header {header_in}");
+    log_info!("[info] [reheader] --vcf {vcf_in}");
+    log_info!("[info] [reheader] --output {out_path}");
+    log_info!("[info] [reheader] --force {force}");
+
+    // New header: keep every line, remember the #CHROM column count
+    let mut new_header: Vec<String> = Vec::new();
+    let mut header_cols: Option<usize> = None;
+    for line in open_input(header_in)?.lines() {
+        let l = line?;
+        if l.trim().is_empty() {
+            continue;
+        }
+        if !l.starts_with('#') {
+            eprintln!("[warn] Header file contains a non-'#' line; stopping header read there");
+            break;
+        }
+        if l.starts_with("#CHROM") {
+            header_cols = Some(l.split('\t').count());
+        }
+        new_header.push(l);
+    }
+    if header_cols.is_none() {
+        eprintln!(
+            "[warn] Header file {header_in} has no #CHROM line; downstream tools may reject the output."
+        );
+    }
+
+    // Skip the old header, then stream the body
+    let mut reader = open_input(vcf_in)?;
+    let mut first_body: Option<String> = None;
+    let mut buf = String::new();
+    loop {
+        buf.clear();
+        if reader.read_line(&mut buf)? == 0 {
+            break;
+        }
+        if buf.starts_with('#') {
+            continue;
+        }
+        first_body = Some(buf.trim_end_matches(['\n', '\r']).to_string());
+        break;
+    }
+
+    if let (Some(hc), Some(body)) = (header_cols, &first_body) {
+        let body_cols = body.split('\t').count();
+        if hc != body_cols {
+            if force {
+                eprintln!(
+                    "[warn] #CHROM has {hc} columns but body has {body_cols}; continuing due to --force"
+                );
+            } else {
+                return Err(Box::new(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!(
+                        "New #CHROM header has {hc} columns but VCF body has {body_cols}; use --force to override"
+                    ),
+                )));
+            }
+        }
+    }
+
+    let mut out = BufWriter::new(File::create(&out_path)?);
+    for l in &new_header {
+        write!(out, "{}{}", l, eol())?;
+    }
+    let mut body_lines: u64 = 0;
+    if let Some(first) = first_body {
+        write!(out, "{}{}", first, eol())?;
+        body_lines += 1;
+        for line in reader.lines() {
+            write!(out, "{}{}", line?, eol())?;
+            body_lines += 1;
+        }
+    }
+    out.flush()?;
+
+    log_info!(
+        "[info] [reheader] Done: {} header lines + {} body lines → {out_path}",
+        new_header.len(),
+        body_lines
+    );
+    Ok(())
+}
@@ -1,18 +1,29 @@
 // src/sort_main.rs
+use crate::chrom::{ChromCase, extract_chr_token};
+use crate::line_sep::eol;
+use crate::progress::Progress;
 use clap::ArgMatches;
-use flate2::read::MultiGzDecoder;
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
 use std::cmp::Ordering;
+use std::collections::BinaryHeap;
 use std::fs::File;
 use std::io::{BufRead, BufReader, BufWriter, Write};
 use std::path::Path;
 
-/// Build <orig_stem>.sorted.vcf in the *original VCF's directory*.
+/// Build <orig_stem>.sorted.vcf in the *original VCF's directory* (or `output_dir` if given).
 /// If `candidate_name` is provided (from --output), use its base name but
-/// still place it in the original directory and force the suffix to `.sorted.vcf`.
-fn sorted_in_original_dir(original_vcf: &str, candidate_name: Option<&str>) -> String {
+/// still place it in that directory and force the suffix to `.sorted.vcf`.
+fn sorted_in_original_dir(
+    original_vcf: &str,
+    candidate_name: Option<&str>,
+    output_dir: Option<&str>,
+) -> String {
     use std::path::Path;
-    let p = Path::new(original_vcf);
-    let dir = p.parent().unwrap_or_else(|| Path::new("."));
+    let p = Path::new(crate::io_utils::name_for_defaults(original_vcf));
+    let dir = output_dir
+        .map(Path::new)
+        .unwrap_or_else(|| p.parent().unwrap_or_else(|| Path::new(".")));
 
     let base = if let Some(name) = candidate_name {
         let fname = Path::new(name)
@@ -45,77 +56,101 @@ fn sorted_in_original_dir(original_vcf: &str, candidate_name: Option<&str>) -> S
     dir.join(base).to_string_lossy().into_owned()
 }
 
-/// Extract a token immediately following any "chr" substring (case-insensitive).
-/// Returns (Some(token), has_suffix, found_chr)
-#[inline]
-fn extract_chr_token(raw: &str) -> (Option<String>, bool, bool) {
-    let raw_lower = raw.to_ascii_lowercase();
-    if let Some(idx) = raw_lower.find("chr") {
-        let after = &raw[idx + 3..];
-        let mut token = String::new();
-        let mut has_suffix = false;
-        for c in after.chars() {
-            if c.is_ascii_alphanumeric() {
-                token.push(c);
-            } else {
-                has_suffix = true;
-                break;
-            }
-        }
-        if token.is_empty() {
-            return (None, has_suffix, true);
-        }
-        let tok_norm = if token.chars().all(|c| c.is_ascii_digit()) {
-            token
-        } else {
-            token.to_ascii_uppercase()
-        };
-        (Some(tok_norm), has_suffix, true)
-    } else {
-        (None, false, false)
+/// Rank of the sex/mito tokens, placed after the N numbered chromosomes: X->N+1, Y->N+2, M/MT->N+3
+fn sex_mito_rank(t: &str) -> Option<(u32, String)> {
+    let n = crate::chrom::autosomes();
+    match t {
+        "X" => Some((n + 1, "chrX".to_string())),
+        "Y" => Some((n + 2, "chrY".to_string())),
+        "M" | "MT" => Some((n + 3, "chrM".to_string())),
+        _ => None,
     }
 }
 
 /// Map any CHROM string to a rank and a normalized display like chr1..chr22, chrX, chrY, chrM
-/// Rank: 1..=22 -> 1..=22, X->23, Y->24, M/MT->25.
+/// Rank: 1..=N -> 1..=N (N = `--autosomes`, default 22), then X, Y, M/MT.
 /// Returns None if the chromosome cannot be interpreted (those will sort after known ones).
 #[inline]
-fn chrom_rank_and_display(raw: &str) -> Option<(u8, String)> {
-    // Prefer token after "chr"
-    if let (Some(tok), _suf, _found_chr) = extract_chr_token(raw) {
+fn chrom_rank_and_display(raw: &str) -> Option<(u32, String)> {
+    // Prefer token after "chr"; a suffix ("chr10_random", "chrUn_...") is its own contig and
+    // sorts naturally after the standard ones, except the "chrMT" spelling of chrM
+    if let (Some(tok), suf, _found_chr) = extract_chr_token(raw) {
         let t = tok.to_ascii_uppercase();
-        if let Ok(n) = t.parse::<u8>() {
-            if (1..=22).contains(&n) {
+        if suf {
+            let lower = raw.to_ascii_lowercase();
+            return if lower.ends_with("chrmt") {
+                sex_mito_rank("MT")
+            } else {
+                None
+            };
+        }
+        if let Ok(n) = t.parse::<u32>() {
+            if crate::chrom::is_std_autosome(n) {
                 return Some((n, format!("chr{}", n)));
             }
         }
-        return match t.as_str() {
-            "X" => Some((23, "chrX".to_string())),
-            "Y" => Some((24, "chrY".to_string())),
-            "M" | "MT" => Some((25, "chrM".to_string())),
-            _ => None,
-        };
+        return sex_mito_rank(&t);
     }
 
     // Accept bare tokens: "1", "2", ..., "22", "X", "Y", "M", "MT"
     let t = raw.trim();
     if t.chars().all(|c| c.is_ascii_digit()) {
-        if let Ok(n) = t.parse::<u8>() {
-            if (1..=22).contains(&n) {
+        if let Ok(n) = t.parse::<u32>() {
+            if crate::chrom::is_std_autosome(n) {
                 return Some((n, format!("chr{}", n)));
             }
         }
     }
-    match t.to_ascii_uppercase().as_str() {
-        "X" => Some((23, "chrX".to_string())),
-        "Y" => Some((24, "chrY".to_string())),
-        "M" | "MT" => Some((25, "chrM".to_string())),
-        _ => None,
+    sex_mito_rank(&t.to_ascii_uppercase())
+}
+
+/// Natural string order: runs of ASCII digits compare by numeric value, everything else
+/// byte-wise, e.g. "chr9_random" < "chr10_random", "scaffold_2" < "scaffold_10".
+/// Strings differing only in leading zeros compare equal.
+fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let (a, b) = (a.as_bytes(), b.as_bytes());
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() && j < b.len() {
+        if a[i].is_ascii_digit() && b[j].is_ascii_digit() {
+            let (si, sj) = (i, j);
+            while i < a.len() && a[i].is_ascii_digit() {
+                i += 1;
+            }
+            while j < b.len() && b[j].is_ascii_digit() {
+                j += 1;
+            }
+            // Compare without leading zeros: longer run is larger, then digit by digit
+            let da = trim_leading_zeros(&a[si..i]);
+            let db = trim_leading_zeros(&b[sj..j]);
+            let ord = da.len().cmp(&db.len()).then_with(|| da.cmp(db));
+            if ord != Ordering::Equal {
+                return ord;
+            }
+        } else {
+            if a[i] != b[j] {
+                return a[i].cmp(&b[j]);
+            }
+            i += 1;
+            j += 1;
+        }
     }
+    (a.len() - i).cmp(&(b.len() - j))
+}
+
+fn trim_leading_zeros(digits: &[u8]) -> &[u8] {
+    let nz = digits
+        .iter()
+        .position(|&d| d != b'0')
+        .unwrap_or(digits.len());
+    &digits[nz..]
 }
 
 /// Universal comparator for VCF lines (body only).
-/// Sorts by CHROM rank (1..22, X, Y, M), then by POS (numeric), then by ID (string).
+/// Sorts by CHROM rank (1..N with N = `--autosomes`, X, Y, M), then by POS (numeric),
+/// then by ID (string).
+/// Records that compare `Equal` keep their input order: every caller uses the stable `sort_by`,
+/// and the external merge breaks ties by run index, so the output is byte-identical across runs
+/// and between the in-memory and external strategies.
 #[inline]
 fn cmp_vcf_records(a: &str, b: &str) -> Ordering {
     let fa: Vec<&str> = a.split('\t').collect();
@@ -144,8 +179,8 @@ fn cmp_vcf_records(a: &str, b: &str) -> Ordering {
         (Some(_), _) => std::cmp::Ordering::Less,
         (_, Some(_)) => std::cmp::Ordering::Greater,
         (_, _) => {
-            // Fallback: lexicographic CHROM, then numeric POS
-            let ord = ca.cmp(cb);
+            // Fallback: natural CHROM order (scaffold_2 < scaffold_10), then numeric POS
+            let ord = natural_cmp(ca, cb).then_with(|| ca.cmp(cb));
             if ord != std::cmp::Ordering::Equal {
                 return ord;
             }
@@ -156,67 +191,793 @@ fn cmp_vcf_records(a: &str, b: &str) -> Ordering {
     }
 }
 
-pub fn sort_main(matches: &ArgMatches) -> Result<(), Box<dyn std::error::Error>> {
-    // Adjust these argument names if your CLI uses different flags
-    let input = matches
-        .get_one::<String>("vcf")
-        .or_else(|| matches.get_one::<String>("input"))
-        .ok_or("Missing --vcf/--input")?;
-    let candidate_name = matches.get_one::<String>("output").map(|s| s.as_str());
-    let output = sorted_in_original_dir(input, candidate_name);
-
-    println!("[info] [sort] --vcf {input}");
-    println!("[info] [sort] --output {output}");
-
-    // Open input (supports .vcf and .vcf.gz)
-    let infile = File::open(input)?;
-    let ext = Path::new(input)
-        .extension()
-        .and_then(|s| s.to_str())
-        .unwrap_or("");
-    let reader: Box<dyn BufRead> = if ext.eq_ignore_ascii_case("gz") {
-        Box::new(BufReader::new(MultiGzDecoder::new(infile)))
+/// One `--prefix` sort key: a 0-based column and its direction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SortKey {
+    pub col: usize,
+    pub desc: bool,
+}
+
+/// Column index for a VCF keyword (CHROM..FORMAT, case-insensitive) or a 0-based index
+fn column_index(name: &str) -> Option<usize> {
+    match name.trim_start_matches('#').to_ascii_uppercase().as_str() {
+        "CHROM" => Some(0),
+        "POS" => Some(1),
+        "ID" => Some(2),
+        "REF" => Some(3),
+        "ALT" => Some(4),
+        "QUAL" => Some(5),
+        "FILTER" => Some(6),
+        "INFO" => Some(7),
+        "FORMAT" => Some(8),
+        other => other.parse::<usize>().ok(),
+    }
+}
+
+/// Parse `--prefix`, e.g. `CHROM,POS:desc,3`: comma-separated columns, each optionally suffixed
+/// with `:asc` or `:desc`. `--reverse` flips the direction of every key.
+pub fn parse_sort_keys(spec: &str, reverse: bool) -> Result<Vec<SortKey>, String> {
+    let mut keys = Vec::new();
+    for part in spec.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+        let (name, dir) = match part.rsplit_once(':') {
+            Some((n, d)) => (n, Some(d)),
+            None => (part, None),
+        };
+        let desc = match dir.map(|d| d.to_ascii_lowercase()).as_deref() {
+            None | Some("asc") => false,
+            Some("desc") => true,
+            Some(d) => {
+                return Err(format!(
+                    "Invalid --prefix key '{part}': unknown direction '{d}' (asc|desc)"
+                ));
+            }
+        };
+        let col = column_index(name)
+            .ok_or_else(|| format!("Invalid --prefix key '{part}': unknown column '{name}'"))?;
+        keys.push(SortKey {
+            col,
+            desc: desc != reverse,
+        });
+    }
+    if keys.is_empty() {
+        return Err(format!("Invalid --prefix '{spec}': no columns given"));
+    }
+    Ok(keys)
+}
+
+/// Compare two body lines key by key, left to right; each column compares numerically when
+/// both values parse as numbers, lexically otherwise.
+pub fn cmp_by_keys(a: &str, b: &str, keys: &[SortKey]) -> Ordering {
+    let fa: Vec<&str> = a.split('\t').collect();
+    let fb: Vec<&str> = b.split('\t').collect();
+    for k in keys {
+        let ka = fa.get(k.col).copied().unwrap_or("");
+        let kb = fb.get(k.col).copied().unwrap_or("");
+        let ord = match (ka.parse::<f64>(), kb.parse::<f64>()) {
+            (Ok(na), Ok(nb)) => na.partial_cmp(&nb).unwrap_or(Ordering::Equal),
+            _ => ka.cmp(kb),
+        };
+        let ord = if k.desc { ord.reverse() } else { ord };
+        if ord != Ordering::Equal {
+            return ord;
+        }
+    }
+    Ordering::Equal
+}
+
+/// `--info-key ID[:asc|desc]`: sort by the numeric value of an INFO field (first value of a list)
+#[derive(Debug, Clone)]
+struct InfoSortKey {
+    id: String,
+    desc: bool,
+    /// `--info-missing`: records without a numeric value go last (default) or first
+    missing_last: bool,
+}
+
+impl InfoSortKey {
+    fn parse(spec: &str, reverse: bool, missing_last: bool) -> Result<Self, String> {
+        let (id, dir) = match spec.rsplit_once(':') {
+            Some((n, d)) => (n.trim(), Some(d)),
+            None => (spec.trim(), None),
+        };
+        let desc = match dir.map(|d| d.to_ascii_lowercase()).as_deref() {
+            None | Some("asc") => false,
+            Some("desc") => true,
+            Some(d) => {
+                return Err(format!(
+                    "Invalid --info-key '{spec}': unknown direction '{d}' (asc|desc)"
+                ));
+            }
+        };
+        if id.is_empty() || id.contains([';', '=', '\t']) {
+            return Err(format!("Invalid --info-key '{spec}': not an INFO ID"));
+        }
+        Ok(InfoSortKey {
+            id: id.to_string(),
+            desc: desc != reverse,
+            missing_last,
+        })
+    }
+
+    /// Numeric value of the key in a body line's INFO column, if present and parseable
+    fn value(&self, line: &str) -> Option<f64> {
+        let info = line.split('\t').nth(7)?;
+        let (_, v) = crate::header::info_items(info).find(|(k, _)| *k == self.id)?;
+        v?.split(',')
+            .next()?
+            .trim()
+            .parse::<f64>()
+            .ok()
+            .filter(|x| !x.is_nan())
+    }
+
+    fn cmp(&self, a: &str, b: &str) -> Ordering {
+        match (self.value(a), self.value(b)) {
+            (Some(x), Some(y)) => {
+                let ord = x.partial_cmp(&y).unwrap_or(Ordering::Equal);
+                if self.desc { ord.reverse() } else { ord }
+            }
+            (Some(_), None) if self.missing_last => Ordering::Less,
+            (Some(_), None) => Ordering::Greater,
+            (None, Some(_)) if self.missing_last => Ordering::Greater,
+            (None, Some(_)) => Ordering::Less,
+            (None, None) => Ordering::Equal,
+        }
+    }
+}
+
+/// Body record order of the `sort` subcommand, from `--prefix` / `--reverse` / `--info-key`
+#[derive(Debug, Clone)]
+enum RecordOrder {
+    /// `--prefix POS` (the default): the chromosome-aware `cmp_vcf_records`, optionally reversed
+    Genomic { desc: bool },
+    /// Any other key list: column-by-column numeric-or-lexical `cmp_by_keys`, as in `align --sort`
+    Keys(Vec<SortKey>),
+    /// `--info-key`: the INFO value first, ties broken by the `--prefix` order
+    Info(InfoSortKey, Box<RecordOrder>),
+}
+
+impl RecordOrder {
+    fn from_keys(keys: Vec<SortKey>) -> Self {
+        match keys.as_slice() {
+            [k] if k.col == 1 => RecordOrder::Genomic { desc: k.desc },
+            _ => RecordOrder::Keys(keys),
+        }
+    }
+
+    fn cmp(&self, a: &str, b: &str) -> Ordering {
+        match self {
+            RecordOrder::Genomic { desc: false } => cmp_vcf_records(a, b),
+            RecordOrder::Genomic { desc: true } => cmp_vcf_records(a, b).reverse(),
+            RecordOrder::Keys(keys) => cmp_by_keys(a, b, keys),
+            RecordOrder::Info(key, then) => key.cmp(a, b).then_with(|| then.cmp(a, b)),
+        }
+    }
+}
+
+/// Default for `--buffered-sort-threshold`: inputs (estimated uncompressed) below 2 GiB are sorted in memory.
+const DEFAULT_BUFFERED_SORT_THRESHOLD: u64 = 2 * 1024 * 1024 * 1024;
+/// Rough gzip expansion ratio used to estimate the uncompressed size of a `.gz` input.
+const GZ_SIZE_MULTIPLIER: u64 = 4;
+/// Bytes of body lines held in memory per sorted run in the external merge sort.
+const EXTERNAL_RUN_BYTES: usize = 256 * 1024 * 1024;
+/// Smallest in-memory body sorted in parallel with `--threads` > 1; below it the pool costs more
+/// than it saves
+#[cfg(feature = "rayon")]
+const PAR_SORT_MIN_LINES: usize = 100_000;
+
+/// A line from one sorted run, ordered for a min-heap by the run's `RecordOrder`.
+/// Ties are broken by run index so equal records keep their input order.
+struct MergeItem<'a> {
+    line: String,
+    run: usize,
+    order: &'a RecordOrder,
+}
+
+impl PartialEq for MergeItem<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for MergeItem<'_> {}
+
+impl PartialOrd for MergeItem<'_> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for MergeItem<'_> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // BinaryHeap is a max-heap: reverse to pop the smallest record first
+        self.order
+            .cmp(&self.line, &other.line)
+            .then(self.run.cmp(&other.run))
+            .reverse()
+    }
+}
+
+/// True for the `#CHROM` column header line, including a bare `#CHROM` without columns.
+fn is_column_header(l: &str) -> bool {
+    l.starts_with("#CHROM\t") || l.starts_with("#CHROM ") || l.trim_end() == "#CHROM"
+}
+
+/// Which INFO value wins when `--collapse-equal-alt` merges a key present in several records
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CollapseKeep {
+    First,
+    Last,
+}
+
+/// Merge INFO `add` into `acc` (ordered items): union of keys, conflicts resolved by `keep`.
+fn merge_info(acc: &mut Vec<(String, Option<String>)>, add: &str, keep: CollapseKeep) {
+    if add == "." || add.is_empty() {
+        return;
+    }
+    for item in add.split(';').filter(|s| !s.is_empty()) {
+        let (k, v) = match item.split_once('=') {
+            Some((k, v)) => (k, Some(v.to_string())),
+            None => (item, None),
+        };
+        match acc.iter_mut().find(|(ek, _)| ek == k) {
+            Some(existing) => {
+                if keep == CollapseKeep::Last {
+                    existing.1 = v;
+                }
+            }
+            None => acc.push((k.to_string(), v)),
+        }
+    }
+}
+
+/// Which record `--dedup` keeps from a run of duplicates
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DedupKeep {
+    First,
+    MaxQual,
+}
+
+/// How records with the same CHROM/POS/REF/ALT are reduced after sorting
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum NeighbourMerge {
+    /// `--collapse-equal-alt`: one record with the union of the INFO keys
+    Collapse(CollapseKeep),
+    /// `--dedup`: one of the records, unchanged
+    Dedup(DedupKeep),
+}
+
+/// CHROM/POS/REF/ALT of a body line, compared as raw strings by `--dedup`
+fn dedup_key(line: &str) -> Option<[&str; 4]> {
+    let f: Vec<&str> = line.splitn(6, '\t').collect();
+    match f.as_slice() {
+        [chrom, pos, _, r, a, ..] => Some([chrom, pos, r, a]),
+        _ => None,
+    }
+}
+
+/// QUAL for `--dedup-keep max-qual`; missing or unparsable sorts below any number
+fn qual_of(line: &str) -> f64 {
+    line.split('\t')
+        .nth(5)
+        .and_then(|q| q.parse::<f64>().ok())
+        .unwrap_or(f64::NEG_INFINITY)
+}
+
+/// Record being collapsed: first record's columns plus the merged INFO items
+struct PendingRecord {
+    key: String,
+    fields: Vec<String>,
+    info: Vec<(String, Option<String>)>,
+}
+
+impl PendingRecord {
+    fn into_line(mut self) -> String {
+        if self.fields.len() > 7 {
+            self.fields[7] = if self.info.is_empty() {
+                ".".to_string()
+            } else {
+                self.info
+                    .iter()
+                    .map(|(k, v)| match v {
+                        Some(v) => format!("{k}={v}"),
+                        None => k.clone(),
+                    })
+                    .collect::<Vec<_>>()
+                    .join(";")
+            };
+        }
+        self.fields.join("\t")
+    }
+}
+
+/// Post-sort emission: writes body lines, optionally collapsing records that share
+/// CHROM/POS/REF/ALT (`make_key` for standard contigs, the raw columns otherwise). Records are
+/// matched within the run of consecutive records with the same POS, so equal records still meet
+/// when others at that position sort between them (e.g. by ID). Non-INFO columns come from the
+/// first record of a match; a position's records are written in first-seen order.
+/// With `--dedup` (exclusive with collapsing), records at the same POS with the same raw
+/// CHROM/POS/REF/ALT are reduced to one instead, unchanged.
+struct BodyWriter<W: Write> {
+    w: W,
+    collapse: Option<CollapseKeep>,
+    /// POS of the records in `pending` / `held`
+    site: Option<String>,
+    pending: Vec<PendingRecord>,
+    dedup: Option<DedupKeep>,
+    held: Vec<String>,
+    written: usize,
+    collapsed: usize,
+}
+
+impl<W: Write> BodyWriter<W> {
+    fn new(w: W, merge: Option<NeighbourMerge>) -> Self {
+        BodyWriter {
+            w,
+            collapse: match merge {
+                Some(NeighbourMerge::Collapse(k)) => Some(k),
+                _ => None,
+            },
+            site: None,
+            pending: Vec::new(),
+            dedup: match merge {
+                Some(NeighbourMerge::Dedup(k)) => Some(k),
+                _ => None,
+            },
+            held: Vec::new(),
+            written: 0,
+            collapsed: 0,
+        }
+    }
+
+    fn push(&mut self, line: String) -> std::io::Result<()> {
+        if let Some(keep) = self.dedup {
+            return self.push_dedup(line, keep);
+        }
+        let keep = match self.collapse {
+            Some(k) => k,
+            None => {
+                write!(self.w, "{}{}", line, eol())?;
+                self.written += 1;
+                return Ok(());
+            }
+        };
+        let fields: Vec<String> = line.split('\t').map(|s| s.to_string()).collect();
+        if fields.len() < 5 {
+            // Short line: nothing to match on, emit untouched
+            self.flush_pending()?;
+            write!(self.w, "{}{}", line, eol())?;
+            self.written += 1;
+            return Ok(());
+        }
+        if self.site.as_deref() != Some(fields[1].as_str()) {
+            self.flush_pending()?;
+            self.site = Some(fields[1].clone());
+        }
+        let key = crate::name::make_key(&fields[0], &fields[1], &fields[3], &fields[4], ":")
+            .unwrap_or_else(|| {
+                // Non-standard contig: compare the raw columns
+                format!("{}\t{}\t{}\t{}", fields[0], fields[1], fields[3], fields[4])
+            });
+        let info = fields.get(7).map(|s| s.as_str()).unwrap_or(".");
+        match self.pending.iter_mut().find(|p| p.key == key) {
+            Some(p) => {
+                merge_info(&mut p.info, info, keep);
+                self.collapsed += 1;
+            }
+            None => {
+                let mut merged = Vec::new();
+                merge_info(&mut merged, info, keep);
+                self.pending.push(PendingRecord {
+                    key,
+                    fields,
+                    info: merged,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// `--dedup`: hold one record per CHROM/POS/REF/ALT of the current POS; on ties the
+    /// earlier record stays
+    fn push_dedup(&mut self, line: String, keep: DedupKeep) -> std::io::Result<()> {
+        let pos = match dedup_key(&line) {
+            Some(k) => k[1].to_string(),
+            None => {
+                // Short line: nothing to match on, emit untouched
+                self.flush_pending()?;
+                write!(self.w, "{}{}", line, eol())?;
+                self.written += 1;
+                return Ok(());
+            }
+        };
+        if self.site.as_deref() != Some(pos.as_str()) {
+            self.flush_pending()?;
+            self.site = Some(pos);
+        }
+        let key = dedup_key(&line);
+        match self.held.iter().position(|h| dedup_key(h) == key) {
+            Some(i) => {
+                self.collapsed += 1;
+                if keep == DedupKeep::MaxQual && qual_of(&line) > qual_of(&self.held[i]) {
+                    self.held[i] = line;
+                }
+            }
+            None => self.held.push(line),
+        }
+        Ok(())
+    }
+
+    fn flush_pending(&mut self) -> std::io::Result<()> {
+        for p in std::mem::take(&mut self.pending) {
+            write!(self.w, "{}{}", p.into_line(), eol())?;
+            self.written += 1;
+        }
+        self.site = None;
+        for h in std::mem::take(&mut self.held) {
+            write!(self.w, "{}{}", h, eol())?;
+            self.written += 1;
+        }
+        Ok(())
+    }
+
+    /// Flush everything; returns (records written, records collapsed or deduplicated away)
+    fn finish(mut self) -> std::io::Result<(usize, usize)> {
+        self.flush_pending()?;
+        self.w.flush()?;
+        Ok((self.written, self.collapsed))
+    }
+}
+
+/// Write the pre-header and the column header (or the fallback column header).
+fn write_header<W: Write>(
+    w: &mut W,
+    pre_header: &[String],
+    col_header: &Option<String>,
+) -> std::io::Result<()> {
+    for h in pre_header {
+        write!(w, "{}{}", h, eol())?;
+    }
+    if let Some(h) = col_header {
+        write!(w, "{}{}", h, eol())?;
     } else {
-        Box::new(BufReader::new(infile))
-    };
+        // Fallback if there's no column header (rare, non-compliant VCF)
+        write!(
+            w,
+            "#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\tFORMAT{}",
+            eol()
+        )?;
+    }
+    Ok(())
+}
 
+/// Sort a body run in memory and spill it to `path`.
+fn spill_run(run: &mut Vec<String>, path: &str, order: &RecordOrder) -> std::io::Result<()> {
+    run.sort_by(|a, b| order.cmp(a, b));
+    let mut w = BufWriter::new(File::create(path)?);
+    for l in run.iter() {
+        write!(w, "{}{}", l, eol())?;
+    }
+    w.flush()?;
+    run.clear();
+    Ok(())
+}
+
+/// In-memory strategy: collect the whole body, sort it, write it.
+fn sort_in_memory(
+    reader: Box<dyn BufRead>,
+    output: &str,
+    order: &RecordOrder,
+    case: ChromCase,
+    merge: Option<NeighbourMerge>,
+    threads: usize,
+) -> std::io::Result<(usize, usize)> {
     // Collect header and body
     let mut pre_header: Vec<String> = Vec::new(); // lines starting with "##"
     let mut col_header: Option<String> = None; // line starting with "#CHROM"
     let mut body: Vec<String> = Vec::new(); // variant lines
 
+    let mut progress = Progress::new("sort");
     for line in reader.lines() {
         let l = line?;
+        progress.tick(l.len());
         if l.starts_with("##") {
-            pre_header.push(l);
-        } else if l.starts_with("#CHROM\t") || l.starts_with("#CHROM ") {
+            pre_header.push(case.apply_to_contig_line(&l));
+        } else if is_column_header(&l) {
             col_header = Some(l);
         } else {
             // everything after #CHROM is body; if a malformed file had data before, we still treat as body
-            body.push(l);
+            body.push(case.apply_to_body_line(l));
         }
     }
+    progress.finish();
 
-    // Sort body with the universal comparator
-    // For very large files, consider external merge sort; this keeps it simple and fast for typical sizes.
-    body.sort_by(|a, b| cmp_vcf_records(a, b));
+    // Sort body (stable: equal keys keep their input order). `par_sort_by` is a stable merge
+    // sort too, so the output is the same for any --threads.
+    #[cfg(feature = "rayon")]
+    {
+        if threads > 1 && body.len() >= PAR_SORT_MIN_LINES {
+            let pool = rayon::ThreadPoolBuilder::new()
+                .num_threads(threads)
+                .build()
+                .map_err(std::io::Error::other)?;
+            pool.install(|| body.par_sort_by(|a, b| order.cmp(a, b)));
+        } else {
+            body.sort_by(|a, b| order.cmp(a, b));
+        }
+    }
+    // Without rayon --threads is accepted but the sort stays sequential
+    #[cfg(not(feature = "rayon"))]
+    {
+        let _ = threads;
+        body.sort_by(|a, b| order.cmp(a, b));
+    }
 
     // Write output
-    let mut w = BufWriter::new(File::create(&output)?);
-    for h in &pre_header {
-        writeln!(w, "{}", h)?;
+    let mut w = BufWriter::new(File::create(output)?);
+    write_header(&mut w, &pre_header, &col_header)?;
+    let mut bw = BodyWriter::new(w, merge);
+    for l in body {
+        bw.push(l)?;
+    }
+    bw.finish()
+}
+
+/// External strategy: spill sorted runs of at most `run_bytes` to `<run_base>.run<N>.tmp`,
+/// then k-way merge them with a `BinaryHeap` using the same comparator.
+fn sort_external(
+    reader: Box<dyn BufRead>,
+    output: &str,
+    run_base: &str,
+    run_bytes: usize,
+    order: &RecordOrder,
+    case: ChromCase,
+    merge: Option<NeighbourMerge>,
+) -> std::io::Result<(usize, usize)> {
+    let mut pre_header: Vec<String> = Vec::new();
+    let mut col_header: Option<String> = None;
+    let mut run: Vec<String> = Vec::new();
+    let mut run_size: usize = 0;
+    let mut run_paths: Vec<String> = Vec::new();
+
+    let mut progress = Progress::new("sort");
+    for line in reader.lines() {
+        let l = line?;
+        progress.tick(l.len());
+        if l.starts_with("##") {
+            pre_header.push(case.apply_to_contig_line(&l));
+        } else if is_column_header(&l) {
+            col_header = Some(l);
+        } else {
+            let l = case.apply_to_body_line(l);
+            run_size += l.len() + 1;
+            run.push(l);
+            if run_size >= run_bytes {
+                let path = format!("{}.run{}.tmp", run_base, run_paths.len());
+                spill_run(&mut run, &path, order)?;
+                run_paths.push(path);
+                run_size = 0;
+            }
+        }
+    }
+    progress.finish();
+    if !run.is_empty() {
+        let path = format!("{}.run{}.tmp", run_base, run_paths.len());
+        spill_run(&mut run, &path, order)?;
+        run_paths.push(path);
+    }
+    log_info!(
+        "[info] [sort] External merge sort: {} sorted run(s) spilled",
+        run_paths.len()
+    );
+
+    // k-way merge
+    let mut readers = Vec::with_capacity(run_paths.len());
+    for p in &run_paths {
+        readers.push(BufReader::new(File::open(p)?).lines());
+    }
+    let mut heap: BinaryHeap<MergeItem> = BinaryHeap::with_capacity(readers.len());
+    for (i, r) in readers.iter_mut().enumerate() {
+        if let Some(l) = r.next() {
+            heap.push(MergeItem {
+                line: l?,
+                run: i,
+                order,
+            });
+        }
+    }
+
+    let mut w = BufWriter::new(File::create(output)?);
+    write_header(&mut w, &pre_header, &col_header)?;
+    let mut bw = BodyWriter::new(w, merge);
+    while let Some(item) = heap.pop() {
+        if let Some(l) = readers[item.run].next() {
+            heap.push(MergeItem {
+                line: l?,
+                run: item.run,
+                order,
+            });
+        }
+        bw.push(item.line)?;
+    }
+    let counts = bw.finish()?;
+
+    for p in &run_paths {
+        let _ = std::fs::remove_file(p);
     }
-    if let Some(h) = &col_header {
-        writeln!(w, "{}", h)?;
+    Ok(counts)
+}
+
+pub fn sort_main(matches: &ArgMatches) -> Result<(), Box<dyn std::error::Error>> {
+    // Adjust these argument names if your CLI uses different flags
+    let input = matches
+        .get_one::<String>("vcf")
+        .or_else(|| matches.get_one::<String>("input"))
+        .ok_or("Missing --vcf/--input")?;
+    let candidate_name = matches.get_one::<String>("output").map(|s| s.as_str());
+    let output_dir = matches.get_one::<String>("output-dir").map(|s| s.as_str());
+    if let Some(d) = output_dir {
+        std::fs::create_dir_all(d)?;
+    }
+    let output = sorted_in_original_dir(input, candidate_name, output_dir);
+    let mut threshold: u64 = matches
+        .get_one::<String>("buffered-sort-threshold")
+        .and_then(|s| s.parse::<u64>().ok())
+        .unwrap_or(DEFAULT_BUFFERED_SORT_THRESHOLD);
+    // --max-mem caps both the in-memory sort (larger bodies go external) and the run size
+    let max_mem: Option<u64> = match matches.get_one::<String>("max-mem") {
+        Some(s) => match s.trim().parse::<u64>() {
+            Ok(b) if b > 0 => Some(b),
+            _ => {
+                return Err(
+                    format!("--max-mem expects a positive number of bytes, got '{s}'").into(),
+                );
+            }
+        },
+        None => None,
+    };
+    let run_bytes = match max_mem {
+        Some(m) => {
+            threshold = threshold.min(m);
+            usize::try_from(m).unwrap_or(usize::MAX)
+        }
+        None => EXTERNAL_RUN_BYTES,
+    };
+
+    let prefix = matches
+        .get_one::<String>("prefix")
+        .map(|s| s.as_str())
+        .unwrap_or("POS");
+    let reverse = matches.get_flag("reverse");
+    let mut order = RecordOrder::from_keys(parse_sort_keys(prefix, reverse)?);
+    let info_key = matches.get_one::<String>("info-key");
+    if let Some(spec) = info_key {
+        let missing_last = matches
+            .get_one::<String>("info-missing")
+            .map(|s| s.as_str())
+            != Some("first");
+        let key = InfoSortKey::parse(spec, reverse, missing_last)?;
+        order = RecordOrder::Info(key, Box::new(order));
+    }
+
+    log_info!("[info] [sort] --vcf {input}");
+    log_info!("[info] [sort] --output {output}");
+    log_info!("[info] [sort] --prefix {prefix}");
+    log_info!("[info] [sort] --reverse {reverse}");
+    if let RecordOrder::Info(key, _) = &order {
+        log_info!(
+            "[info] [sort] --info-key {} ({}, missing values {})",
+            key.id,
+            if key.desc { "desc" } else { "asc" },
+            if key.missing_last { "last" } else { "first" }
+        );
+    }
+    let tabix = matches.get_flag("tabix");
+    log_info!("[info] [sort] --tabix {tabix}");
+    if tabix && !matches!(order, RecordOrder::Genomic { desc: false }) {
+        return Err(
+            "--tabix needs coordinate order: drop --prefix/--reverse/--info-key (default POS ascending)".into(),
+        );
+    }
+    let chrom_case = ChromCase::from_arg(matches.get_one::<String>("chrom-case"));
+    log_info!("[info] [sort] --buffered-sort-threshold {threshold}");
+    log_info!(
+        "[info] [sort] --max-mem {}",
+        max_mem.map_or("None".to_string(), |m| m.to_string())
+    );
+    log_info!("[info] [sort] --chrom-case {:?}", chrom_case);
+    let threads: usize = match matches.get_one::<String>("threads") {
+        Some(t) => match t.trim().parse::<usize>() {
+            Ok(n) if n > 0 => n,
+            _ => return Err(format!("--threads expects a positive integer, got '{t}'").into()),
+        },
+        None => 1,
+    };
+    log_info!("[info] [sort] --threads {threads}");
+    let collapse = if matches.get_flag("collapse-equal-alt") {
+        match matches
+            .get_one::<String>("collapse-keep")
+            .map(|s| s.as_str())
+        {
+            Some("first") => Some(CollapseKeep::First),
+            _ => Some(CollapseKeep::Last),
+        }
     } else {
-        // Fallback if there's no column header (rare, non-compliant VCF)
-        writeln!(w, "#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\tFORMAT")?;
+        None
+    };
+    log_info!("[info] [sort] --collapse-equal-alt {:?}", collapse);
+    let dedup = if matches.get_flag("dedup") {
+        match matches.get_one::<String>("dedup-keep").map(|s| s.as_str()) {
+            Some("max-qual") => Some(DedupKeep::MaxQual),
+            _ => Some(DedupKeep::First),
+        }
+    } else {
+        None
+    };
+    log_info!("[info] [sort] --dedup {:?}", dedup);
+    // clap rejects --collapse-equal-alt together with --dedup
+    let merge = collapse
+        .map(NeighbourMerge::Collapse)
+        .or(dedup.map(NeighbourMerge::Dedup));
+
+    // Pick a strategy from the (estimated uncompressed) input size; stdin has no known size,
+    // so it always goes through the bounded-memory external sort
+    let external = if crate::io_utils::is_stdin(input) {
+        log_info!("[info] [sort] Input size unknown (stdin); strategy: external merge sort");
+        true
+    } else {
+        let on_disk = std::fs::metadata(input)?.len();
+        let ext = Path::new(input)
+            .extension()
+            .and_then(|s| s.to_str())
+            .unwrap_or("");
+        let estimated = if ext.eq_ignore_ascii_case("gz") {
+            on_disk.saturating_mul(GZ_SIZE_MULTIPLIER)
+        } else {
+            on_disk
+        };
+        let external = estimated >= threshold;
+        log_info!(
+            "[info] [sort] Input size {} bytes (estimated uncompressed {}); strategy: {}",
+            on_disk,
+            estimated,
+            if external {
+                "external merge sort"
+            } else {
+                "in-memory"
+            }
+        );
+        external
+    };
+
+    // Input: .vcf, .vcf.gz or stdin (`-`)
+    let reader = crate::io_utils::open_input(input)?;
+
+    let (n, collapsed) = if external {
+        let run_base = crate::io_utils::temp_path(input, &output, "");
+        sort_external(
+            reader, &output, &run_base, run_bytes, &order, chrom_case, merge,
+        )?
+    } else {
+        sort_in_memory(reader, &output, &order, chrom_case, merge, threads)?
+    };
+
+    if collapse.is_some() {
+        log_info!("[info] [sort] Collapsed {collapsed} record(s) into equal-ALT neighbours");
     }
-    for l in &body {
-        writeln!(w, "{}", l)?;
+    if dedup.is_some() {
+        log_info!("[info] [sort] Removed {collapsed} duplicate record(s)");
     }
-    w.flush()?;
+    log_info!("[info] [sort] Done: {n} records → {output}");
 
-    println!("[info] [sort] Done → {output}");
+    // --tabix: recompress the sorted file as BGZF and index it; the plain file is replaced
+    if tabix {
+        let gz_path = format!("{output}.gz");
+        let (indexed, contigs) = crate::tabix::bgzip_and_index(&output, &gz_path)?;
+        std::fs::remove_file(&output)?;
+        log_info!(
+            "[info] [sort] Wrote BGZF {gz_path} and index {gz_path}.tbi ({indexed} records on {contigs} contig(s))"
+        );
+    }
     Ok(())
 }
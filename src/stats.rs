@@ -0,0 +1,62 @@
+use std::sync::OnceLock;
+
+/// How final per-subcommand stats are reported (`--stats-format`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StatsFormat {
+    /// `[summary] key=value ...` on stderr
+    #[default]
+    Text,
+    /// One JSON object per line on stderr
+    Json,
+}
+
+static STATS_FORMAT: OnceLock<StatsFormat> = OnceLock::new();
+
+/// Set the process-wide stats format from the `--stats-format` value (first call wins)
+pub fn set_format(value: &str) {
+    let fmt = if value.eq_ignore_ascii_case("json") {
+        StatsFormat::Json
+    } else {
+        StatsFormat::Text
+    };
+    let _ = STATS_FORMAT.set(fmt);
+}
+
+pub fn format() -> StatsFormat {
+    STATS_FORMAT.get().copied().unwrap_or_default()
+}
+
+fn json_escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Emit the final stats of a subcommand on stderr, as text or as a single JSON line
+/// `{"command":"<command>","key":value,...}`.
+pub fn emit(command: &str, fields: &[(&str, u64)]) {
+    match format() {
+        StatsFormat::Text => {
+            let kv: Vec<String> = fields.iter().map(|(k, v)| format!("{k}={v}")).collect();
+            eprintln!("[summary] {}", kv.join(" "));
+        }
+        StatsFormat::Json => {
+            let mut line = format!("{{\"command\":\"{}\"", json_escape(command));
+            for (k, v) in fields {
+                line.push_str(&format!(",\"{}\":{}", json_escape(k), v));
+            }
+            line.push('}');
+            eprintln!("{line}");
+        }
+    }
+}
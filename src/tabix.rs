@@ -0,0 +1,321 @@
+// src/tabix.rs
+// BGZF (block gzip) writer and tabix (`.tbi`) index for coordinate-sorted VCFs (`sort --tabix`)
+use flate2::Compression;
+use flate2::Crc;
+use flate2::write::DeflateEncoder;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+
+/// Uncompressed bytes per BGZF block (same as bgzip)
+const BGZF_BLOCK_SIZE: usize = 0xff00;
+/// Empty BGZF block that marks the end of the file
+const BGZF_EOF: [u8; 28] = [
+    0x1f, 0x8b, 0x08, 0x04, 0x00, 0x00, 0x00, 0x00, 0x00, 0xff, 0x06, 0x00, 0x42, 0x43, 0x02, 0x00,
+    0x1b, 0x00, 0x03, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+];
+/// Linear index window: 16 kb (`min_shift` 14)
+const TBI_MIN_SHIFT: u32 = 14;
+/// htslib's pseudo-bin carrying per-reference offsets and record counts
+const TBI_META_BIN: u32 = 37450;
+
+/// Writes BGZF blocks and reports the virtual offset (`block_start << 16 | offset_in_block`)
+/// of the next byte, which is what the index points at.
+struct BgzfWriter<W: Write> {
+    inner: W,
+    buf: Vec<u8>,
+    /// Compressed bytes written so far, i.e. the file offset of the next block
+    block_start: u64,
+}
+
+impl<W: Write> BgzfWriter<W> {
+    fn new(inner: W) -> Self {
+        BgzfWriter {
+            inner,
+            buf: Vec::with_capacity(BGZF_BLOCK_SIZE),
+            block_start: 0,
+        }
+    }
+
+    fn virtual_offset(&self) -> u64 {
+        (self.block_start << 16) | self.buf.len() as u64
+    }
+
+    fn write_all(&mut self, mut data: &[u8]) -> std::io::Result<()> {
+        while !data.is_empty() {
+            let n = (BGZF_BLOCK_SIZE - self.buf.len()).min(data.len());
+            self.buf.extend_from_slice(&data[..n]);
+            data = &data[n..];
+            // Flush as soon as a block is full so virtual offsets never point past its end
+            if self.buf.len() == BGZF_BLOCK_SIZE {
+                self.flush_block()?;
+            }
+        }
+        Ok(())
+    }
+
+    fn flush_block(&mut self) -> std::io::Result<()> {
+        if self.buf.is_empty() {
+            return Ok(());
+        }
+        let mut enc = DeflateEncoder::new(Vec::new(), Compression::default());
+        enc.write_all(&self.buf)?;
+        let cdata = enc.finish()?;
+        let mut crc = Crc::new();
+        crc.update(&self.buf);
+
+        // gzip header with the BC extra subfield holding the total block size - 1
+        let bsize = (cdata.len() + 25) as u16;
+        let mut header = [
+            0x1f, 0x8b, 0x08, 0x04, 0x00, 0x00, 0x00, 0x00, 0x00, 0xff, 0x06, 0x00, 0x42, 0x43,
+            0x02, 0x00, 0x00, 0x00,
+        ];
+        header[16..18].copy_from_slice(&bsize.to_le_bytes());
+        self.inner.write_all(&header)?;
+        self.inner.write_all(&cdata)?;
+        self.inner.write_all(&crc.sum().to_le_bytes())?;
+        self.inner
+            .write_all(&(self.buf.len() as u32).to_le_bytes())?;
+        self.block_start += bsize as u64 + 1;
+        self.buf.clear();
+        Ok(())
+    }
+
+    fn finish(mut self) -> std::io::Result<W> {
+        self.flush_block()?;
+        self.inner.write_all(&BGZF_EOF)?;
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+}
+
+/// Smallest bin of the UCSC/htslib binning scheme containing the 0-based `[beg, end)`
+fn reg2bin(beg: u64, end: u64) -> u32 {
+    let end = end.max(beg + 1) - 1;
+    let mut level_first = ((1u64 << 15) - 1) / 7;
+    let mut shift = TBI_MIN_SHIFT;
+    while level_first > 0 {
+        if beg >> shift == end >> shift {
+            return (level_first + (beg >> shift)) as u32;
+        }
+        shift += 3;
+        level_first = (level_first - 1) / 8;
+    }
+    0
+}
+
+/// Index of one reference (CHROM): chunks per bin, the linear index and its meta counts
+#[derive(Default)]
+struct RefIndex {
+    bins: BTreeMap<u32, Vec<(u64, u64)>>,
+    linear: Vec<u64>,
+    first_voff: u64,
+    last_voff: u64,
+    n_records: u64,
+}
+
+impl RefIndex {
+    fn add(&mut self, beg: u64, end: u64, voff_beg: u64, voff_end: u64) {
+        if self.n_records == 0 {
+            self.first_voff = voff_beg;
+        }
+        self.n_records += 1;
+        self.last_voff = voff_end;
+
+        let chunks = self.bins.entry(reg2bin(beg, end)).or_default();
+        match chunks.last_mut() {
+            // Records are written back to back: extend the previous chunk
+            Some(last) if last.1 == voff_beg => last.1 = voff_end,
+            _ => chunks.push((voff_beg, voff_end)),
+        }
+
+        let w_beg = (beg >> TBI_MIN_SHIFT) as usize;
+        let w_end = ((end.max(beg + 1) - 1) >> TBI_MIN_SHIFT) as usize;
+        if self.linear.len() <= w_end {
+            self.linear.resize(w_end + 1, u64::MAX);
+        }
+        for w in &mut self.linear[w_beg..=w_end] {
+            if *w == u64::MAX {
+                *w = voff_beg;
+            }
+        }
+    }
+
+    fn write<W: Write>(&self, w: &mut BgzfWriter<W>) -> std::io::Result<()> {
+        w.write_all(&(self.bins.len() as i32 + 1).to_le_bytes())?;
+        for (bin, chunks) in &self.bins {
+            w.write_all(&bin.to_le_bytes())?;
+            w.write_all(&(chunks.len() as i32).to_le_bytes())?;
+            for (b, e) in chunks {
+                w.write_all(&b.to_le_bytes())?;
+                w.write_all(&e.to_le_bytes())?;
+            }
+        }
+        // Pseudo-bin: (first, last) virtual offsets, then (mapped, unmapped) record counts
+        w.write_all(&TBI_META_BIN.to_le_bytes())?;
+        w.write_all(&2i32.to_le_bytes())?;
+        for v in [self.first_voff, self.last_voff, self.n_records, 0] {
+            w.write_all(&v.to_le_bytes())?;
+        }
+
+        // Windows without a record of their own point at the next record after them
+        let mut linear = self.linear.clone();
+        let mut next = self.last_voff;
+        for v in linear.iter_mut().rev() {
+            if *v == u64::MAX {
+                *v = next;
+            } else {
+                next = *v;
+            }
+        }
+        w.write_all(&(linear.len() as i32).to_le_bytes())?;
+        for v in &linear {
+            w.write_all(&v.to_le_bytes())?;
+        }
+        Ok(())
+    }
+}
+
+/// Compress the sorted VCF `plain` into BGZF at `gz_path` and write `<gz_path>.tbi` next to it.
+/// Records must be grouped by CHROM and ascending by POS within each CHROM.
+/// Returns the number of indexed records and CHROMs.
+pub fn bgzip_and_index(plain: &str, gz_path: &str) -> std::io::Result<(u64, usize)> {
+    let mut reader = BufReader::new(File::open(plain)?);
+    let mut bgzf = BgzfWriter::new(BufWriter::new(File::create(gz_path)?));
+
+    let mut names: Vec<String> = Vec::new();
+    let mut refs: Vec<RefIndex> = Vec::new();
+    let mut last_pos: u64 = 0;
+    let mut line: Vec<u8> = Vec::new();
+    loop {
+        line.clear();
+        if reader.read_until(b'\n', &mut line)? == 0 {
+            break;
+        }
+        let voff_beg = bgzf.virtual_offset();
+        bgzf.write_all(&line)?;
+        let voff_end = bgzf.virtual_offset();
+
+        let text = String::from_utf8_lossy(&line);
+        let text = text.trim_end_matches(['\n', '\r']);
+        if text.is_empty() || text.starts_with('#') {
+            continue;
+        }
+        let mut it = text.split('\t');
+        let chrom = it.next().unwrap_or("");
+        let pos: u64 = it.next().and_then(|p| p.parse().ok()).ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("--tabix: record without a numeric POS: {text}"),
+            )
+        })?;
+        let ref_len = it.nth(1).map(|r| r.len() as u64).unwrap_or(1).max(1);
+
+        if names.last().map(|n| n.as_str()) != Some(chrom) {
+            if names.iter().any(|n| n == chrom) {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!(
+                        "--tabix: CHROM {chrom} appears in more than one block; input is not sorted by CHROM"
+                    ),
+                ));
+            }
+            names.push(chrom.to_string());
+            refs.push(RefIndex::default());
+            last_pos = 0;
+        }
+        if pos < last_pos {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!(
+                    "--tabix: {chrom}:{pos} follows {chrom}:{last_pos}; input is not sorted by POS"
+                ),
+            ));
+        }
+        last_pos = pos;
+
+        let beg = pos.saturating_sub(1);
+        refs.last_mut().expect("a reference was pushed above").add(
+            beg,
+            beg + ref_len,
+            voff_beg,
+            voff_end,
+        );
+    }
+    bgzf.finish()?;
+
+    // .tbi: header (VCF preset), reference names, one index per reference; itself BGZF-compressed
+    let mut idx = BgzfWriter::new(BufWriter::new(File::create(format!("{gz_path}.tbi"))?));
+    idx.write_all(b"TBI\x01")?;
+    // n_ref, format (2 = VCF), col_seq, col_beg, col_end, meta char, skip
+    for v in [names.len() as i32, 2, 1, 2, 0, '#' as i32, 0] {
+        idx.write_all(&v.to_le_bytes())?;
+    }
+    let mut name_block: Vec<u8> = Vec::new();
+    for n in &names {
+        name_block.extend_from_slice(n.as_bytes());
+        name_block.push(0);
+    }
+    idx.write_all(&(name_block.len() as i32).to_le_bytes())?;
+    idx.write_all(&name_block)?;
+    for r in &refs {
+        r.write(&mut idx)?;
+    }
+    // Records without coordinates (none for VCF; header lines are not counted)
+    idx.write_all(&0u64.to_le_bytes())?;
+    idx.finish()?;
+
+    Ok((refs.iter().map(|r| r.n_records).sum(), names.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn index_err(name: &str, body: &str) -> String {
+        let dir = std::env::temp_dir();
+        let plain = dir.join(format!(
+            "gfa2bin-aligner-tabix-{}-{name}.vcf",
+            std::process::id()
+        ));
+        let gz = format!("{}.gz", plain.display());
+        std::fs::write(&plain, format!("#CHROM\tPOS\tID\tREF\tALT\n{body}")).unwrap();
+        let err = bgzip_and_index(plain.to_str().unwrap(), &gz).unwrap_err();
+        for p in [
+            plain.to_string_lossy().into_owned(),
+            gz.clone(),
+            format!("{gz}.tbi"),
+        ] {
+            let _ = std::fs::remove_file(p);
+        }
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        err.to_string()
+    }
+
+    #[test]
+    fn unsorted_pos_is_rejected() {
+        let err = index_err("pos", "chr1\t20\t.\tA\tG\nchr1\t10\t.\tA\tG\n");
+        assert!(
+            err.contains("chr1:10 follows chr1:20; input is not sorted by POS"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn chrom_split_into_two_blocks_is_rejected() {
+        let err = index_err(
+            "chrom",
+            "chr1\t1\t.\tA\tG\nchr2\t1\t.\tA\tG\nchr1\t5\t.\tA\tG\n",
+        );
+        assert!(
+            err.contains("CHROM chr1 appears in more than one block"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn record_without_numeric_pos_is_rejected() {
+        let err = index_err("nopos", "chr1\tx\t.\tA\tG\n");
+        assert!(err.contains("record without a numeric POS"), "{err}");
+    }
+}
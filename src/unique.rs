@@ -1,35 +1,96 @@
+use crate::io_utils::open_input;
+use crate::line_sep::eol;
 use anyhow::{Context, Result, bail};
-use flate2::read::MultiGzDecoder;
+use std::collections::HashMap;
 use std::fs::File;
-use std::io::{BufRead, BufReader, Read, Write};
-use std::path::Path;
+use std::io::{BufRead, Write};
+
+/// How `unique` finds duplicated sample columns (`--mode`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum UniqueMode {
+    /// Cut at the first column equal to its left neighbour (trailing duplicated block)
+    #[default]
+    Adjacent,
+    /// Group identical columns wherever they are and keep the first of each group
+    Groups,
+}
+
+impl UniqueMode {
+    pub fn from_arg(value: Option<&String>) -> Self {
+        match value.map(|s| s.as_str()) {
+            Some("groups") => UniqueMode::Groups,
+            _ => UniqueMode::Adjacent,
+        }
+    }
+}
 
 /// Stream the file twice:
-/// 1) detect the earliest sample column where, for the first `same` variant lines,
-///    col[i] == col[i-1] holds (i.e., duplicated content start).
-/// 2) re-emit the VCF trimming to that column (exclusive).
+/// 1) detect the duplicated sample columns from the first `same` variant lines:
+///    - `Adjacent`: the earliest sample column where col[i] == col[i-1] holds
+///      (i.e., duplicated content start); everything from there on is dropped.
+///    - `Groups`: columns whose first `same` values are identical form a group,
+///      wherever they are; only the first column of each group is kept.
+/// 2) re-emit the VCF keeping the fixed columns and the surviving samples.
 ///
 /// Behavior:
 /// - If cut_idx == 0 (no samples at all) or cut_idx <= 10 → keep only fixed cols 1..9
-/// - If no duplication detected → keep all columns
+/// - If no duplication is detected → keep all columns
 /// - Header/meta lines (#...) are always preserved.
 /// - Works for .vcf and .vcf.gz by sniffing extension.
-pub fn run_cleanning(vcf_file: &str, same: &usize) -> Result<()> {
-    if *same == 0 {
+/// - Output goes to `out` (stdout or `--output`).
+/// - `report` (`--report`): TSV of the dropped sample columns (name, 0-based sample index,
+///   1-based VCF column); written even when nothing is dropped, saying so.
+/// - `keep_samples` (`--keep-samples`): keep exactly these samples (in input order) instead of
+///   detecting duplicates; `same` and `mode` are then unused and the file is read once.
+pub fn run_cleanning<W: Write>(
+    vcf_file: &str,
+    same: &usize,
+    mode: UniqueMode,
+    report: Option<&str>,
+    keep_samples: Option<&[String]>,
+    out: W,
+) -> Result<()> {
+    let mut out = std::io::BufWriter::new(out);
+    if *same == 0 && keep_samples.is_none() {
         // Nothing to detect; just stream through unchanged
-        let mut inp = open_maybe_gz(vcf_file)?;
-        let mut out = std::io::stdout().lock();
+        let mut inp = open_input(vcf_file).with_context(|| format!("open {vcf_file}"))?;
         std::io::copy(&mut inp, &mut out).context("streaming VCF")?;
+        out.flush()?;
+        if let Some(path) = report {
+            write_drop_report(path, &[], None)?;
+        }
         return Ok(());
     }
+    if keep_samples.is_none() && crate::io_utils::is_stdin(vcf_file) {
+        // Detection reads the input once more; a pipe cannot be rewound for that
+        bail!(
+            "--vcf -: stdin can only be read with --same 0 or --keep-samples; \
+             pass a file to detect duplicates"
+        );
+    }
 
-    // First pass: find cut index
-    let cut_idx = detect_cut_idx(vcf_file, *same)
-        .with_context(|| format!("detecting duplicated content start in {vcf_file}"))?;
+    // First pass: absolute indices of the sample columns to keep (None = keep everything);
+    // --keep-samples resolves them from the #CHROM line of the second pass instead
+    let mut kept_cols: Option<Vec<usize>> = match mode {
+        _ if keep_samples.is_some() => None,
+        UniqueMode::Adjacent => {
+            let cut_idx = detect_cut_idx(vcf_file, *same)
+                .with_context(|| format!("detecting duplicated content start in {vcf_file}"))?;
+            // 0-based: keep [0..9) fixed cols and [9..cut) samples
+            cut_idx.map(|cut| {
+                if cut <= 10 {
+                    Vec::new()
+                } else {
+                    (9..cut).collect()
+                }
+            })
+        }
+        UniqueMode::Groups => detect_duplicate_groups(vcf_file, *same)
+            .with_context(|| format!("grouping duplicated sample columns in {vcf_file}"))?,
+    };
 
-    // Second pass: emit trimmed VCF to stdout
-    let reader = BufReader::new(open_maybe_gz(vcf_file)?);
-    let mut out = std::io::BufWriter::new(std::io::stdout().lock());
+    // Second pass: emit trimmed VCF
+    let reader = open_input(vcf_file).with_context(|| format!("open {vcf_file}"))?;
 
     let mut chrom_seen = false;
     for line_res in reader.lines() {
@@ -41,21 +102,18 @@ pub fn run_cleanning(vcf_file: &str, same: &usize) -> Result<()> {
                 if fields.len() < 9 {
                     bail!("#CHROM header has fewer than 9 columns");
                 }
-                if let Some(cut) = cut_idx {
-                    // 0-based: keep [0..9) fixed cols and [9..cut) samples
-                    if cut <= 10 {
-                        writeln!(out, "{}", fields[..9].join("\t"))?;
-                    } else {
-                        let mut kept = Vec::with_capacity(9 + (cut - 9));
-                        kept.extend_from_slice(&fields[..9]);
-                        kept.extend_from_slice(&fields[9..cut]);
-                        writeln!(out, "{}", kept.join("\t"))?;
-                    }
-                } else {
-                    writeln!(out, "{line}")?;
+                if let Some(names) = keep_samples {
+                    kept_cols = Some(keep_sample_columns(&fields, names)?);
+                }
+                if let Some(path) = report {
+                    write_drop_report(path, &fields, kept_cols.as_deref())?;
+                }
+                match &kept_cols {
+                    Some(cols) => write!(out, "{}{}", select_columns(&fields, cols), eol())?,
+                    None => write!(out, "{line}{}", eol())?,
                 }
             } else {
-                writeln!(out, "{line}")?;
+                write!(out, "{line}{}", eol())?;
             }
             continue;
         }
@@ -64,49 +122,78 @@ pub fn run_cleanning(vcf_file: &str, same: &usize) -> Result<()> {
         if !chrom_seen {
             bail!("Encountered variant line before #CHROM header");
         }
-        if let Some(cut) = cut_idx {
-            if cut <= 10 {
-                // Only fixed columns
-                let mut it = line.split('\t');
-                // Collect first 9 fields
-                let mut first9 = Vec::with_capacity(9);
-                for _ in 0..9 {
-                    match it.next() {
-                        Some(v) => first9.push(v),
-                        None => bail!("Variant line has fewer than 9 fields"),
-                    }
-                }
-                writeln!(out, "{}", first9.join("\t"))?;
-            } else {
+        match &kept_cols {
+            Some(cols) => {
                 let fields: Vec<&str> = line.split('\t').collect();
                 if fields.len() < 9 {
                     bail!("Variant line has fewer than 9 fields");
                 }
-                let mut kept = Vec::with_capacity(cut);
-                kept.extend_from_slice(&fields[..9]);
-                // Be robust if line has fewer sample cols than header suggested
-                let end = cut.min(fields.len());
-                if end > 9 {
-                    kept.extend_from_slice(&fields[9..end]);
-                }
-                writeln!(out, "{}", kept.join("\t"))?;
+                write!(out, "{}{}", select_columns(&fields, cols), eol())?;
             }
-        } else {
-            writeln!(out, "{line}")?;
+            None => write!(out, "{line}{}", eol())?,
         }
     }
     out.flush()?;
     Ok(())
 }
 
-fn open_maybe_gz(path: &str) -> Result<Box<dyn Read>> {
-    if path.ends_with(".gz") {
-        let f = File::open(path).with_context(|| format!("open {path}"))?;
-        Ok(Box::new(MultiGzDecoder::new(f)))
-    } else {
-        let f = File::open(path).with_context(|| format!("open {path}"))?;
-        Ok(Box::new(f))
+/// `--report`: list the sample columns of the `#CHROM` header `fields` that are not in `kept`
+/// (None = all kept). An empty `fields` (file passed through with `--same 0`) drops nothing.
+fn write_drop_report(path: &str, fields: &[&str], kept: Option<&[usize]>) -> Result<()> {
+    let mut w = std::io::BufWriter::new(
+        File::create(path).with_context(|| format!("create report {path}"))?,
+    );
+    write!(w, "sample\tsample_index\tcolumn{}", eol())?;
+    let dropped: Vec<usize> = match kept {
+        Some(kept) => (9..fields.len()).filter(|c| !kept.contains(c)).collect(),
+        None => Vec::new(),
+    };
+    if dropped.is_empty() {
+        write!(w, "# no sample columns dropped{}", eol())?;
+    }
+    for &c in &dropped {
+        write!(w, "{}\t{}\t{}{}", fields[c], c - 9, c + 1, eol())?;
     }
+    w.flush()?;
+    elog_info!(
+        "[info] [unique] {} sample column(s) dropped; report written to {path}",
+        dropped.len()
+    );
+    Ok(())
+}
+
+/// `--keep-samples`: absolute indices of the `#CHROM` sample columns named in `names`, in input
+/// order; a name missing from the header is an error.
+fn keep_sample_columns(fields: &[&str], names: &[String]) -> Result<Vec<usize>> {
+    let missing: Vec<&str> = names
+        .iter()
+        .map(String::as_str)
+        .filter(|n| !fields[9..].contains(n))
+        .collect();
+    if !missing.is_empty() {
+        bail!(
+            "--keep-samples: not in the #CHROM header: {}",
+            missing.join(",")
+        );
+    }
+    let cols: Vec<usize> = (9..fields.len())
+        .filter(|&c| names.iter().any(|n| n == fields[c]))
+        .collect();
+    elog_info!(
+        "[info] [unique] --keep-samples: keeping {} of {} samples",
+        cols.len(),
+        fields.len() - 9
+    );
+    Ok(cols)
+}
+
+/// The 9 fixed fields plus the sample columns at `cols` (absolute indices); indices past the
+/// end of a short line are skipped so ragged lines do not fail.
+fn select_columns(fields: &[&str], cols: &[usize]) -> String {
+    let mut kept: Vec<&str> = Vec::with_capacity(9 + cols.len());
+    kept.extend_from_slice(&fields[..9]);
+    kept.extend(cols.iter().filter_map(|&c| fields.get(c).copied()));
+    kept.join("\t")
 }
 
 /// Return Some(cut_idx) where cut_idx is the 0-based column index at which duplicated
@@ -114,11 +201,11 @@ fn open_maybe_gz(path: &str) -> Result<Box<dyn Read>> {
 /// The index refers to absolute field index (including fixed 0..8), so:
 /// - samples start at index 9
 /// - we cut to keep [0..cut_idx)
+///
 /// If no duplication is detected, return None.
 fn detect_cut_idx(path: &str, same: usize) -> Result<Option<usize>> {
-    let reader = BufReader::new(open_maybe_gz(path)?);
+    let reader = open_input(path).with_context(|| format!("open {path}"))?;
 
-    let mut chrom_fields: Option<Vec<String>> = None;
     let mut sample_count: usize = 0;
 
     // For columns >= 10 (0-based >= 9+1), keep counters of matches to their immediate left
@@ -142,7 +229,6 @@ fn detect_cut_idx(path: &str, same: usize) -> Result<Option<usize>> {
                 if sample_count >= 2 {
                     counts = vec![0; sample_count - 1];
                 }
-                chrom_fields = Some(fields);
                 active = true;
             }
             continue;
@@ -196,3 +282,72 @@ fn detect_cut_idx(path: &str, same: usize) -> Result<Option<usize>> {
     }
     Ok(None)
 }
+
+/// `--mode groups`: collect each sample column's first `same` values and group identical
+/// columns, wherever they sit. Columns are compared by value (a map keyed on the values), so
+/// distinct columns never merge. Every group with more than one member is reported on stderr.
+/// Returns the absolute indices of the first column of every group (input order), or None
+/// when all columns are distinct.
+fn detect_duplicate_groups(path: &str, same: usize) -> Result<Option<Vec<usize>>> {
+    let reader = open_input(path).with_context(|| format!("open {path}"))?;
+
+    let mut names: Vec<String> = Vec::new();
+    let mut values: Vec<Vec<Option<String>>> = Vec::new();
+    let mut lines_seen: usize = 0;
+
+    for line_res in reader.lines() {
+        let line = line_res?;
+        if line.starts_with('#') {
+            if line.starts_with("#CHROM") {
+                names = line.split('\t').skip(9).map(|s| s.to_string()).collect();
+                values = vec![Vec::with_capacity(same); names.len()];
+            }
+            continue;
+        }
+        if names.is_empty() {
+            continue;
+        }
+        if lines_seen >= same {
+            break;
+        }
+        // A missing trailing field is kept as absent, so it never matches a present value
+        let mut samples = line.split('\t').skip(9);
+        for v in values.iter_mut() {
+            v.push(samples.next().map(str::to_string));
+        }
+        lines_seen += 1;
+    }
+
+    // Group by column values, keyed by the first column of each group
+    let mut first_of: HashMap<&[Option<String>], usize> = HashMap::new();
+    let mut groups: Vec<Vec<usize>> = Vec::new();
+    for (k, v) in values.iter().enumerate() {
+        match first_of.get(v.as_slice()) {
+            Some(&g) => groups[g].push(k),
+            None => {
+                first_of.insert(v, groups.len());
+                groups.push(vec![k]);
+            }
+        }
+    }
+
+    let duplicated: Vec<&Vec<usize>> = groups.iter().filter(|g| g.len() > 1).collect();
+    if duplicated.is_empty() {
+        return Ok(None);
+    }
+    for g in &duplicated {
+        let members: Vec<&str> = g.iter().map(|&k| names[k].as_str()).collect();
+        elog_info!(
+            "[info] [unique] identical columns: {} (keeping {})",
+            members.join(","),
+            members[0]
+        );
+    }
+    elog_info!(
+        "[info] [unique] {} group(s) of identical columns; keeping {} of {} samples",
+        duplicated.len(),
+        groups.len(),
+        names.len()
+    );
+    Ok(Some(groups.iter().map(|g| 9 + g[0]).collect()))
+}
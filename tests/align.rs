@@ -0,0 +1,616 @@
+mod common;
+
+use common::{Scratch, body, cols, run, run_ok, stderr};
+
+/// Alignment TSV (node, distance, position, -, path): node 5 sits on chr1, node 6 is 3 bp off chr2
+const ALN: &str = "5\t-1\t100\tx\tchr1\n6\t3\t200\tx\tchr2\n";
+/// reference.tsv (6 columns) for the same nodes
+const REF: &str = "5\t99\t103\tACGT\t4\tchr1\n6\t200\t202\tGG\t2\tchr2\n";
+const VCF_HEADER: &str =
+    "##fileformat=VCFv4.2\n#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\tFORMAT\tS1\n";
+
+/// Write the alignment/reference fixtures plus `records` as the VCF body; returns (vcf, aln, ref)
+fn fixture(s: &Scratch, records: &str) -> (String, String, String) {
+    (
+        s.write("in.vcf", &format!("{VCF_HEADER}{records}")),
+        s.write("aln.tsv", ALN),
+        s.write("ref.tsv", REF),
+    )
+}
+
+#[test]
+fn normalize_info_chrom_rewrites_listed_keys_only() {
+    let s = Scratch::new("align-info-chrom");
+    let (vcf, aln, _) = fixture(
+        &s,
+        "5\t1\t.\tA\t<BND>\t.\tPASS\tSVTYPE=BND;CHR2=1;OTHER=1\tGT\t0/1\n",
+    );
+    let out = s.path("out.vcf");
+    run_ok(&[
+        "align",
+        "-v",
+        &vcf,
+        "-a",
+        &aln,
+        "-o",
+        &out,
+        "--no-header",
+        "--normalize-info-chrom",
+        "CHR2",
+    ]);
+    let text = s.read("out.vcf");
+    let rows = body(&text);
+    assert_eq!(rows.len(), 1);
+    assert_eq!(cols(rows[0])[7], "SVTYPE=BND;CHR2=chr1;OTHER=1");
+}
+
+#[test]
+fn normalized_info_chrom_follows_chrom_case() {
+    let s = Scratch::new("align-info-chrom-case");
+    let (vcf, aln, _) = fixture(
+        &s,
+        "5\t1\t.\tA\t<BND>\t.\tPASS\tSVTYPE=BND;CHR2=1\tGT\t0/1\n",
+    );
+    let out = s.path("out.vcf");
+    run_ok(&[
+        "align",
+        "-v",
+        &vcf,
+        "-a",
+        &aln,
+        "-o",
+        &out,
+        "--no-header",
+        "--normalize-info-chrom",
+        "CHR2",
+        "--chrom-case",
+        "upper",
+    ]);
+    let text = s.read("out.vcf");
+    let row = cols(body(&text)[0]);
+    assert_eq!(row[0], "CHR1");
+    assert_eq!(row[7], "SVTYPE=BND;CHR2=CHR1");
+}
+
+#[test]
+fn keep_gt_only_strips_other_format_subfields() {
+    let s = Scratch::new("align-keep-gt");
+    let (vcf, aln, reference) = fixture(
+        &s,
+        "5\t1\t.\tA\tG\t.\tPASS\t.\tGT:AD:PL\t0/1:3,4:0,1,2\n6\t1\t.\tG\tT\t.\tPASS\t.\tAD:GT\t0,5:1/1\n",
+    );
+    let out = s.path("out.vcf");
+    run_ok(&[
+        "align",
+        "-v",
+        &vcf,
+        "-a",
+        &aln,
+        "-r",
+        &reference,
+        "-o",
+        &out,
+        "--keep-gt-only",
+    ]);
+    let text = s.read("out.headed.vcf");
+    let rows = body(&text);
+    assert_eq!(rows.len(), 2);
+    for (row, gt) in rows.iter().zip(["0/1", "1/1"]) {
+        assert_eq!(cols(row)[8..], ["GT", gt]);
+    }
+    let format_lines: Vec<&str> = text.lines().filter(|l| l.starts_with("##FORMAT")).collect();
+    assert_eq!(format_lines.len(), 1, "{text}");
+    assert!(format_lines[0].starts_with("##FORMAT=<ID=GT,"));
+}
+
+#[test]
+fn output_dir_keeps_default_names() {
+    let s = Scratch::new("align-output-dir");
+    let (vcf, aln, reference) = fixture(&s, "5\t1\t.\tA\tG\t.\tPASS\t.\tGT\t0/1\n");
+    let dir = s.path("out");
+    std::fs::create_dir(&dir).unwrap();
+
+    run_ok(&[
+        "align",
+        "-v",
+        &vcf,
+        "-a",
+        &aln,
+        "--no-header",
+        "--output-dir",
+        &dir,
+    ]);
+    assert!(s.exists("out/in.replaced.vcf"));
+
+    run_ok(&[
+        "align",
+        "-v",
+        &vcf,
+        "-a",
+        &aln,
+        "-r",
+        &reference,
+        "--output-dir",
+        &dir,
+    ]);
+    assert!(s.exists("out/in.replaced.headed.vcf"));
+    assert!(!s.exists("in.replaced.vcf") && !s.exists("in.replaced.headed.vcf"));
+}
+
+#[test]
+fn require_map_coverage_fails_on_a_poor_map() {
+    let s = Scratch::new("align-coverage");
+    // Node 9 is in neither table: 1 of 2 records mapped
+    let (vcf, aln, _) = fixture(
+        &s,
+        "5\t1\t.\tA\tG\t.\tPASS\t.\tGT\t0/1\n9\t1\t.\tA\tG\t.\tPASS\t.\tGT\t0/1\n",
+    );
+    let out = s.path("out.vcf");
+    let o = run(&[
+        "align",
+        "-v",
+        &vcf,
+        "-a",
+        &aln,
+        "-o",
+        &out,
+        "--no-header",
+        "--require-map-coverage",
+        "0.9",
+    ]);
+    assert!(!o.status.success());
+    assert!(
+        stderr(&o).contains("Map coverage 0.5000 (1/2)"),
+        "{}",
+        stderr(&o)
+    );
+
+    run_ok(&[
+        "align",
+        "-v",
+        &vcf,
+        "-a",
+        &aln,
+        "-o",
+        &out,
+        "--no-header",
+        "--require-map-coverage",
+        "0.5",
+    ]);
+}
+
+#[test]
+fn tsv_comments_are_skipped_anywhere() {
+    let s = Scratch::new("align-tsv-comments");
+    let (vcf, _, _) = fixture(
+        &s,
+        "5\t1\t.\tA\tG\t.\tPASS\t.\tGT\t0/1\n6\t1\t.\tG\tT\t.\tPASS\t.\tGT\t1/1\n",
+    );
+    let aln = s.write(
+        "aln.tsv",
+        "# generated\n5\t-1\t100\tx\tchr1\n# node 6 below\n6\t3\t200\tx\tchr2\n",
+    );
+    let reference = s.write(
+        "ref.tsv",
+        "#node\tstart\tend\tseq\tlength\tpath\n5\t99\t103\tACGT\t4\tchr1\n#mid\n6\t200\t202\tGG\t2\tchr2\n",
+    );
+    let out = s.path("out.vcf");
+    let o = run_ok(&[
+        "align",
+        "-v",
+        &vcf,
+        "-a",
+        &aln,
+        "-r",
+        &reference,
+        "-o",
+        &out,
+        "--no-header",
+    ]);
+    assert!(
+        stderr(&o).contains("replaced=2 unmapped=0"),
+        "{}",
+        stderr(&o)
+    );
+
+    // A custom prefix is honoured the same way
+    let aln = s.write(
+        "aln.tsv",
+        ";c\n5\t-1\t100\tx\tchr1\n;c\n6\t3\t200\tx\tchr2\n",
+    );
+    run_ok(&[
+        "align",
+        "-v",
+        &vcf,
+        "-a",
+        &aln,
+        "-o",
+        &out,
+        "--no-header",
+        "--tsv-comment-prefix",
+        ";",
+    ]);
+    let text = s.read("out.vcf");
+    let chroms: Vec<&str> = body(&text).iter().map(|l| cols(l)[0]).collect();
+    assert_eq!(chroms, ["chr1", "chr2"]);
+}
+
+#[test]
+fn line_sep_crlf_applies_to_plain_and_gz_output() {
+    let s = Scratch::new("align-crlf");
+    let (vcf, aln, reference) = fixture(
+        &s,
+        "5\t1\t.\tA\tG\t.\tPASS\t.\tGT\t0/1\n6\t1\t.\tG\tT\t.\tPASS\t.\tGT\t1/1\n",
+    );
+    run_ok(&[
+        "--line-sep",
+        "crlf",
+        "align",
+        "-v",
+        &vcf,
+        "-a",
+        &aln,
+        "-r",
+        &reference,
+        "-o",
+        &s.path("out.vcf"),
+    ]);
+    run_ok(&[
+        "align",
+        "-v",
+        &vcf,
+        "-a",
+        &aln,
+        "-r",
+        &reference,
+        "-o",
+        &s.path("z.vcf"),
+        "--gzip",
+        "--line-sep",
+        "crlf",
+    ]);
+
+    for text in [s.read("out.headed.vcf"), s.read_gz("z.headed.vcf.gz")] {
+        assert!(text.ends_with("\r\n"));
+        let lines: Vec<&str> = text.split_inclusive('\n').collect();
+        assert!(lines.len() > 4, "{text}");
+        assert!(lines.iter().all(|l| l.ends_with("\r\n")), "{text:?}");
+    }
+}
+
+#[test]
+fn node_offset_shifts_vcf_ids_before_lookup() {
+    let s = Scratch::new("align-node-offset");
+    // 1-based VCF ids: node 6 in the VCF is graph node 5 (chr1), 7 is 6 (chr2)
+    let (vcf, aln, _) = fixture(
+        &s,
+        "6\t1\t.\tA\tG\t.\tPASS\t.\tGT\t0/1\n7\t1\t.\tG\tT\t.\tPASS\t.\tGT\t1/1\n",
+    );
+    let out = s.path("out.vcf");
+    run_ok(&[
+        "align",
+        "-v",
+        &vcf,
+        "-a",
+        &aln,
+        "-o",
+        &out,
+        "--no-header",
+        "--node-offset",
+        "1",
+    ]);
+    let text = s.read("out.vcf");
+    let chroms: Vec<&str> = body(&text).iter().map(|l| cols(l)[0]).collect();
+    assert_eq!(chroms, ["chr1", "chr2"]);
+}
+
+#[test]
+fn ref_rewrite_if_different_keeps_soft_masked_ref() {
+    let s = Scratch::new("align-ref-rewrite");
+    // reference.tsv has ACGT for node 5 and GG for node 6
+    let (vcf, aln, reference) = fixture(
+        &s,
+        "5\t1\t.\tacGT\tG\t.\tPASS\t.\tGT\t0/1\n6\t1\t.\tGA\tT\t.\tPASS\t.\tGT\t1/1\n",
+    );
+    let refs = |mode: &str| -> Vec<String> {
+        let out = s.path(&format!("{mode}.vcf"));
+        run_ok(&[
+            "align",
+            "-v",
+            &vcf,
+            "-a",
+            &aln,
+            "-r",
+            &reference,
+            "-o",
+            &out,
+            "--no-header",
+            "--ref-rewrite",
+            mode,
+        ]);
+        let text = s.read(&format!("{mode}.vcf"));
+        body(&text).iter().map(|l| cols(l)[3].to_string()).collect()
+    };
+    assert_eq!(refs("if-different"), ["acGT", "GG"]);
+    assert_eq!(refs("always"), ["ACGT", "GG"]);
+    assert_eq!(refs("never"), ["acGT", "GA"]);
+}
+
+#[test]
+fn bench_reports_throughput_without_writing() {
+    let s = Scratch::new("align-bench");
+    let (vcf, aln, reference) = fixture(&s, "5\t1\t.\tA\tG\t.\tPASS\t.\tGT\t0/1\n");
+    let o = run_ok(&["align", "-v", &vcf, "-a", &aln, "-r", &reference, "--bench"]);
+    let stdout = String::from_utf8_lossy(&o.stdout);
+    for phase in ["read", "transform"] {
+        let line = stdout
+            .lines()
+            .find(|l| l.starts_with(&format!("[bench] {phase}:")) && l.contains("lines/s"))
+            .unwrap_or_else(|| panic!("no {phase} throughput in {stdout}"));
+        // "... -> 6.3 MB/s, 142608 lines/s"
+        let rate = line.split("-> ").nth(1).unwrap();
+        let lines = rate
+            .split_once(" MB/s, ")
+            .unwrap()
+            .1
+            .split_once(" lines/s")
+            .unwrap()
+            .0;
+        assert!(lines.parse::<f64>().unwrap() > 0.0, "{line}");
+    }
+    assert!(
+        stdout.contains("[bench] both phases are single-threaded"),
+        "{stdout}"
+    );
+    let mut files: Vec<String> = std::fs::read_dir(&s.dir)
+        .unwrap()
+        .map(|e| e.unwrap().file_name().to_string_lossy().into_owned())
+        .collect();
+    files.sort();
+    assert_eq!(files, ["aln.tsv", "in.vcf", "ref.tsv"]);
+}
+
+#[test]
+fn gzip_writes_only_the_compressed_final_file() {
+    let s = Scratch::new("align-gzip");
+    let (vcf, aln, reference) = fixture(
+        &s,
+        "6\t1\t.\tG\tT\t.\tPASS\t.\tGT\t1/1\n5\t1\t.\tA\tG\t.\tPASS\t.\tGT\t0/1\n",
+    );
+    let cases: [(&str, &[&str], &str); 3] = [
+        ("headed.vcf", &["-r", &reference], "headed.headed.vcf.gz"),
+        ("plain.vcf", &["--no-header"], "plain.vcf.gz"),
+        (
+            "sorted.vcf",
+            &["--no-header", "--sort"],
+            "sorted.sorted.vcf.gz",
+        ),
+    ];
+    for (out, extra, gz) in cases {
+        let out_path = s.path(out);
+        let mut args = vec!["align", "-v", &vcf, "-a", &aln, "-o", &out_path, "--gzip"];
+        args.extend_from_slice(extra);
+        run_ok(&args);
+        let text = s.read_gz(gz);
+        assert_eq!(body(&text).len(), 2, "{gz}: {text}");
+    }
+    let leftovers: Vec<String> = std::fs::read_dir(&s.dir)
+        .unwrap()
+        .map(|e| e.unwrap().file_name().to_string_lossy().into_owned())
+        .filter(|f| (f.ends_with(".vcf") && f != "in.vcf") || f.contains(".tmp"))
+        .collect();
+    assert!(leftovers.is_empty(), "plain leftovers: {leftovers:?}");
+}
+
+#[test]
+fn original_pos_to_each_target() {
+    let s = Scratch::new("align-original-pos");
+    let (vcf, aln, reference) = fixture(&s, "5\t7\trs1\tA\tG\t.\tPASS\tDP=3\tGT\t0/1\n");
+    let run_mode = |mode: &str| -> (String, Vec<String>) {
+        let out = s.path(&format!("{mode}.vcf"));
+        run_ok(&[
+            "align",
+            "-v",
+            &vcf,
+            "-a",
+            &aln,
+            "-r",
+            &reference,
+            "-o",
+            &out,
+            "--original-pos-to",
+            mode,
+        ]);
+        let text = s.read(&format!("{mode}.headed.vcf"));
+        let row: Vec<String> = cols(body(&text)[0]).iter().map(|c| c.to_string()).collect();
+        (text, row)
+    };
+    let opos_def = "##INFO=<ID=OPOS,Number=1,Type=Integer,";
+    for (mode, id, info) in [
+        ("id", "7", "DP=3"),
+        ("info", "rs1", "DP=3;OPOS=7"),
+        ("both", "7", "DP=3;OPOS=7"),
+        ("none", "rs1", "DP=3"),
+    ] {
+        let (text, row) = run_mode(mode);
+        assert_eq!(
+            (row[1].as_str(), row[2].as_str(), row[7].as_str()),
+            ("100", id, info),
+            "{mode}"
+        );
+        assert_eq!(
+            text.contains(opos_def),
+            info.contains("OPOS"),
+            "{mode}: {text}"
+        );
+    }
+}
+
+#[test]
+fn path_name_with_space_is_rejected_or_escaped() {
+    let s = Scratch::new("align-escape");
+    let (vcf, _, _) = fixture(&s, "5\t1\t.\tA\tG\t.\tPASS\t.\tGT\t0/1\n");
+    let aln = s.write("aln.tsv", "5\t-1\t100\tx\tsample 1#chr1\n");
+    let out = s.path("out.vcf");
+    let args = [
+        "align",
+        "-v",
+        &vcf,
+        "-a",
+        &aln,
+        "-o",
+        &out,
+        "--no-header",
+        "--ignore",
+        "0",
+    ];
+    let o = run(&args);
+    assert!(!o.status.success());
+    assert!(stderr(&o).contains("sample 1#chr1"), "{}", stderr(&o));
+
+    run_ok(&[&args[..], &["--escape-special"]].concat());
+    let text = s.read("out.vcf");
+    assert_eq!(cols(body(&text)[0])[0], "sample%201#chr1");
+}
+
+#[test]
+fn node_at_start_zero_gets_pos_one() {
+    let s = Scratch::new("align-start-zero");
+    // Node 7 has an alignment row, node 8 is only in reference.tsv; both start at 0
+    let vcf = s.write(
+        "in.vcf",
+        &format!(
+            "{VCF_HEADER}7\t1\t.\tA\tG\t.\tPASS\t.\tGT\t0/1\n\
+             8\t1\t.\tA\tG\t.\tPASS\t.\tGT\t0/1\n"
+        ),
+    );
+    let aln = s.write("aln.tsv", "7\t-1\t0\tx\tchr1\n");
+    let reference = s.write("ref.tsv", "7\t0\t4\tACGT\t4\tchr1\n8\t0\t2\tGG\t2\tchr2\n");
+    let pos = |extra: &[&str]| -> Vec<String> {
+        let out = s.path("out.vcf");
+        let mut args = vec![
+            "align",
+            "-v",
+            &vcf,
+            "-a",
+            &aln,
+            "-r",
+            &reference,
+            "-o",
+            &out,
+            "--no-header",
+        ];
+        args.extend_from_slice(extra);
+        run_ok(&args);
+        body(&s.read("out.vcf"))
+            .iter()
+            .map(|l| cols(l)[1].to_string())
+            .collect()
+    };
+    // reference.tsv starts are 0-based, whatever --coord-base says about the alignment TSV
+    assert_eq!(pos(&["--pos-mode", "start"]), ["1", "1"]);
+    assert_eq!(
+        pos(&["--pos-mode", "start", "--coord-base", "0"]),
+        ["1", "1"]
+    );
+    // alignment positions are taken as given unless declared 0-based; node 8 falls back to
+    // its reference.tsv start in either case
+    assert_eq!(pos(&["--pos-mode", "position"]), ["0", "1"]);
+    assert_eq!(
+        pos(&["--pos-mode", "position", "--coord-base", "0"]),
+        ["1", "1"]
+    );
+}
+
+#[test]
+fn gfa_sequence_wins_over_reference_seq_column() {
+    let s = Scratch::new("align-gfa-ref");
+    let (vcf, aln, reference) = fixture(&s, "5\t1\t.\tN\tG\t.\tPASS\t.\tGT\t0/1\n");
+    let gfa = s.write("g.gfa", "S\t5\tTTTT\n");
+    let out = s.path("out.vcf");
+    let o = run_ok(&[
+        "align",
+        "-v",
+        &vcf,
+        "-a",
+        &aln,
+        "-r",
+        &reference,
+        "-g",
+        &gfa,
+        "-o",
+        &out,
+        "--no-header",
+    ]);
+    assert_eq!(cols(body(&s.read("out.vcf"))[0])[3], "TTTT");
+    let err = stderr(&o);
+    assert!(
+        err.contains(" ref=1 ") && err.contains(" missing_seq=0 "),
+        "{err}"
+    );
+}
+
+#[test]
+fn reference_only_node_takes_its_start_in_every_pos_mode() {
+    let s = Scratch::new("align-ref-only");
+    // Node 7 has no alignment row; reference.tsv puts it at 0-based start 41
+    let (vcf, aln, _) = fixture(&s, "7\t1\t.\tA\tG\t.\tPASS\t.\tGT\t0/1\n");
+    let reference = s.write("ref7.tsv", "7\t41\t45\tACGT\t4\tchr3\n");
+    for mode in ["sum", "position"] {
+        let out = s.path("out.vcf");
+        let o = run_ok(&[
+            "align",
+            "-v",
+            &vcf,
+            "-a",
+            &aln,
+            "-r",
+            &reference,
+            "-o",
+            &out,
+            "--no-header",
+            "--pos-mode",
+            mode,
+        ]);
+        assert_eq!(
+            cols(body(&s.read("out.vcf"))[0])[..2],
+            ["chr3", "42"],
+            "{mode}"
+        );
+        let err = stderr(&o);
+        assert!(
+            err.contains(" via_ref=1 ") && err.contains(" via_aln=0 "),
+            "{mode}: {err}"
+        );
+    }
+}
+
+#[test]
+fn use_end_takes_the_length_from_a_four_column_reference() {
+    let s = Scratch::new("align-use-end-4col");
+    let (vcf, aln, _) = fixture(&s, "5\t1\t.\tA\t<DEL>\t.\tPASS\tSVTYPE=DEL\tGT\t0/1\n");
+    let reference = s.write("ref4.tsv", "node\tstart\tend\tpath\n5\t99\t103\tchr1\n");
+    let out = s.path("out.vcf");
+    let o = run_ok(&[
+        "align",
+        "-v",
+        &vcf,
+        "-a",
+        &aln,
+        "-r",
+        &reference,
+        "-o",
+        &out,
+        "--no-header",
+        "--pos-mode",
+        "start",
+        "--use-end",
+    ]);
+    // Start 99 is POS 100; the node spans 4 bases, so its last base is POS 103
+    assert_eq!(cols(body(&s.read("out.vcf"))[0])[1], "103");
+    let err = stderr(&o);
+    assert!(
+        err.contains(" end_pos=1 ") && err.contains(" end_missing_len=0"),
+        "{err}"
+    );
+}
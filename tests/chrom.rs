@@ -0,0 +1,84 @@
+mod common;
+
+use common::{Scratch, body, cols, run_ok};
+use gfa2bin_aligner::{load_chrom_map, set_autosomes};
+
+const NAME: &str = "chr1_scaffold_chr2";
+
+#[test]
+fn align_header_and_extract_normalize_alike() {
+    let s = Scratch::new("chrom-consistent");
+
+    let gfa = s.write("g.gfa", &format!("S\t5\tACGT\nP\t{NAME}\t5+\t*\n"));
+    run_ok(&["extract", "-g", &gfa, "-o", &s.path("ref.tsv")]);
+    let extracted = s.read("ref.tsv");
+    let extract_chrom = cols(extracted.lines().nth(1).unwrap())[5].to_string();
+
+    let vcf = s.write(
+        "in.vcf",
+        "##fileformat=VCFv4.2\n#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\n5\t1\t.\tA\tG\t.\tPASS\t.\n",
+    );
+    let aln = s.write("aln.tsv", &format!("5\t-1\t1\tx\t{NAME}\n"));
+    run_ok(&[
+        "align",
+        "-v",
+        &vcf,
+        "-a",
+        &aln,
+        "-r",
+        &s.path("ref.tsv"),
+        "-o",
+        &s.path("a.vcf"),
+    ]);
+    let aligned = s.read("a.headed.vcf");
+    let align_chrom = cols(body(&aligned)[0])[0].to_string();
+
+    let raw = s.write(
+        "raw.vcf",
+        &format!("#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\n{NAME}\t1\t.\tA\tG\t.\tPASS\t.\n"),
+    );
+    run_ok(&[
+        "header",
+        "-v",
+        &raw,
+        "-r",
+        &s.path("ref.tsv"),
+        "-o",
+        &s.path("h.vcf"),
+        "--ignore",
+        "4",
+    ]);
+    let headed = s.read("h.vcf");
+    let contig = |text: &str| -> String {
+        let line = text
+            .lines()
+            .find(|l| l.starts_with("##contig=<ID="))
+            .unwrap();
+        line["##contig=<ID=".len()..]
+            .split([',', '>'])
+            .next()
+            .unwrap()
+            .to_string()
+    };
+
+    assert_eq!(extract_chrom, "chr2");
+    assert_eq!(align_chrom, "chr2");
+    assert_eq!(contig(&aligned), "chr2");
+    assert_eq!(contig(&headed), "chr2");
+}
+
+#[test]
+fn process_wide_settings_refuse_a_different_second_value() {
+    let s = Scratch::new("chrom-globals");
+    let a = s.write("a.tsv", "CM000663.2\tchr1\n");
+    let b = s.write("b.tsv", "CM000663.2\tchr7\n");
+    assert_eq!(load_chrom_map(&a).unwrap(), 1);
+    // The same table again is fine, a different one is not silently ignored
+    let again = s.write("a2.tsv", "CM000663.2\tchr1\n");
+    assert_eq!(load_chrom_map(&again).unwrap(), 1);
+    assert!(load_chrom_map(&b).is_err());
+
+    set_autosomes(19).unwrap();
+    set_autosomes(19).unwrap();
+    assert!(set_autosomes(22).is_err());
+}
@@ -0,0 +1,127 @@
+//! Helpers shared by the CLI tests: a scratch directory per test and a runner for the binary.
+#![allow(dead_code)]
+
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Output, Stdio};
+
+/// Per-test temporary directory, removed on drop
+pub struct Scratch {
+    pub dir: PathBuf,
+}
+
+impl Scratch {
+    pub fn new(name: &str) -> Self {
+        let dir = std::env::temp_dir().join(format!(
+            "gfa2bin-aligner-test-{}-{name}",
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        Scratch { dir }
+    }
+
+    /// Absolute path of `name` inside the scratch directory
+    pub fn path(&self, name: &str) -> String {
+        self.dir.join(name).to_string_lossy().into_owned()
+    }
+
+    /// Write `content` to `name` and return its path
+    pub fn write(&self, name: &str, content: &str) -> String {
+        let p = self.path(name);
+        fs::write(&p, content).unwrap();
+        p
+    }
+
+    /// Write `content` gzip-compressed to `name` and return its path
+    pub fn write_gz(&self, name: &str, content: &str) -> String {
+        use std::io::Write;
+        let p = self.path(name);
+        let mut enc = flate2::write::GzEncoder::new(
+            fs::File::create(&p).unwrap(),
+            flate2::Compression::default(),
+        );
+        enc.write_all(content.as_bytes()).unwrap();
+        enc.finish().unwrap();
+        p
+    }
+
+    pub fn read(&self, name: &str) -> String {
+        fs::read_to_string(self.dir.join(name)).unwrap_or_else(|e| panic!("reading {name}: {e}"))
+    }
+
+    /// Decompressed contents of a gzip/BGZF file
+    pub fn read_gz(&self, name: &str) -> String {
+        use std::io::Read;
+        let mut s = String::new();
+        flate2::read::MultiGzDecoder::new(fs::File::open(self.dir.join(name)).unwrap())
+            .read_to_string(&mut s)
+            .unwrap();
+        s
+    }
+
+    pub fn exists(&self, name: &str) -> bool {
+        self.dir.join(name).exists()
+    }
+}
+
+impl Drop for Scratch {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+/// Run the binary with `args`
+pub fn run(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_gfa2bin-aligner"))
+        .args(args)
+        .output()
+        .expect("running gfa2bin-aligner")
+}
+
+/// Run the binary with `args`, feeding `input` on stdin
+pub fn run_stdin(args: &[&str], input: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_gfa2bin-aligner"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("running gfa2bin-aligner");
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .unwrap();
+    child
+        .wait_with_output()
+        .expect("waiting for gfa2bin-aligner")
+}
+
+/// Run the binary and fail the test (showing stderr) unless it exits successfully
+pub fn run_ok(args: &[&str]) -> Output {
+    let out = run(args);
+    assert!(
+        out.status.success(),
+        "gfa2bin-aligner {} failed:\n{}",
+        args.join(" "),
+        String::from_utf8_lossy(&out.stderr)
+    );
+    out
+}
+
+pub fn stderr(out: &Output) -> String {
+    String::from_utf8_lossy(&out.stderr).into_owned()
+}
+
+/// Data lines of a VCF/TSV text (lines not starting with '#')
+pub fn body(text: &str) -> Vec<&str> {
+    text.lines().filter(|l| !l.starts_with('#')).collect()
+}
+
+/// Tab-separated fields of a line
+pub fn cols(line: &str) -> Vec<&str> {
+    line.split('\t').collect()
+}
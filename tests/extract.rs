@@ -0,0 +1,189 @@
+mod common;
+
+use common::{Scratch, run, run_ok, stderr};
+
+const GFA: &str = "H\tVN:Z:1.0\nS\t1\tACGT\nS\t2\tGG\nS\t3\tT\nP\tchr1\t1+,2+,3+\t*\n";
+
+#[test]
+fn gzipped_gfa_matches_plain() {
+    let s = Scratch::new("extract-gz");
+    let plain = s.write("g.gfa", GFA);
+    let gz = s.write_gz("g.gfa.gz", GFA);
+    run_ok(&["extract", "-g", &plain, "-o", &s.path("plain.tsv")]);
+    run_ok(&["extract", "-g", &gz, "-o", &s.path("gz.tsv")]);
+    let out = s.read("gz.tsv");
+    assert_eq!(out, s.read("plain.tsv"));
+    assert!(out.contains("1\t0\t4\tACGT\t4\tchr1"), "{out}");
+    assert!(out.contains("3\t6\t7\tT\t1\tchr1"), "{out}");
+    // The decompressed temp copy is cleaned up
+    assert!(!s.exists("gz.tsv.gfa.tmp"));
+}
+
+#[test]
+fn report_ignore_drops_counts_each_reason() {
+    let s = Scratch::new("extract-ignore-drops");
+    let gfa = s.write(
+        "g.gfa",
+        "S\t1\tACGT\nS\t2\tGG\nP\tchr1\t1+,2+\t*\nP\tchr1_random\t1+\t*\nP\tscaffold_3\t2+\t*\n\
+         P\tchrUn_x\t2+\t*\nP\tchr30\t2+\t*\n",
+    );
+    let out = s.path("ref.tsv");
+    let o = run_ok(&["extract", "-g", &gfa, "-o", &out, "--report-ignore-drops"]);
+    assert!(String::from_utf8_lossy(&o.stdout).contains(
+        "--ignore 4: kept 2 path(s), dropped 3 (no_chr=1, bad_token=1, suffix=0, non_standard=1)"
+    ));
+    let o = run_ok(&[
+        "extract",
+        "-g",
+        &gfa,
+        "-o",
+        &out,
+        "--report-ignore-drops",
+        "--ignore",
+        "3",
+    ]);
+    assert!(String::from_utf8_lossy(&o.stdout).contains(
+        "--ignore 3: kept 2 path(s), dropped 3 (no_chr=1, bad_token=1, suffix=1, non_standard=0)"
+    ));
+}
+
+#[test]
+fn path_name_with_space_is_rejected_or_escaped() {
+    let s = Scratch::new("extract-escape");
+    let gfa = s.write("g.gfa", "S\t1\tACGT\nP\tsample 1#chr1\t1+\t*\n");
+    let out = s.path("ref.tsv");
+    let o = run(&["extract", "-g", &gfa, "-o", &out, "--ignore", "0"]);
+    assert!(!o.status.success());
+    assert!(
+        stderr(&o).contains(r#"\"sample 1#chr1\" (character illegal in CHROM)"#),
+        "{}",
+        stderr(&o)
+    );
+
+    run_ok(&[
+        "extract",
+        "-g",
+        &gfa,
+        "-o",
+        &out,
+        "--ignore",
+        "0",
+        "--escape-special",
+    ]);
+    assert!(
+        s.read("ref.tsv")
+            .contains("1\t0\t4\tACGT\t4\tsample%201#chr1\n")
+    );
+}
+
+#[test]
+fn autosomes_19_keeps_mouse_chromosomes_only() {
+    let s = Scratch::new("extract-autosomes");
+    let gfa = s.write(
+        "g.gfa",
+        "S\t1\tACGT\nP\tchr19\t1+\t*\nP\tchr20\t1+\t*\nP\tchrX\t1+\t*\n",
+    );
+    let out = s.path("ref.tsv");
+    let paths = |extra: &[&str]| -> Vec<String> {
+        let mut args: Vec<&str> = extra.to_vec();
+        args.extend_from_slice(&["extract", "-g", &gfa, "-o", &out]);
+        run_ok(&args);
+        s.read("ref.tsv")
+            .lines()
+            .skip(1)
+            .map(|l| l.rsplit('\t').next().unwrap().to_string())
+            .collect()
+    };
+    assert_eq!(paths(&[]), ["chr19", "chr20", "chrX"]);
+    assert_eq!(paths(&["--autosomes", "19"]), ["chr19", "chrX"]);
+}
+
+#[test]
+fn coord_checks_flag_bad_w_spans_and_missing_segments() {
+    let s = Scratch::new("extract-coords");
+    // W spans: 10-17 matches 4 + 2 + 1 bp, 0-5 does not, `*` is not checked
+    let gfa = s.write(
+        "w.gfa",
+        "S\t1\tACGT\nS\t2\tGG\nS\t3\tT\nW\tHG1\t1\tchr1\t10\t17\t>1>2>3\n\
+         W\tHG2\t1\tchr1\t0\t5\t>1<2>3\nW\tHG3\t1\tchr1\t*\t*\t>1\n",
+    );
+    let out = s.path("ref.tsv");
+    // The default mode reads the spans back from the W lines, --low-mem parses them directly
+    for mode in [&[][..], &["--low-mem"][..]] {
+        let mut args = vec!["extract", "-g", &gfa, "-o", &out];
+        args.extend_from_slice(mode);
+        let o = run_ok(&args);
+        let err = stderr(&o);
+        let warns: Vec<&str> = err.lines().filter(|l| l.starts_with("[warn]")).collect();
+        assert_eq!(warns.len(), 1, "{mode:?}: {warns:?}");
+        assert!(
+            warns[0].contains("path HG2#1#chr1:0-5: W line spans 0-5 but its nodes add up to 7 bp")
+        );
+
+        args.push("--strict-coords");
+        assert!(!run(&args).status.success(), "{mode:?}");
+        assert!(!s.exists("ref.tsv"));
+    }
+
+    // Node 9 has no S line: the in-memory path sees an empty sequence
+    let gfa = s.write(
+        "p.gfa",
+        "S\t1\tACGT\nS\t2\tGG\nP\tchr1\t1+,9+,2+\t*\nP\tchr2\t1+,2+\t*\n",
+    );
+    let o = run_ok(&["extract", "-g", &gfa, "-o", &out]);
+    let err = stderr(&o);
+    assert!(
+        err.contains("[warn] path chr1: node 9 has no sequence"),
+        "{err}"
+    );
+    assert!(!err.contains("path chr2:"), "{err}");
+}
+
+#[test]
+fn low_mem_matches_the_default_mode_on_p_and_w_lines() {
+    let s = Scratch::new("extract-low-mem-parity");
+    let gfa = s.write(
+        "g.gfa",
+        "S\t1\tACGT\nS\t2\tGG\nS\t3\tT\nP\tHG0#0#chr1\t1+,2-,3+\t*\n\
+         W\tHG1\t1\tchr1\t10\t17\t>1<2>3\nW\tHG2\t2\tchr1\t0\t5\t>1>3\n",
+    );
+    run_ok(&[
+        "extract",
+        "-g",
+        &gfa,
+        "-o",
+        &s.path("default.tsv"),
+        "--ignore",
+        "0",
+    ]);
+    run_ok(&[
+        "extract",
+        "-g",
+        &gfa,
+        "-o",
+        &s.path("low.tsv"),
+        "--ignore",
+        "0",
+        "--low-mem",
+    ]);
+    let out = s.read("default.tsv");
+    assert_eq!(s.read("low.tsv"), out);
+    assert!(out.contains("\tHG1#1#chr1:10-17\n"), "{out}");
+    assert!(out.contains("\tHG2#2#chr1:0-5\n"), "{out}");
+}
+
+#[test]
+fn low_mem_warns_on_duplicate_segment_ids() {
+    let s = Scratch::new("extract-dup-segments");
+    let gfa = s.write(
+        "g.gfa",
+        "S\t1\tACGT\nS\t1\tAC\nS\t2\tGG\nP\tchr1\t1+,2+\t*\n",
+    );
+    let o = run_ok(&["extract", "-g", &gfa, "-o", &s.path("ref.tsv"), "--low-mem"]);
+    assert!(
+        stderr(&o).contains("[warn] 1 duplicate S line(s) (first: segment 1)"),
+        "{}",
+        stderr(&o)
+    );
+    assert!(s.read("ref.tsv").contains("1\t0\t2\tAC\t2\tchr1"));
+}
@@ -0,0 +1,347 @@
+mod common;
+
+use common::{Scratch, run_ok, stderr};
+
+/// 4-column reference.tsv (node, start, end, path); contig lengths come from `end`
+const REF: &str = "node\tstart\tend\tpath\n5\t99\t103\tchr1\n";
+const VCF: &str = "##fileformat=VCFv4.2\n#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\nchr1\t1\t.\tA\tG\t.\tPASS\t.\n";
+
+#[test]
+fn output_dir_keeps_default_name() {
+    let s = Scratch::new("header-output-dir");
+    let vcf = s.write("in.vcf", VCF);
+    let reference = s.write("ref.tsv", REF);
+    let dir = s.path("out");
+    std::fs::create_dir(&dir).unwrap();
+    run_ok(&["header", "-v", &vcf, "-r", &reference, "--output-dir", &dir]);
+    assert!(s.exists("out/in.headed.vcf"));
+    assert!(!s.exists("in.headed.vcf"));
+}
+
+#[test]
+fn stats_format_json_prints_one_object_on_stderr() {
+    let s = Scratch::new("header-stats-json");
+    let vcf = s.write("in.vcf", VCF);
+    let reference = s.write("ref.tsv", REF);
+    let o = run_ok(&[
+        "--stats-format",
+        "json",
+        "header",
+        "-v",
+        &vcf,
+        "-r",
+        &reference,
+        "-o",
+        &s.path("out.vcf"),
+    ]);
+    let err = stderr(&o);
+    assert!(!err.contains("[summary]"), "{err}");
+    let json: Vec<&str> = err.lines().filter(|l| l.starts_with('{')).collect();
+    assert_eq!(json.len(), 1, "{err}");
+
+    // Flat object of string/integer values: split it into key/value pairs
+    let inner = json[0]
+        .strip_prefix('{')
+        .and_then(|l| l.strip_suffix('}'))
+        .unwrap();
+    let fields: Vec<(&str, &str)> = inner
+        .split(',')
+        .map(|kv| {
+            let (k, v) = kv.split_once(':').unwrap();
+            (k.trim_matches('"'), v)
+        })
+        .collect();
+    assert_eq!(fields[0], ("command", "\"header\""));
+    let contigs = fields.iter().find(|(k, _)| *k == "contigs").unwrap().1;
+    assert_eq!(contigs.parse::<u64>().unwrap(), 1);
+}
+
+#[test]
+fn bare_chrom_header_line_is_kept() {
+    let s = Scratch::new("header-bare-chrom");
+    let vcf = s.write("in.vcf", "#CHROM\nchr1\t3\tb\tA\tG\t.\tPASS\t.\n");
+    let reference = s.write("ref.tsv", REF);
+    run_ok(&[
+        "header",
+        "-v",
+        &vcf,
+        "-r",
+        &reference,
+        "-o",
+        &s.path("out.vcf"),
+    ]);
+    let text = s.read("out.vcf");
+    let column_headers: Vec<&str> = text.lines().filter(|l| l.starts_with("#CHROM")).collect();
+    assert_eq!(column_headers, ["#CHROM"], "{text}");
+    assert!(
+        text.ends_with("#CHROM\nchr1\t3\tb\tA\tG\t.\tPASS\t.\n"),
+        "{text}"
+    );
+}
+
+#[test]
+fn chrom_case_upper_recases_body_and_contigs() {
+    let s = Scratch::new("header-chrom-case");
+    let reference = s.write("ref.tsv", REF);
+    let vcf = s.write("in.vcf", VCF);
+    let out = s.path("out.vcf");
+    run_ok(&[
+        "header",
+        "-v",
+        &vcf,
+        "-r",
+        &reference,
+        "-o",
+        &out,
+        "--chrom-case",
+        "upper",
+    ]);
+    // In place: the body comes from the spool instead of a second pass
+    run_ok(&[
+        "header",
+        "-v",
+        &vcf,
+        "-r",
+        &reference,
+        "-o",
+        &vcf,
+        "--chrom-case",
+        "upper",
+    ]);
+
+    for name in ["out.vcf", "in.vcf"] {
+        let text = s.read(name);
+        assert!(text.contains("##contig=<ID=CHR1"), "{name}: {text}");
+        assert!(
+            text.ends_with("\nCHR1\t1\t.\tA\tG\t.\tPASS\t.\n"),
+            "{name}: {text}"
+        );
+        assert!(!text.contains("chr1"), "{name}: {text}");
+    }
+}
+
+#[test]
+fn drop_untyped_info_omits_keys_with_only_empty_values() {
+    let s = Scratch::new("header-drop-untyped");
+    let vcf = s.write(
+        "in.vcf",
+        "##fileformat=VCFv4.2\n#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\n\
+         chr1\t1\t.\tA\tG\t.\tPASS\tDP=3;EMPTY=\nchr1\t2\t.\tA\tG\t.\tPASS\tEMPTY=;DP=4\n",
+    );
+    let reference = s.write("ref.tsv", REF);
+    let info_ids = |extra: &[&str]| -> Vec<String> {
+        let mut args = vec!["header", "-v", &vcf, "-r", &reference, "-o"];
+        let out = s.path("out.vcf");
+        args.push(&out);
+        args.extend_from_slice(extra);
+        let o = run_ok(&args);
+        if !extra.is_empty() {
+            assert!(
+                stderr(&o).contains("only empty values: EMPTY"),
+                "{}",
+                stderr(&o)
+            );
+        }
+        s.read("out.vcf")
+            .lines()
+            .filter_map(|l| l.strip_prefix("##INFO=<ID="))
+            .map(|l| l.split(',').next().unwrap().to_string())
+            .collect()
+    };
+    assert_eq!(info_ids(&[]), ["DP", "EMPTY"]);
+    assert_eq!(info_ids(&["--drop-untyped-info"]), ["DP"]);
+}
+
+#[test]
+fn contig_length_override_lengthens_past_reference_end() {
+    let s = Scratch::new("header-length-override");
+    let vcf = s.write("in.vcf", VCF);
+    let reference = s.write("ref.tsv", REF);
+    let lengths = s.write("len.genome", "chr1\t248956422\nchr2\t777\n");
+    let contig = |extra: &[&str]| -> String {
+        let out = s.path("out.vcf");
+        let mut args = vec!["header", "-v", &vcf, "-r", &reference, "-o", &out];
+        args.extend_from_slice(extra);
+        run_ok(&args);
+        let text = s.read("out.vcf");
+        text.lines()
+            .filter(|l| l.starts_with("##contig"))
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+    // reference.tsv ends node 5 at 103
+    assert_eq!(contig(&[]), "##contig=<ID=chr1,length=103>");
+    assert_eq!(
+        contig(&["--contig-length-override", &lengths]),
+        "##contig=<ID=chr1,length=248956422>"
+    );
+}
+
+/// Header lines starting with `prefix`, e.g. `##ALT=`
+fn meta_lines<'a>(text: &'a str, prefix: &str) -> Vec<&'a str> {
+    text.lines().filter(|l| l.starts_with(prefix)).collect()
+}
+
+#[test]
+fn format_float_in_a_later_sample_promotes_the_type() {
+    let s = Scratch::new("header-format-float");
+    let vcf = s.write(
+        "in.vcf",
+        "##fileformat=VCFv4.2\n#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\tFORMAT\tS1\tS2\tS3\n\
+         chr1\t1\t.\tA\tG\t.\tPASS\t.\tGT:DS:DP\t0/1:1:4\t1/1:1.5:7\t0/0:0:2\n",
+    );
+    let reference = s.write("ref.tsv", REF);
+    run_ok(&[
+        "header",
+        "-v",
+        &vcf,
+        "-r",
+        &reference,
+        "-o",
+        &s.path("out.vcf"),
+    ]);
+    let text = s.read("out.vcf");
+    let format = meta_lines(&text, "##FORMAT=<ID=");
+    let typ = |id: &str| -> String {
+        let line = format
+            .iter()
+            .find(|l| l.starts_with(&format!("##FORMAT=<ID={id},")))
+            .unwrap();
+        line.split("Type=")
+            .nth(1)
+            .unwrap()
+            .split(',')
+            .next()
+            .unwrap()
+            .to_string()
+    };
+    assert_eq!(typ("DS"), "Float");
+    assert_eq!(typ("DP"), "Integer");
+    assert!(
+        format
+            .iter()
+            .any(|l| l.starts_with("##FORMAT=<ID=GT,Number=1,Type=String,")),
+        "{text}"
+    );
+}
+
+#[test]
+fn symbolic_alts_get_alt_lines() {
+    let s = Scratch::new("header-alt");
+    let vcf = s.write(
+        "in.vcf",
+        "##fileformat=VCFv4.2\n#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\n\
+         chr1\t1\t.\tA\t<DEL>\t.\tPASS\t.\nchr1\t5\t.\tA\t<DUP:TANDEM>,G\t.\tPASS\t.\n\
+         chr1\t9\t.\tA\tT\t.\tPASS\t.\n",
+    );
+    let reference = s.write("ref.tsv", REF);
+    run_ok(&[
+        "header",
+        "-v",
+        &vcf,
+        "-r",
+        &reference,
+        "-o",
+        &s.path("out.vcf"),
+    ]);
+    let text = s.read("out.vcf");
+    let ids: Vec<&str> = meta_lines(&text, "##ALT=<ID=")
+        .iter()
+        .map(|l| l["##ALT=<ID=".len()..].split(',').next().unwrap())
+        .collect();
+    assert_eq!(ids, ["DEL", "DUP:TANDEM"]);
+}
+
+#[test]
+fn existing_contig_line_is_merged_with_its_attributes() {
+    let s = Scratch::new("header-contig-merge");
+    let vcf = s.write(
+        "in.vcf",
+        "##fileformat=VCFv4.2\n##contig=<ID=chr1,length=248956422,assembly=GRCh38>\n\
+         ##contig=<ID=chr2,assembly=GRCh38>\n#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\n\
+         chr1\t1\t.\tA\tG\t.\tPASS\t.\n",
+    );
+    // reference.tsv says chr1 ends at 103 and chr2 at 300: the input header wins for chr1
+    let reference = s.write(
+        "ref.tsv",
+        "node\tstart\tend\tpath\n5\t99\t103\tchr1\n6\t200\t300\tchr2\n",
+    );
+    run_ok(&[
+        "header",
+        "-v",
+        &vcf,
+        "-r",
+        &reference,
+        "-o",
+        &s.path("out.vcf"),
+    ]);
+    let text = s.read("out.vcf");
+    assert_eq!(
+        meta_lines(&text, "##contig="),
+        [
+            "##contig=<ID=chr1,length=248956422,assembly=GRCh38>",
+            "##contig=<ID=chr2,length=300,assembly=GRCh38>",
+        ]
+    );
+}
+
+#[test]
+fn semicolon_joined_filters_each_get_a_filter_line() {
+    let s = Scratch::new("header-filter");
+    let vcf = s.write(
+        "in.vcf",
+        "##fileformat=VCFv4.2\n##FILTER=<ID=q10,Description=\"Quality below 10\">\n\
+         #CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\n\
+         chr1\t1\t.\tA\tG\t.\tq10;LowQual\t.\nchr1\t5\t.\tA\tT\t.\tPASS\t.\n\
+         chr1\t9\t.\tA\tC\t.\tLowQual;s50;q10\t.\nchr1\t12\t.\tA\tC\t.\t.\t.\n",
+    );
+    let reference = s.write("ref.tsv", REF);
+    run_ok(&[
+        "header",
+        "-v",
+        &vcf,
+        "-r",
+        &reference,
+        "-o",
+        &s.path("out.vcf"),
+    ]);
+    let text = s.read("out.vcf");
+    let ids: Vec<&str> = meta_lines(&text, "##FILTER=<ID=")
+        .iter()
+        .map(|l| l["##FILTER=<ID=".len()..].split(',').next().unwrap())
+        .collect();
+    // q10 is declared already; PASS and `.` are implicit
+    assert_eq!(ids, ["q10", "LowQual", "s50"]);
+}
+
+#[test]
+fn contig_length_comes_from_extract_output() {
+    let s = Scratch::new("header-extract-ref");
+    let gfa = s.write(
+        "g.gfa",
+        "S\t1\tACGT\nS\t2\tGG\nS\t3\tT\nP\tchr1\t1+,2+,3+\t*\n",
+    );
+    run_ok(&["extract", "-g", &gfa, "-o", &s.path("ref.tsv")]);
+    // A leading comment must not be taken for the column header
+    let commented = s.write(
+        "commented.tsv",
+        &format!("# extract output\n{}", s.read("ref.tsv")),
+    );
+    let vcf = s.write("in.vcf", VCF);
+    for reference in [s.path("ref.tsv"), commented] {
+        run_ok(&[
+            "header",
+            "-v",
+            &vcf,
+            "-r",
+            &reference,
+            "-o",
+            &s.path("out.vcf"),
+        ]);
+        // The path ends at 7; its longest node is only 4 bp
+        assert_eq!(
+            meta_lines(&s.read("out.vcf"), "##contig="),
+            ["##contig=<ID=chr1,length=7>"]
+        );
+    }
+}
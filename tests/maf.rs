@@ -0,0 +1,179 @@
+mod common;
+
+use common::{Scratch, body, run_ok};
+
+const HEADER: &str =
+    "##fileformat=VCFv4.2\n#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\tFORMAT\tS1\tS2\tS3\tS4\n";
+
+#[test]
+fn gt_counts_for_a_known_site() {
+    let s = Scratch::new("maf-gt-counts");
+    let vcf = s.write(
+        "in.vcf",
+        &format!("{HEADER}chr1\t10\tv1\tA\tG\t.\tPASS\t.\tGT\t0/0\t0/1\t1|0\t./.\n"),
+    );
+    run_ok(&[
+        "maf",
+        "-v",
+        &vcf,
+        "-t",
+        "0.1",
+        "-o",
+        &s.path("out.vcf"),
+        "--gt-counts",
+        &s.path("gt.tsv"),
+    ]);
+    let report = s.read("gt.tsv");
+    let rows: Vec<&str> = report.lines().collect();
+    assert_eq!(
+        rows,
+        [
+            "chrom\tpos\tid\tc00\tc01\tc10\tc11\tdenom\tkept",
+            "chr1\t10\tv1\t1\t1\t1\t0\t3\t1"
+        ]
+    );
+}
+
+#[test]
+fn ploidy_from_contig_treats_prefixed_chry_as_haploid() {
+    let s = Scratch::new("maf-haploid");
+    let vcf = s.write(
+        "in.vcf",
+        &format!(
+            "{HEADER}GRCh38#0#chrY\t10\ty1\tA\tG\t.\tPASS\t.\tGT\t0\t1\t1\t0\n\
+             chr1\t10\ta1\tA\tG\t.\tPASS\t.\tGT\t0\t1\t1\t0\n\
+             Y\t20\ty2\tA\tG\t.\tPASS\t.\tGT\t0\t1\t.\t0\n"
+        ),
+    );
+    run_ok(&[
+        "maf",
+        "-v",
+        &vcf,
+        "-t",
+        "0.1",
+        "-o",
+        &s.path("out.vcf"),
+        "--ploidy-from-contig",
+        "--gt-counts",
+        &s.path("gt.tsv"),
+    ]);
+    let report = s.read("gt.tsv");
+    let rows: Vec<&str> = report.lines().skip(1).collect();
+    assert_eq!(
+        rows,
+        [
+            "GRCh38#0#chrY\t10\ty1\t2\t0\t0\t2\t4\t1",
+            "chr1\t10\ta1\t0\t0\t0\t0\t0\t0",
+            "Y\t20\ty2\t2\t0\t0\t1\t3\t1",
+        ]
+    );
+}
+
+#[test]
+fn output_is_identical_for_1_and_8_threads() {
+    let s = Scratch::new("maf-threads");
+    let gts = ["0/0", "0/1", "1|0", "1/1", "./.", "0|0"];
+    let mut text = HEADER.to_string();
+    for i in 0..5000usize {
+        let row: Vec<&str> = (0..4)
+            .map(|j| gts[(i * 7 + j * 3 + i / 5) % gts.len()])
+            .collect();
+        text.push_str(&format!(
+            "chr{}\t{}\tv{i}\tA\tG\t.\tPASS\t.\tGT\t{}\n",
+            1 + i % 3,
+            10 + i,
+            row.join("\t")
+        ));
+    }
+    let vcf = s.write("in.vcf", &text);
+    for t in ["1", "8"] {
+        run_ok(&[
+            "maf",
+            "-v",
+            &vcf,
+            "-t",
+            "0.2",
+            "-T",
+            t,
+            "-o",
+            &s.path(&format!("out{t}.vcf")),
+            "--gt-counts",
+            &s.path(&format!("gt{t}.tsv")),
+        ]);
+    }
+    let out = s.read("out1.vcf");
+    assert!(out.lines().filter(|l| !l.starts_with('#')).count() > 100);
+    assert_eq!(out, s.read("out8.vcf"));
+    assert_eq!(s.read("gt1.tsv"), s.read("gt8.tsv"));
+}
+
+#[test]
+fn multiallelic_sites_apply_the_band_to_every_allele() {
+    let s = Scratch::new("maf-multiallelic");
+    // a1: allele frequencies 0.25/0.25/0.5; a2: 0.375/0.125/0.5 (allele 1 is below 0.2)
+    let vcf = s.write(
+        "in.vcf",
+        &format!(
+            "{HEADER}chr1\t10\ta1\tA\tG,T\t.\tPASS\t.\tGT\t0/2\t1/2\t0/1\t2/2\n\
+             chr1\t20\ta2\tA\tG,T\t.\tPASS\t.\tGT\t0/2\t1/2\t0/0\t2/2\n"
+        ),
+    );
+    run_ok(&["maf", "-v", &vcf, "-t", "0.2", "-o", &s.path("out.vcf")]);
+    let text = s.read("out.vcf");
+    let ids: Vec<&str> = body(&text)
+        .iter()
+        .map(|l| l.split('\t').nth(2).unwrap())
+        .collect();
+    assert_eq!(ids, ["a1"]);
+}
+
+#[test]
+fn unordered_het_merges_01_and_10() {
+    let s = Scratch::new("maf-unordered-het");
+    let header = "##fileformat=VCFv4.2\n#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\tFORMAT\t\
+                  S1\tS2\tS3\tS4\tS5\tS6\tS7\tS8\tS9\tS10\n";
+    // 0/0 and 1/1 at 0.3 each; 0/1 and 1|0 at 0.2 each, 0.4 as one het class
+    let vcf = s.write(
+        "in.vcf",
+        &format!(
+            "{header}chr1\t10\th1\tA\tG\t.\tPASS\t.\tGT\t0/0\t0/0\t0/0\t0/1\t0/1\t1|0\t1|0\t1/1\t1/1\t1/1\n"
+        ),
+    );
+    let kept = |extra: &[&str]| -> usize {
+        let out = s.path("out.vcf");
+        let mut args = vec!["maf", "-v", &vcf, "-t", "0.25", "-o", &out];
+        args.extend_from_slice(extra);
+        run_ok(&args);
+        body(&s.read("out.vcf")).len()
+    };
+    assert_eq!(kept(&[]), 0);
+    assert_eq!(kept(&["--unordered-het"]), 1);
+}
+
+#[test]
+fn contig_lines_survive_chr_prefix_mismatch() {
+    let s = Scratch::new("maf-contig-prefix");
+    let contigs =
+        "##contig=<ID=1,length=100>\n##contig=<ID=chr2,length=100>\n##contig=<ID=3,length=100>\n";
+    let header = HEADER.replacen("#CHROM", &format!("{contigs}#CHROM"), 1);
+    let vcf = s.write(
+        "in.vcf",
+        &format!(
+            "{header}chr1\t10\ta\tA\tG\t.\tPASS\t.\tGT\t0/1\t0/1\t0/0\t0/0\n\
+             2\t10\tb\tA\tG\t.\tPASS\t.\tGT\t0/1\t0/1\t0/0\t0/0\n\
+             chr3\t10\tc\tA\tG\t.\tPASS\t.\tGT\t0/0\t0/0\t0/0\t0/0\n"
+        ),
+    );
+    run_ok(&["maf", "-v", &vcf, "-t", "0.1", "-o", &s.path("out.vcf")]);
+    let text = s.read("out.vcf");
+    let kept: Vec<&str> = text.lines().filter(|l| l.starts_with("##contig")).collect();
+    // chr3 has no kept record, so only its contig goes
+    assert_eq!(
+        kept,
+        [
+            "##contig=<ID=1,length=100>",
+            "##contig=<ID=chr2,length=100>"
+        ]
+    );
+    assert_eq!(body(&text).len(), 2);
+}
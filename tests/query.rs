@@ -0,0 +1,61 @@
+mod common;
+
+use common::{Scratch, body, run, run_ok};
+
+const HEADER: &str = "##fileformat=VCFv4.2\n#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\n";
+
+#[test]
+fn assume_sorted_stops_after_region_end() {
+    let s = Scratch::new("query-sorted");
+    // The last line is not UTF-8: reading it fails the run, so it doubles as a sentinel
+    let mut bytes = format!(
+        "{HEADER}chr1\t5\ta\tA\tG\t.\tPASS\t.\nchr1\t15\tb\tA\tG\t.\tPASS\t.\n\
+         chr1\t25\tc\tA\tG\t.\tPASS\t.\n"
+    )
+    .into_bytes();
+    bytes.extend_from_slice(b"chr1\t99\t\xff\xfe\tA\tG\t.\tPASS\t.\n");
+    let vcf = s.path("in.vcf");
+    std::fs::write(&vcf, bytes).unwrap();
+    let out = s.path("out.vcf");
+
+    let full = run(&["query", "-v", &vcf, "-r", "chr1:1-20", "-o", &out]);
+    assert!(
+        !full.status.success(),
+        "full scan should reach the sentinel"
+    );
+
+    let o = run_ok(&[
+        "query",
+        "-v",
+        &vcf,
+        "-r",
+        "chr1:1-20",
+        "-o",
+        &out,
+        "--assume-sorted",
+    ]);
+    assert!(
+        String::from_utf8_lossy(&o.stdout).contains("read 3 records, emitted 2 (stopped early")
+    );
+    let text = s.read("out.vcf");
+    let ids: Vec<&str> = body(&text)
+        .iter()
+        .map(|l| l.split('\t').nth(2).unwrap())
+        .collect();
+    assert_eq!(ids, ["a", "b"]);
+}
+
+#[test]
+fn reads_gzipped_input() {
+    let s = Scratch::new("query-gz");
+    let vcf = s.write_gz(
+        "in.vcf.gz",
+        &format!("{HEADER}chr1\t5\ta\tA\tG\t.\tPASS\t.\nchr2\t5\tb\tA\tG\t.\tPASS\t.\n"),
+    );
+    let out = s.path("out.vcf");
+    run_ok(&["query", "-v", &vcf, "-r", "chr2", "-o", &out]);
+    assert_eq!(
+        s.read("out.vcf"),
+        format!("{HEADER}chr2\t5\tb\tA\tG\t.\tPASS\t.\n")
+    );
+}
@@ -0,0 +1,57 @@
+mod common;
+
+use common::{Scratch, body, cols};
+use gfa2bin_aligner::{
+    PosMode, StreamOptions, read_alignment_tsv, read_reference_tsv, stream_replace_chrom_to_tmp,
+};
+
+#[test]
+fn four_and_six_column_files_agree_on_path_and_start() {
+    let s = Scratch::new("reference-columns");
+    let four = s.write(
+        "four.tsv",
+        "node\tstart\tend\tpath\n5\t99\t103\tchr1\n6\t200\t202\tchr2\n",
+    );
+    let six = s.write(
+        "six.tsv",
+        "node\tstart\tend\tseq\tlength\tpath\n5\t99\t103\tACGT\t4\tchr1\n6\t200\t202\tGG\t2\tchr2\n",
+    );
+    let four = read_reference_tsv(&four, "#").unwrap();
+    let six = read_reference_tsv(&six, "#").unwrap();
+    for r in [&four, &six] {
+        assert_eq!(r.path.get(&5).map(String::as_str), Some("chr1"));
+        assert_eq!(r.path.get(&6).map(String::as_str), Some("chr2"));
+        assert_eq!(r.start.get(&5), Some(&99));
+        assert_eq!(r.start.get(&6), Some(&200));
+        assert_eq!(r.node_len(5), Some(4));
+    }
+    // Only the 6-column layout carries sequences
+    assert!(four.seq.is_empty());
+    assert_eq!(six.seq.get(&5).map(String::as_str), Some("ACGT"));
+}
+
+#[test]
+fn second_reference_replaces_the_first() {
+    let s = Scratch::new("reference-reload");
+    let vcf = s.write(
+        "in.vcf",
+        "##fileformat=VCFv4.2\n#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\n5\t1\t.\tN\tG\t.\tPASS\t.\n",
+    );
+    let aln = read_alignment_tsv(&s.write("aln.tsv", "5\t-1\t100\tx\tchr1\n"), "#").unwrap();
+    let opts = StreamOptions {
+        pos_mode: PosMode::Start,
+        ..Default::default()
+    };
+    let refs = [
+        ("a.tsv", "5\t9\t13\tACGT\t4\tchr1\n"),
+        ("b.tsv", "5\t49\t51\tTT\t2\tchr1\n"),
+    ];
+    let mut rows = Vec::new();
+    for (name, text) in refs {
+        let reference = read_reference_tsv(&s.write(name, text), "#").unwrap();
+        let out = s.path("out.vcf");
+        stream_replace_chrom_to_tmp(&vcf, &out, &aln, &reference, None, &opts).unwrap();
+        rows.push(cols(body(&s.read("out.vcf"))[0])[..4].join("\t"));
+    }
+    assert_eq!(rows, ["chr1\t10\t1\tACGT", "chr1\t50\t1\tTT"]);
+}
@@ -0,0 +1,39 @@
+mod common;
+
+use common::{Scratch, run, run_ok, stderr};
+
+const BODY: &str =
+    "chr1\t10\tv1\tA\tG\t.\tPASS\t.\tGT\t0/1\nchr1\t20\tv2\tC\tT\t.\tPASS\t.\tGT\t1/1\n";
+
+#[test]
+fn swaps_header_onto_gzipped_body() {
+    let s = Scratch::new("reheader-swap");
+    let old = "##fileformat=VCFv4.2\n##source=old\n#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\tFORMAT\tOLD\n";
+    let new = "##fileformat=VCFv4.2\n##source=new\n##contig=<ID=chr1>\n#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\tFORMAT\tNEW\n";
+    let vcf = s.write_gz("in.vcf.gz", &format!("{old}{BODY}"));
+    let header = s.write("new.hdr", new);
+    let out = s.path("out.vcf");
+    run_ok(&["reheader", "--header", &header, "-v", &vcf, "-o", &out]);
+
+    assert_eq!(s.read("out.vcf"), format!("{new}{BODY}"));
+}
+
+#[test]
+fn sample_count_mismatch_needs_force() {
+    let s = Scratch::new("reheader-force");
+    let old = "##fileformat=VCFv4.2\n#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\tFORMAT\tS1\n";
+    let new =
+        "##fileformat=VCFv4.2\n#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\tFORMAT\tS1\tS2\n";
+    let vcf = s.write("in.vcf", &format!("{old}{BODY}"));
+    let header = s.write("new.hdr", new);
+    let out = s.path("out.vcf");
+
+    let o = run(&["reheader", "--header", &header, "-v", &vcf, "-o", &out]);
+    assert!(!o.status.success());
+    assert!(stderr(&o).contains("--force"), "{}", stderr(&o));
+
+    run_ok(&[
+        "reheader", "--header", &header, "-v", &vcf, "-o", &out, "--force",
+    ]);
+    assert_eq!(s.read("out.vcf"), format!("{new}{BODY}"));
+}
@@ -0,0 +1,57 @@
+mod common;
+
+use common::{Scratch, run, run_ok};
+
+const VCF: &str = "##fileformat=VCFv4.2\n#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\n\
+chr1\t100\t12345\tA\tG\t.\tPASS\t.\nchr2\t200\t67890\tC\tT\t.\tPASS\t.\n";
+
+#[test]
+fn id_delim_underscore_for_map_and_tokens() {
+    let s = Scratch::new("rename-delim");
+    let vcf = s.write("in.vcf", VCF);
+    let qtl = s.write(
+        "q.tsv",
+        "gene\tsnp\tp\ng1\t12345\t0.1\ng2\tchr2_200_C_T\t0.2\n",
+    );
+    run_ok(&[
+        "rename",
+        "-v",
+        &vcf,
+        "-q",
+        &qtl,
+        "--id-delim",
+        "_",
+        "--map-mode",
+        "all",
+    ]);
+    let out = s.read("q.tsv.renamed.tsv");
+    assert!(out.contains("g1\t1_100_A_G\t0.1"), "{out}");
+    assert!(out.contains("g2\t2_200_C_T\t0.2"), "{out}");
+}
+
+#[test]
+fn cached_map_is_rebuilt_when_id_delim_changes() {
+    let s = Scratch::new("rename-cache");
+    let vcf = s.write("in.vcf", VCF);
+    let qtl = s.write("q.tsv", "gene\tsnp\tp\ng1\t12345\t0.1\n");
+    run_ok(&["rename", "-v", &vcf, "-q", &qtl, "--id-delim", "_"]);
+    assert!(s.read("q.tsv.renamed.tsv").contains("g1\t1_100_A_G"));
+    assert!(
+        s.read("map.tsv")
+            .starts_with("#map id_delim=\"_\" map_mode=ids-only")
+    );
+
+    run_ok(&["rename", "-v", &vcf, "-q", &qtl, "--id-delim", ":"]);
+    let out = s.read("q.tsv.renamed.tsv");
+    assert!(out.contains("g1\t1:100:A:G"), "stale map reused: {out}");
+}
+
+#[test]
+fn empty_id_delim_is_rejected() {
+    let s = Scratch::new("rename-empty");
+    let vcf = s.write("in.vcf", VCF);
+    let qtl = s.write("q.tsv", "gene\tsnp\tp\ng1\t12345\t0.1\n");
+    let out = run(&["rename", "-v", &vcf, "-q", &qtl, "--id-delim", ""]);
+    assert!(!out.status.success());
+    assert!(common::stderr(&out).contains("--id-delim must not be empty"));
+}
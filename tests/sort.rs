@@ -0,0 +1,212 @@
+mod common;
+
+use common::{Scratch, body, cols, run_ok};
+
+const VCF: &str = "##fileformat=VCFv4.2\n#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\n\
+chr2\t5\ta\tA\tG\t.\tPASS\t.\nchr1\t30\tb\tA\tG\t.\tPASS\t.\n\
+chr10\t1\tc\tA\tG\t.\tPASS\t.\nchr1\t7\td\tA\tG\t.\tPASS\t.\n";
+
+// `sort` forces the `.sorted.vcf` suffix onto `--output`, so `-o x.vcf` writes `x.sorted.vcf`
+
+#[test]
+fn buffered_sort_threshold_picks_either_strategy_with_same_output() {
+    let s = Scratch::new("sort-threshold");
+    let vcf = s.write("in.vcf", VCF);
+    let ext = run_ok(&[
+        "sort",
+        "-v",
+        &vcf,
+        "-o",
+        &s.path("ext.vcf"),
+        "--buffered-sort-threshold",
+        "1",
+    ]);
+    let mem = run_ok(&[
+        "sort",
+        "-v",
+        &vcf,
+        "-o",
+        &s.path("mem.vcf"),
+        "--buffered-sort-threshold",
+        "1000000000",
+    ]);
+    assert!(String::from_utf8_lossy(&ext.stdout).contains("strategy: external merge sort"));
+    assert!(String::from_utf8_lossy(&mem.stdout).contains("strategy: in-memory"));
+
+    let sorted = s.read("mem.sorted.vcf");
+    assert_eq!(s.read("ext.sorted.vcf"), sorted);
+    let ids: Vec<&str> = body(&sorted)
+        .iter()
+        .map(|l| l.split('\t').nth(2).unwrap())
+        .collect();
+    assert_eq!(ids, ["d", "b", "a", "c"]);
+}
+
+#[test]
+fn output_dir_keeps_default_name() {
+    let s = Scratch::new("sort-output-dir");
+    let vcf = s.write("in.vcf", VCF);
+    let dir = s.path("out");
+    std::fs::create_dir(&dir).unwrap();
+    run_ok(&["sort", "-v", &vcf, "--output-dir", &dir]);
+    assert!(s.exists("out/in.sorted.vcf"));
+    assert!(!s.exists("in.sorted.vcf"));
+}
+
+#[test]
+fn bare_chrom_header_line_is_kept() {
+    let s = Scratch::new("sort-bare-chrom");
+    let vcf = s.write(
+        "in.vcf",
+        "#CHROM\nchr2\t5\ta\tA\tG\t.\tPASS\t.\nchr1\t3\tb\tA\tG\t.\tPASS\t.\n",
+    );
+    run_ok(&["sort", "-v", &vcf, "-o", &s.path("out.vcf")]);
+    assert_eq!(
+        s.read("out.sorted.vcf"),
+        "#CHROM\nchr1\t3\tb\tA\tG\t.\tPASS\t.\nchr2\t5\ta\tA\tG\t.\tPASS\t.\n"
+    );
+}
+
+#[test]
+fn collapse_equal_alt_merges_complementary_info() {
+    let s = Scratch::new("sort-collapse");
+    // Same site twice, split by another ALT at that position (IDs order a < b < c), and the
+    // same on a non-standard contig
+    let vcf = s.write(
+        "in.vcf",
+        "#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\n\
+         chr1\t10\tc\tA\tG\t.\tPASS\tDP=7;AF=0.5\n\
+         chr1\t10\ta\tA\tG\t.\tPASS\tDP=3;AC=1\n\
+         chr1\t10\tb\tA\tT\t.\tPASS\tDP=9\n\
+         scaffold_7\t5\tx\tC\tT\t.\tPASS\tAC=2\n\
+         scaffold_7\t5\ty\tC\tT\t.\tPASS\tAN=4\n",
+    );
+    let o = run_ok(&[
+        "sort",
+        "-v",
+        &vcf,
+        "-o",
+        &s.path("out.vcf"),
+        "--collapse-equal-alt",
+    ]);
+    assert!(String::from_utf8_lossy(&o.stdout).contains("Collapsed 2 record(s)"));
+    assert_eq!(
+        body(&s.read("out.sorted.vcf")),
+        [
+            "chr1\t10\ta\tA\tG\t.\tPASS\tDP=7;AC=1;AF=0.5",
+            "chr1\t10\tb\tA\tT\t.\tPASS\tDP=9",
+            "scaffold_7\t5\tx\tC\tT\t.\tPASS\tAC=2;AN=4",
+        ]
+    );
+}
+
+#[test]
+fn dedup_matches_duplicates_split_by_id() {
+    let s = Scratch::new("sort-dedup");
+    // a and c are duplicates, but b (another ALT) sorts between them by ID
+    let vcf = s.write(
+        "in.vcf",
+        "#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\n\
+         chr1\t10\tc\tA\tG\t50\tPASS\t.\n\
+         chr1\t10\ta\tA\tG\t20\tPASS\t.\n\
+         chr1\t10\tb\tA\tT\t.\tPASS\t.\n\
+         chr2\t10\td\tA\tG\t.\tPASS\t.\n",
+    );
+    let sorted = |extra: &[&str]| -> Vec<String> {
+        let out = s.path("out.vcf");
+        let mut args = vec!["sort", "-v", &vcf, "-o", &out, "--dedup"];
+        args.extend_from_slice(extra);
+        run_ok(&args);
+        body(&s.read("out.sorted.vcf"))
+            .iter()
+            .map(|l| cols(l)[2].to_string())
+            .collect()
+    };
+    assert_eq!(sorted(&[]), ["a", "b", "d"]);
+    assert_eq!(sorted(&["--dedup-keep", "max-qual"]), ["c", "b", "d"]);
+}
+
+#[test]
+fn tied_records_keep_input_order_across_runs() {
+    let s = Scratch::new("sort-stable");
+    // Same CHROM/POS/ID for every record: only the input order can decide
+    let mut text = String::from("#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\n");
+    let alts = ["T", "C", "G", "A"];
+    for i in 0..400 {
+        text.push_str(&format!("chr1\t10\t.\tA\t{}\t{i}\tPASS\t.\n", alts[i % 4]));
+    }
+    let vcf = s.write("in.vcf", &text);
+    run_ok(&["sort", "-v", &vcf, "-o", &s.path("a.vcf")]);
+    run_ok(&["sort", "-v", &vcf, "-o", &s.path("b.vcf")]);
+    run_ok(&[
+        "sort",
+        "-v",
+        &vcf,
+        "-o",
+        &s.path("c.vcf"),
+        "--max-mem",
+        "1000",
+    ]);
+    let first = s.read("a.sorted.vcf");
+    assert_eq!(first, text);
+    assert_eq!(s.read("b.sorted.vcf"), first);
+    assert_eq!(s.read("c.sorted.vcf"), first);
+}
+
+#[test]
+fn autosomes_19_orders_chr20_as_non_standard() {
+    let s = Scratch::new("sort-autosomes");
+    let vcf = s.write(
+        "in.vcf",
+        "#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\nchr20\t1\ta\tA\tG\t.\t.\t.\n\
+         chrX\t1\tb\tA\tG\t.\t.\t.\nchr19\t1\tc\tA\tG\t.\t.\t.\nchrM\t1\td\tA\tG\t.\t.\t.\n",
+    );
+    let order = |extra: &[&str]| -> Vec<String> {
+        let out = s.path("out.vcf");
+        let mut args = vec!["sort", "-v", &vcf, "-o", &out];
+        args.extend_from_slice(extra);
+        run_ok(&args);
+        body(&s.read("out.sorted.vcf"))
+            .iter()
+            .map(|l| l.split('\t').next().unwrap().to_string())
+            .collect()
+    };
+    assert_eq!(order(&[]), ["chr19", "chr20", "chrX", "chrM"]);
+    assert_eq!(
+        order(&["--autosomes", "19"]),
+        ["chr19", "chrX", "chrM", "chr20"]
+    );
+}
+
+#[test]
+fn tabix_writes_bgzf_of_the_sorted_output_and_an_index() {
+    use std::io::Read;
+
+    let s = Scratch::new("sort-tabix");
+    let vcf = s.write("in.vcf", VCF);
+    run_ok(&["sort", "-v", &vcf, "-o", &s.path("plain.vcf")]);
+    run_ok(&["sort", "-v", &vcf, "-o", &s.path("t.vcf"), "--tabix"]);
+    assert_eq!(s.read_gz("t.sorted.vcf.gz"), s.read("plain.sorted.vcf"));
+    assert!(!s.exists("t.sorted.vcf"));
+
+    // Both files end with the empty BGZF block that marks EOF
+    let eof: [u8; 28] = [
+        0x1f, 0x8b, 0x08, 0x04, 0x00, 0x00, 0x00, 0x00, 0x00, 0xff, 0x06, 0x00, 0x42, 0x43, 0x02,
+        0x00, 0x1b, 0x00, 0x03, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    ];
+    let gz = std::fs::read(s.path("t.sorted.vcf.gz")).unwrap();
+    let tbi = std::fs::read(s.path("t.sorted.vcf.gz.tbi")).unwrap();
+    assert!(gz.ends_with(&eof));
+    assert!(tbi.ends_with(&eof));
+
+    let mut idx = Vec::new();
+    flate2::read::MultiGzDecoder::new(&tbi[..])
+        .read_to_end(&mut idx)
+        .unwrap();
+    assert_eq!(&idx[..4], b"TBI\x01");
+    // n_ref, then the VCF preset
+    assert_eq!(i32::from_le_bytes(idx[4..8].try_into().unwrap()), 3);
+    assert_eq!(i32::from_le_bytes(idx[8..12].try_into().unwrap()), 2);
+    let names_len = i32::from_le_bytes(idx[32..36].try_into().unwrap()) as usize;
+    assert_eq!(&idx[36..36 + names_len], b"chr1\0chr2\0chr10\0");
+}
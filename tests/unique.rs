@@ -0,0 +1,147 @@
+mod common;
+
+use common::{Scratch, run, run_ok, run_stdin, stderr};
+
+const VCF: &str = "##fileformat=VCFv4.2\n#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\tFORMAT\tA\tB\tB2\n\
+     chr1\t1\t.\tA\tG\t.\tPASS\t.\tGT\t0/0\t0/1\t0/1\n\
+     chr1\t2\t.\tA\tG\t.\tPASS\t.\tGT\t1/1\t0/0\t0/0\n";
+
+#[test]
+fn stdin_is_rejected_when_detection_needs_two_passes() {
+    let o = run_stdin(&["unique", "-v", "-", "--same", "2"], VCF);
+    assert!(!o.status.success());
+    assert!(
+        stderr(&o).contains("--same 0 or --keep-samples"),
+        "{}",
+        stderr(&o)
+    );
+    // A single pass is fine from stdin
+    let o = run_stdin(&["unique", "-v", "-", "--keep-samples", "A"], VCF);
+    assert!(o.status.success(), "{}", stderr(&o));
+    let text = String::from_utf8_lossy(&o.stdout);
+    assert!(
+        text.contains("#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\tFORMAT\tA\n"),
+        "{text}"
+    );
+}
+
+#[test]
+fn adjacent_mode_cuts_the_trailing_duplicate() {
+    let s = Scratch::new("unique-adjacent");
+    let vcf = s.write("in.vcf", VCF);
+    run_ok(&[
+        "unique",
+        "-v",
+        &vcf,
+        "--same",
+        "2",
+        "-o",
+        &s.path("out.vcf"),
+    ]);
+    let text = s.read("out.vcf");
+    assert!(text.contains("FORMAT\tA\tB\n"), "{text}");
+    assert!(
+        text.contains("chr1\t2\t.\tA\tG\t.\tPASS\t.\tGT\t1/1\t0/0\n"),
+        "{text}"
+    );
+}
+
+#[test]
+fn groups_mode_keeps_one_column_per_group_wherever_it_sits() {
+    let s = Scratch::new("unique-groups");
+    // A2 repeats A and B2 repeats B, scattered; C only matches A on the first line
+    let vcf = s.write(
+        "in.vcf",
+        "#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\tFORMAT\tA\tB\tA2\tC\tB2\n\
+         chr1\t1\t.\tA\tG\t.\tPASS\t.\tGT\t0/1\t1/1\t0/1\t0/1\t1/1\n\
+         chr1\t2\t.\tA\tG\t.\tPASS\t.\tGT\t0/0\t0/1\t0/0\t1/1\t0/1\n",
+    );
+    let o = run_ok(&[
+        "unique",
+        "-v",
+        &vcf,
+        "--same",
+        "2",
+        "--mode",
+        "groups",
+        "-o",
+        &s.path("out.vcf"),
+    ]);
+    let text = s.read("out.vcf");
+    assert!(text.contains("FORMAT\tA\tB\tC\n"), "{text}");
+    assert!(text.contains("GT\t0/0\t0/1\t1/1\n"), "{text}");
+    let err = stderr(&o);
+    assert!(err.contains("identical columns: A,A2 (keeping A)"), "{err}");
+    assert!(err.contains("identical columns: B,B2 (keeping B)"), "{err}");
+}
+
+#[test]
+fn report_lists_dropped_columns_or_says_none() {
+    let s = Scratch::new("unique-report");
+    let vcf = s.write("in.vcf", VCF);
+    let report = s.path("report.tsv");
+    run_ok(&[
+        "unique",
+        "-v",
+        &vcf,
+        "--same",
+        "2",
+        "-o",
+        &s.path("out.vcf"),
+        "--report",
+        &report,
+    ]);
+    assert_eq!(
+        s.read("report.tsv"),
+        "sample\tsample_index\tcolumn\nB2\t2\t12\n"
+    );
+    // Nothing to drop: the report still exists and says so
+    run_ok(&[
+        "unique",
+        "-v",
+        &vcf,
+        "--same",
+        "0",
+        "-o",
+        &s.path("out.vcf"),
+        "--report",
+        &report,
+    ]);
+    assert_eq!(
+        s.read("report.tsv"),
+        "sample\tsample_index\tcolumn\n# no sample columns dropped\n"
+    );
+}
+
+#[test]
+fn keep_samples_keeps_named_columns_in_input_order() {
+    let s = Scratch::new("unique-keep");
+    let vcf = s.write("in.vcf", VCF);
+    run_ok(&[
+        "unique",
+        "-v",
+        &vcf,
+        "--keep-samples",
+        "B2,A",
+        "-o",
+        &s.path("out.vcf"),
+    ]);
+    let text = s.read("out.vcf");
+    assert!(text.contains("FORMAT\tA\tB2\n"), "{text}");
+    assert!(text.contains("GT\t1/1\t0/0\n"), "{text}");
+    let o = run(&[
+        "unique",
+        "-v",
+        &vcf,
+        "--keep-samples",
+        "A,Z",
+        "-o",
+        &s.path("out.vcf"),
+    ]);
+    assert!(!o.status.success());
+    assert!(
+        stderr(&o).contains("not in the #CHROM header: Z"),
+        "{}",
+        stderr(&o)
+    );
+}